@@ -1,4 +1,10 @@
-import { selectBlockWithBBox, deselectAll } from './patternHandle';
+import {
+  selectBlockWithBBox,
+  selectEntitiesWithBBox,
+  selectEntityWithPoint,
+  getPointThreshold,
+  deselectAll,
+} from './patternHandle';
 import { enableSelection, disableSelection } from './fabricHandle';
 import { ACTION_TYPES, CURRENT_ACTION, setCurrentAction } from './actionTypes';
 
@@ -12,6 +18,14 @@ export function setupSelectBlockAction() {
   console.log('Select Block');
 }
 
+export function setupSelectEntityAction() {
+  const currentAction = document.getElementById('current-action') as HTMLInputElement;
+  currentAction.value = 'Select Entity';
+  setCurrentAction(ACTION_TYPES.SELECT_ENTITY);
+  enableSelection();
+  console.log('Select Entity');
+}
+
 export function setupPanAction() {
   const currentAction = document.getElementById('current-action') as HTMLInputElement;
   currentAction.value = 'Pan';
@@ -30,5 +44,18 @@ export function performAction(point: [number, number], mouseDown: boolean) {
     } else if (POINT_DOWN != null) {
       selectBlockWithBBox(POINT_DOWN, point);
     }
+  } else if (CURRENT_ACTION === ACTION_TYPES.SELECT_ENTITY) {
+    if (mouseDown) {
+      POINT_DOWN = point;
+      deselectAll();
+    } else if (POINT_DOWN != null) {
+      // Releasing where the mouse went down is a click on a single entity
+      const distance = Math.hypot(point[0] - POINT_DOWN[0], point[1] - POINT_DOWN[1]);
+      if (distance <= getPointThreshold()) {
+        selectEntityWithPoint(point);
+      } else {
+        selectEntitiesWithBBox(POINT_DOWN, point);
+      }
+    }
   }
 }
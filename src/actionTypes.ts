@@ -1,6 +1,7 @@
 export enum ACTION_TYPES {
   PAN,
   SELECT_BLOCK,
+  SELECT_ENTITY,
 }
 
 export let CURRENT_ACTION = ACTION_TYPES.PAN;
@@ -37,6 +37,45 @@ export function mapBuffersToDevice(vertexArray: Float32Array, indexArray: Uint32
   nrIndices = indexArray.length;
 }
 
+// Upload only the byte ranges written by the last draw, ranges are flattened start, end pairs
+export function updateBufferRanges(
+  vertexArray: Float32Array,
+  indexArray: Uint32Array,
+  vertexRanges: Uint32Array,
+  indexRanges: Uint32Array
+) {
+  if (
+    currentDevice === undefined ||
+    verticesBuffer === undefined ||
+    indexBuffer === undefined ||
+    verticesBuffer.size !== vertexArray.byteLength ||
+    indexBuffer.size !== indexArray.byteLength
+  ) {
+    // Buffers have to be created again when their size changes
+    mapBuffersToDevice(vertexArray, indexArray);
+    return;
+  }
+
+  for (let i = 0; i + 1 < vertexRanges.length; i += 2) {
+    currentDevice.queue.writeBuffer(
+      verticesBuffer,
+      vertexRanges[i],
+      vertexArray.buffer,
+      vertexArray.byteOffset + vertexRanges[i],
+      vertexRanges[i + 1] - vertexRanges[i]
+    );
+  }
+  for (let i = 0; i + 1 < indexRanges.length; i += 2) {
+    currentDevice.queue.writeBuffer(
+      indexBuffer,
+      indexRanges[i],
+      indexArray.buffer,
+      indexArray.byteOffset + indexRanges[i],
+      indexRanges[i + 1] - indexRanges[i]
+    );
+  }
+}
+
 export function getBuffers(): [GPUBuffer, GPUBuffer, number] | undefined {
   if (verticesBuffer === undefined || indexBuffer === undefined || nrIndices == undefined) {
     console.error('Requested buffer not defined yet');
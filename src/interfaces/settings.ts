@@ -7,7 +7,9 @@ export interface ISettings {
   layer_colors: { [layer: number]: string }; // Layer id to hex color
   point_threshold: number; // threshold in model space to select an individual point
   cross_size: number; // How big cross is to denote point entities
+  chord_tolerance: number; // Largest gap between drawn chords and arcs or circles
   view: string; // Could be made into an enum, current Model and every block
+  viewport: [[number, number], [number, number]] | null; // ((min_x, max_x), (min_y, max_y)) drawn, null draws everything
   highlight_offset: [number, number]; // offset of current selection
   highlight_scale: [number, number]; // scale of current selection
   highlight_flip: [boolean, boolean]; // flip status of current selection
@@ -16,4 +18,8 @@ export interface ISettings {
   highlight_rotation_angle: 0; // Rotation angle in radians
   highlight_nr_selected_entities: 0; // Number of objects selected in highlight
   highlight_width_height: [number, number]; // Current height and width of bounding box
+  seam_allowance_layer: number; // Layer seam allowance outlines are added on
+  grade_overlay: boolean; // Draw every graded size over the active one
+  grade_overlay_color: [number, number, number, number]; // Range 0-255
+  overlay_color: [number, number, number, number]; // Temporary drawings such as measurements, range 0-255
 }
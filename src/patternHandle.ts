@@ -1,7 +1,7 @@
 import init, { greet, Handle, InitOutput } from '../wasm-model/pkg/cad_pattern_editor.js';
 import { getInitialSettingsPayload, colorMap, getNextColor } from './settings';
 import { updateAvailableLayers, updateAvailableBlocks, updateSelection } from './setupGUIOptions';
-import { mapBuffersToDevice } from './buffers';
+import { updateBufferRanges } from './buffers';
 import { addHighlightBbox, getDxfWorldCoorindates } from './rendering';
 import { updateBottomBarDisplay } from './displaySelection';
 
@@ -57,7 +57,7 @@ const updateCanvasData = (handle: Handle | undefined) => {
     handle.get_index_buffer_ptr(),
     handle.get_index_buffer_len()
   );
-  mapBuffersToDevice(vertexView, indexView);
+  updateBufferRanges(vertexView, indexView, handle.get_changed_vertex_ranges(), handle.get_changed_index_ranges());
   updateBottomBarDisplay(handle.get_settings());
 };
 
@@ -236,6 +236,32 @@ export function setNewHighlightRect(rect: fabric.Rect) {
   }
 }
 
+const addHighlightRectCallbacks = (bbox: [[number, number], [number, number]]) => {
+  setRectWorldCoords(bbox);
+  addHighlightBbox(bbox);
+
+  if (HIGHLIGHT_RECT) {
+    setRectOriginalWoordCoord(getDxfWorldCoorindates(HIGHLIGHT_RECT.left, HIGHLIGHT_RECT.top));
+
+    HIGHLIGHT_RECT.on('moving', function (e) {
+      if (e.transform) updateOffsetDisplay(e.transform.target as fabric.Rect);
+    });
+    HIGHLIGHT_RECT.on('scaling', function (e) {
+      if (e.transform) {
+        setRectIsScaling(true);
+        updateScalingAnchor(e.transform.corner);
+        updateScalingDisplay(e.transform.target as fabric.Rect);
+      }
+    });
+    HIGHLIGHT_RECT.on('rotating', function (e) {
+      if (e.transform) updateRotatingDisplay(e.transform.target as fabric.Rect);
+    });
+    HIGHLIGHT_RECT.on('modified', function (e) {
+      if (e.transform) setNewHighlightRect(e.transform.target as fabric.Rect);
+    });
+  }
+};
+
 export function selectBlockWithBBox(p1: [number, number], p2: [number, number]) {
   if (PATTERN_WASM_HANDLE === undefined) return;
   const [blockKeys, bbox] = PATTERN_WASM_HANDLE.select_block_with_two_points(
@@ -244,32 +270,34 @@ export function selectBlockWithBBox(p1: [number, number], p2: [number, number])
   );
   updateCanvasData(PATTERN_WASM_HANDLE);
 
-  if (bbox) {
-    setRectWorldCoords(bbox);
-    addHighlightBbox(bbox);
-
-    if (HIGHLIGHT_RECT) {
-      setRectOriginalWoordCoord(getDxfWorldCoorindates(HIGHLIGHT_RECT.left, HIGHLIGHT_RECT.top));
-
-      HIGHLIGHT_RECT.on('moving', function (e) {
-        if (e.transform) updateOffsetDisplay(e.transform.target as fabric.Rect);
-      });
-      HIGHLIGHT_RECT.on('scaling', function (e) {
-        if (e.transform) {
-          setRectIsScaling(true);
-          updateScalingAnchor(e.transform.corner);
-          updateScalingDisplay(e.transform.target as fabric.Rect);
-        }
-      });
-      HIGHLIGHT_RECT.on('rotating', function (e) {
-        if (e.transform) updateRotatingDisplay(e.transform.target as fabric.Rect);
-      });
-      HIGHLIGHT_RECT.on('modified', function (e) {
-        if (e.transform) setNewHighlightRect(e.transform.target as fabric.Rect);
-      });
-    }
-  }
+  if (bbox) addHighlightRectCallbacks(bbox);
 
   updateSelection(blockKeys);
   addChangeSelectionCallbacks(PATTERN_WASM_HANDLE, blockKeys);
 }
+
+// Threshold in model space within which a click picks an entity
+export function getPointThreshold(): number {
+  if (PATTERN_WASM_HANDLE === undefined) return 0;
+  return PATTERN_WASM_HANDLE.get_settings().point_threshold;
+}
+
+export function selectEntityWithPoint(point: [number, number]) {
+  if (PATTERN_WASM_HANDLE === undefined) return;
+  const entityIndexes = PATTERN_WASM_HANDLE.select_entity_with_point(new Float32Array(point));
+  updateCanvasData(PATTERN_WASM_HANDLE);
+  console.log(entityIndexes.length, 'entities selected');
+}
+
+// Dragging right selects entities inside the box, dragging left selects entities crossing it
+export function selectEntitiesWithBBox(p1: [number, number], p2: [number, number]) {
+  if (PATTERN_WASM_HANDLE === undefined) return;
+  const [entityIndexes, bbox] = PATTERN_WASM_HANDLE.select_entities_with_two_points(
+    new Float32Array(p1),
+    new Float32Array(p2)
+  );
+  updateCanvasData(PATTERN_WASM_HANDLE);
+
+  if (bbox) addHighlightRectCallbacks(bbox);
+  console.log(entityIndexes.length, 'entities selected');
+}
@@ -8,7 +8,9 @@ let INITIAL_SETTINGS: ISettings = {
   layer_colors: {},
   point_threshold: 4,
   cross_size: 0.3,
+  chord_tolerance: 0.05,
   view: 'Model',
+  viewport: null,
   highlight_offset: [0, 0],
   highlight_scale: [1, 1],
   highlight_flip: [false, false],
@@ -17,6 +19,10 @@ let INITIAL_SETTINGS: ISettings = {
   highlight_rotation_angle: 0, // In radians
   highlight_nr_selected_entities: 0,
   highlight_width_height: [0, 0],
+  seam_allowance_layer: 11, // Cut
+  grade_overlay: false,
+  grade_overlay_color: [255, 152, 0, 255],
+  overlay_color: [233, 30, 99, 255],
 };
 
 export const LAYER_TO_NAME: { [layer: number]: string } = {
@@ -0,0 +1,240 @@
+// Compares selection queries on a large pattern against a linear scan over every block
+// Run with `cargo bench --bench spatial_index`
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use cad_pattern_editor::block::Block;
+use cad_pattern_editor::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, ParsePattern, Vertex,
+};
+use cad_pattern_editor::pattern::Pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::bounding_box;
+
+const COLUMNS: usize = 100;
+const ROWS: usize = 100;
+const QUERIES: usize = 1000;
+
+// Grid of blocks each holding a closed outline and a few points
+fn large_pattern() -> Pattern {
+    let mut blocks = BTreeMap::new();
+    let mut inserts = vec![];
+    let mut entity_counter = 0;
+    let mut next_index = || {
+        entity_counter += 1;
+        format!("{:032x}", entity_counter)
+    };
+
+    for i in 0..COLUMNS * ROWS {
+        let name = format!("B-{:05}", i);
+        let corners = [(0., 0.), (8., 0.), (8., 8.), (0., 8.)];
+        let mut entities = vec![ParseEntity {
+            entity_type: "LWPOLYLINE".to_string(),
+            layer: "1".to_string(),
+            entity_index: next_index(),
+            shape: Some(true),
+            vertices: Some(
+                corners
+                    .iter()
+                    .map(|(x, y)| Vertex {
+                        x: *x,
+                        y: *y,
+                        bulge: None,
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }];
+        for (x, y) in corners.iter() {
+            entities.push(ParseEntity {
+                entity_type: "POINT".to_string(),
+                layer: "2".to_string(),
+                entity_index: next_index(),
+                position: Some(Vertex {
+                    x: *x,
+                    y: *y,
+                    bulge: None,
+                }),
+                ..Default::default()
+            });
+        }
+        blocks.insert(
+            name.clone(),
+            ParseBlock {
+                entities,
+                centroid: Vertex {
+                    x: 4.,
+                    y: 4.,
+                    bulge: None,
+                },
+                layer: "1".to_string(),
+                inserts: vec![],
+            },
+        );
+        inserts.push(ParseInsertEntity {
+            entity_type: "INSERT".to_string(),
+            name,
+            position: Vertex {
+                x: (i % COLUMNS) as f32 * 10.,
+                y: (i / COLUMNS) as f32 * 10.,
+                bulge: None,
+            },
+            layer: "1".to_string(),
+            ..Default::default()
+        });
+    }
+
+    let document = ParseDocument {
+        pattern_json: ParsePattern {
+            blocks,
+            entities: inserts,
+            ..Default::default()
+        },
+    };
+    Pattern::from_document(&document)
+}
+
+// Query points spread over the pattern, deterministic so runs are comparable
+fn query_points() -> Vec<(f32, f32)> {
+    let extent = (COLUMNS * 10) as f32;
+    (0..QUERIES)
+        .map(|i| {
+            let t = i as f32 * 0.618034;
+            ((t * 97.) % extent, (t * 61.) % extent)
+        })
+        .collect()
+}
+
+// Blocks by name, built once so the linear scans only pay for the scan itself
+fn blocks_by_name(pattern: &Pattern) -> HashMap<&str, &Block> {
+    pattern
+        .get_blocks()
+        .iter()
+        .map(|b| (b.name.as_str(), b))
+        .collect()
+}
+
+// Selection as it was done before the index, measuring every entity of every placed block
+fn linear_closest(
+    pattern: &Pattern,
+    blocks: &HashMap<&str, &Block>,
+    point: &(f32, f32),
+    settings: &ISettings,
+) -> Option<String> {
+    let mut output: Option<(String, f32)> = None;
+    for insert in pattern.get_inserts().iter() {
+        let block = blocks[insert.name.as_str()];
+        let offset_point = (
+            point.0 - insert.position[(0, 0)],
+            point.1 - insert.position[(0, 1)],
+        );
+        for entity in block.get_entities().iter() {
+            if settings.disabled_layers.contains(&entity.layer)
+                || !entity.has_point_within_threshold(&offset_point, settings.point_threshold)
+            {
+                continue;
+            }
+            if let Some(closest_point) = entity.get_closest_point_on_entity(&offset_point) {
+                if output
+                    .as_ref()
+                    .is_none_or(|(_, d)| closest_point.distance < *d)
+                {
+                    output = Some((block.name.clone(), closest_point.distance));
+                }
+            }
+        }
+    }
+    output.map(|(name, _)| name)
+}
+
+// Crossing selection checking every entity of every placed block, sorted to compare with the index
+fn linear_crossing(
+    pattern: &Pattern,
+    blocks: &HashMap<&str, &Block>,
+    bbox: &((f32, f32), (f32, f32)),
+) -> Vec<String> {
+    let mut selected = vec![];
+    for insert in pattern.get_inserts().iter() {
+        let block = blocks[insert.name.as_str()];
+        let offset_bbox = bounding_box::offset_bbox(bbox, &(-&insert.position));
+        for entity in block.get_entities().iter() {
+            if entity.intersects_bbox(&offset_bbox) {
+                selected.push(entity.entity_index_as_uuid());
+            }
+        }
+    }
+    selected.sort();
+    selected
+}
+
+fn report(name: &str, linear: Duration, indexed: Duration) {
+    println!(
+        "{:<24} linear {:>10.3?}  indexed {:>10.3?}  speedup {:>7.1}x",
+        name,
+        linear,
+        indexed,
+        linear.as_secs_f64() / indexed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let start = Instant::now();
+    let pattern = large_pattern();
+    println!(
+        "Built pattern with {} blocks and {} entities in {:.3?}",
+        pattern.get_number_blocks(),
+        pattern.get_number_entities(),
+        start.elapsed()
+    );
+
+    let settings = ISettings {
+        point_threshold: 1.,
+        ..Default::default()
+    };
+    let points = query_points();
+    let blocks = blocks_by_name(&pattern);
+
+    let start = Instant::now();
+    let linear_hits: Vec<Option<String>> = points
+        .iter()
+        .map(|p| linear_closest(&pattern, &blocks, p, &settings))
+        .collect();
+    let linear = start.elapsed();
+
+    let start = Instant::now();
+    let indexed_hits: Vec<Option<String>> = points
+        .iter()
+        .map(|p| pattern.find_closest_entity(p, &settings).map(|h| h.block))
+        .collect();
+    let indexed = start.elapsed();
+
+    assert_eq!(linear_hits, indexed_hits);
+    report("closest entity", linear, indexed);
+
+    let boxes: Vec<((f32, f32), (f32, f32))> = points
+        .iter()
+        .map(|(x, y)| ((*x, x + 25.), (*y, y + 25.)))
+        .collect();
+
+    let start = Instant::now();
+    let linear_hits: Vec<Vec<String>> = boxes
+        .iter()
+        .map(|b| linear_crossing(&pattern, &blocks, b))
+        .collect();
+    let linear = start.elapsed();
+
+    let start = Instant::now();
+    let indexed_hits: Vec<Vec<String>> = boxes
+        .iter()
+        .map(|b| {
+            let mut selected = pattern.find_entities_with_bbox(b, &settings, true).0;
+            selected.sort();
+            selected
+        })
+        .collect();
+    let indexed = start.elapsed();
+
+    assert!(linear_hits.iter().any(|hits| !hits.is_empty()));
+    assert_eq!(linear_hits, indexed_hits);
+    report("crossing selection", linear, indexed);
+}
@@ -0,0 +1,24 @@
+// Batch conversion and inspection of patterns from the command line
+use std::env;
+use std::process::ExitCode;
+
+use cad_pattern_editor::cli;
+use cad_pattern_editor::utils::log;
+
+fn main() -> ExitCode {
+    log::set_logger(Box::new(|level, message| {
+        eprintln!("[{:?}] {}", level, message);
+    }));
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    match cli::run(&args) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(output) => {
+            eprintln!("{}", output);
+            ExitCode::FAILURE
+        }
+    }
+}
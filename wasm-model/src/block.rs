@@ -1,12 +1,25 @@
 use ndarray::{array, Array2};
+use std::collections::HashMap;
 
+use crate::curve::Curve;
 use crate::drawing_output::IDrawingOutput;
 use crate::drawing_parameters::IDrawingParameters;
 use crate::entity;
+use crate::insert;
 use crate::parse_pattern;
+use crate::spline::Spline;
 use crate::user_settings;
 use crate::utils::bounding_box;
 use crate::utils::color;
+use crate::utils::geometry;
+use crate::utils::spatial_index::SpatialIndex;
+use crate::utils::transform::Transform;
+
+// Largest distance between a point and the text labelling it
+pub const LABEL_TOLERANCE: f32 = 1e-3;
+
+// Position of a label in the entities, with where its point and the label are
+type LinkedPosition = (usize, (f32, f32), (f32, f32));
 
 #[derive(Debug)]
 pub struct Block {
@@ -14,10 +27,29 @@ pub struct Block {
     centroid: Array2<f32>,
     layer: i32,
     entities: Vec<entity::Entity>,
+    // Other blocks placed inside this one
+    inserts: Vec<insert::Insert>,
+    // Entity index of the label of each point, linked when the block is loaded
+    point_labels: HashMap<[u8; 32], [u8; 32]>,
     // Cached variables
     bounding_box: ((f32, f32), (f32, f32)),
+    entity_index: SpatialIndex,
     // display variables
     highlighted: bool,
+    // Set when the block has to be written to the draw buffers again
+    draw_dirty: bool,
+}
+
+// Cached and display variables are left out, they follow from the model fields
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        return self.name == other.name
+            && self.centroid == other.centroid
+            && self.layer == other.layer
+            && self.entities == other.entities
+            && self.inserts == other.inserts
+            && self.point_labels == other.point_labels;
+    }
 }
 
 impl Block {
@@ -26,10 +58,14 @@ impl Block {
         return Block {
             layer: layer,
             entities: vec![],
+            inserts: vec![],
+            point_labels: HashMap::new(),
             name: name,
             centroid: array![[center.x, center.y]],
             bounding_box: bounding_box,
+            entity_index: SpatialIndex::default(),
             highlighted: false,
+            draw_dirty: true,
         };
     }
 
@@ -48,7 +84,7 @@ impl Block {
     pub fn add_line(
         &mut self,
         layer: i32,
-        vertices: &Vec<parse_pattern::Vertex>,
+        vertices: &[parse_pattern::Vertex],
         entity_id: [u8; 32],
     ) {
         let arr = array![
@@ -81,15 +117,17 @@ impl Block {
         let num_points = vertices.len();
         let arr: Array2<f32> = Array2::from_shape_vec((num_points, 2), vertex_data).unwrap();
 
-        self.entities.push(entity::Entity::new(
-            entity::EntityTypes::LINE,
+        let mut polyline = entity::Entity::new(
+            entity::EntityTypes::LWPOLYLINE,
             layer,
             shape,
             arr,
             0.,
             entity_id,
             "".to_string(),
-        ))
+        );
+        polyline.set_bulges(vertices.iter().map(|v| v.bulge.unwrap_or(0.)).collect());
+        self.entities.push(polyline)
     }
 
     pub fn add_text(
@@ -111,10 +149,257 @@ impl Block {
         ))
     }
 
+    // Angles in degrees, the arc runs counter clockwise from start to end
+    pub fn add_arc(
+        &mut self,
+        layer: i32,
+        center: &parse_pattern::Vertex,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        entity_id: [u8; 32],
+    ) {
+        let curve = Curve::Arc {
+            center: (center.x, center.y),
+            radius: radius,
+            start_angle: start_angle.to_radians(),
+            end_angle: end_angle.to_radians(),
+        };
+        self.entities
+            .push(entity::Entity::new_curve(layer, curve, entity_id))
+    }
+
+    pub fn add_circle(
+        &mut self,
+        layer: i32,
+        center: &parse_pattern::Vertex,
+        radius: f32,
+        entity_id: [u8; 32],
+    ) {
+        let curve = Curve::Circle {
+            center: (center.x, center.y),
+            radius: radius,
+        };
+        self.entities
+            .push(entity::Entity::new_curve(layer, curve, entity_id))
+    }
+
+    // Major axis is relative to the center, start and end parameters are in radians
+    pub fn add_ellipse(
+        &mut self,
+        layer: i32,
+        center: &parse_pattern::Vertex,
+        major_axis: &parse_pattern::Vertex,
+        ratio: f32,
+        params: (f32, f32),
+        entity_id: [u8; 32],
+    ) {
+        let (start_param, end_param) = params;
+        let curve = Curve::Ellipse {
+            center: (center.x, center.y),
+            major_axis: (major_axis.x, major_axis.y),
+            ratio: ratio,
+            start_param: start_param,
+            end_param: end_param,
+        };
+        self.entities
+            .push(entity::Entity::new_curve(layer, curve, entity_id))
+    }
+
+    pub fn add_spline(&mut self, layer: i32, spline: Spline, entity_id: [u8; 32]) {
+        self.entities.push(entity::Entity::new_curve(
+            layer,
+            Curve::Spline(spline),
+            entity_id,
+        ))
+    }
+
+    // Entities made by an edit rather than read from the document
+    pub fn add_entity(&mut self, entity: entity::Entity) {
+        self.entities.push(entity);
+        self.update_bounding_box();
+    }
+
+    // Moves single vertices of entities given by their index
+    pub fn move_entity_vertices(&mut self, moves: &[([u8; 32], usize, (f32, f32))]) {
+        self.edit_keeping_labels(|entities| {
+            for (entity_index, row, offset) in moves.iter() {
+                for entity in entities.iter_mut() {
+                    if &entity.entity_index == entity_index {
+                        entity.move_vertex(*row, *offset);
+                    }
+                }
+            }
+        });
+    }
+
+    // Links each text to the point it is written on, a point with several texts keeps the closest
+    pub fn link_point_labels(&mut self) {
+        let mut links: Vec<(f32, [u8; 32], [u8; 32])> = vec![];
+        for label in self
+            .entities
+            .iter()
+            .filter(|e| e.entity_type == entity::EntityTypes::TEXT)
+        {
+            let position = match first_vertex(label) {
+                Some(position) => position,
+                None => continue,
+            };
+            let closest = self
+                .entities
+                .iter()
+                .filter(|e| e.entity_type == entity::EntityTypes::POINT)
+                .filter_map(|p| {
+                    first_vertex(p).map(|v| (geometry::distance(&v, &position), p.entity_index))
+                })
+                .filter(|(distance, _)| *distance <= LABEL_TOLERANCE)
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((distance, point_index)) = closest {
+                links.push((distance, point_index, label.entity_index));
+            }
+        }
+        links.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        self.point_labels.clear();
+        for (_, point_index, label_index) in links {
+            self.point_labels.entry(point_index).or_insert(label_index);
+        }
+    }
+
+    pub fn get_point_label(&self, point_index: &[u8; 32]) -> Option<&entity::Entity> {
+        let label_index = self.point_labels.get(point_index)?;
+        return self
+            .entities
+            .iter()
+            .find(|e| &e.entity_index == label_index);
+    }
+
+    // Points labelled with a text, in the order the labels appear in the block
+    pub fn find_labelled_points(&self, text: &str) -> Vec<[u8; 32]> {
+        let mut output: Vec<[u8; 32]> = vec![];
+        for label in self
+            .entities
+            .iter()
+            .filter(|e| e.entity_type == entity::EntityTypes::TEXT && e.text.trim() == text.trim())
+        {
+            let point = self
+                .point_labels
+                .iter()
+                .find(|(_, label_index)| **label_index == label.entity_index);
+            if let Some((point_index, _)) = point {
+                output.push(*point_index);
+            }
+        }
+        return output;
+    }
+
+    // Position in the entities of each linked label, with where its point and the label are
+    fn linked_positions(&self) -> Vec<LinkedPosition> {
+        let positions: HashMap<[u8; 32], usize> = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.entity_index, i))
+            .collect();
+        let mut links: Vec<(&[u8; 32], &[u8; 32])> = self.point_labels.iter().collect();
+        links.sort();
+        return links
+            .into_iter()
+            .filter_map(|(point_index, label_index)| {
+                let label_ind = *positions.get(label_index)?;
+                let point = first_vertex(&self.entities[*positions.get(point_index)?])?;
+                let label = first_vertex(&self.entities[label_ind])?;
+                Some((label_ind, point, label))
+            })
+            .collect();
+    }
+
+    // Runs an edit on the entities, labels follow their point unless the edit moved them as well
+    fn edit_keeping_labels(&mut self, edit: impl FnOnce(&mut Vec<entity::Entity>)) {
+        let before = self.linked_positions();
+        edit(&mut self.entities);
+        let after = self.linked_positions();
+        for ((label_ind, point_before, label_before), (_, point_after, label_after)) in
+            before.iter().zip(after.iter())
+        {
+            if point_before == point_after || label_before != label_after {
+                continue;
+            }
+            self.entities[*label_ind].offset_vertices(&array![[
+                point_after.0 - point_before.0,
+                point_after.1 - point_before.1
+            ]]);
+        }
+        self.update_bounding_box();
+    }
+
+    pub fn add_insert(&mut self, insert: insert::Insert) {
+        self.inserts.push(insert);
+    }
+
+    pub fn to_parse_block(&self) -> parse_pattern::ParseBlock {
+        let (c_x, c_y) = self.get_centroid();
+        return parse_pattern::ParseBlock {
+            entities: self.entities.iter().map(|e| e.to_parse_entity()).collect(),
+            centroid: parse_pattern::Vertex {
+                x: c_x,
+                y: c_y,
+                bulge: None,
+            },
+            layer: self.layer.to_string(),
+            inserts: self.inserts.iter().map(|i| i.to_parse_insert()).collect(),
+        };
+    }
+
     pub fn get_number_entities(&self) -> usize {
         return self.entities.len();
     }
 
+    pub fn get_centroid(&self) -> (f32, f32) {
+        return (self.centroid[(0, 0)], self.centroid[(0, 1)]);
+    }
+
+    pub fn get_layer(&self) -> i32 {
+        return self.layer;
+    }
+
+    pub fn get_entities(&self) -> &Vec<entity::Entity> {
+        return &self.entities;
+    }
+
+    pub fn get_inserts(&self) -> &Vec<insert::Insert> {
+        return &self.inserts;
+    }
+
+    pub fn get_inserts_mut(&mut self) -> &mut Vec<insert::Insert> {
+        return &mut self.inserts;
+    }
+
+    // Puts an entity back the way it was before or after an edit, None removes an entity the
+    // edit added. The current selection is kept and the bounding box is left to the caller
+    pub fn restore_entity(&mut self, entity_index: &[u8; 32], state: Option<&entity::Entity>) {
+        let position = self
+            .entities
+            .iter()
+            .position(|e| &e.entity_index == entity_index);
+        match (position, state) {
+            (Some(ind), Some(entity)) => {
+                let highlighted = self.entities[ind].highlighted;
+                self.entities[ind] = entity.clone();
+                self.entities[ind].highlighted = highlighted;
+            }
+            (Some(ind), None) => {
+                self.entities.remove(ind);
+            }
+            (None, Some(entity)) => {
+                let mut entity = entity.clone();
+                entity.highlighted = self.highlighted;
+                self.entities.push(entity);
+            }
+            (None, None) => {}
+        }
+    }
+
     fn calculate_bounding_box(&self) -> ((f32, f32), (f32, f32)) {
         let mut min_x = f32::INFINITY;
         let mut min_y = f32::INFINITY;
@@ -139,11 +424,29 @@ impl Block {
         return ((min_x, max_x), (min_y, max_y));
     }
 
+    // Recalculate the bounding box and entity index after the entities have changed
     pub fn update_bounding_box(&mut self) -> &((f32, f32), (f32, f32)) {
+        self.draw_dirty = true;
         self.bounding_box = self.calculate_bounding_box();
+        self.entity_index = SpatialIndex::new(
+            self.entities
+                .iter()
+                .map(|e| bounding_box::union(&e.bounding_box, &e.get_selection_bounding_box()))
+                .collect(),
+        );
         return &self.bounding_box;
     }
 
+    // Entities whose boxes are within padding of a point in block coordinates
+    fn entities_near_point(&self, point: &(f32, f32), padding: f32) -> Vec<&entity::Entity> {
+        return self
+            .entity_index
+            .query_point(point, padding)
+            .into_iter()
+            .map(|i| &self.entities[i])
+            .collect();
+    }
+
     pub fn get_bounding_box(&self) -> &((f32, f32), (f32, f32)) {
         return &self.bounding_box;
     }
@@ -152,36 +455,143 @@ impl Block {
         return bounding_box::contains_point(&self.bounding_box, point, padding);
     }
 
+    // Closest entity on an enabled layer within the threshold of a point in block coordinates
+    pub fn get_closest_entity(
+        &self,
+        point: &(f32, f32),
+        threshold: f32,
+        disabled_layers: &[i32],
+    ) -> Option<(&entity::Entity, entity::ClosestPoint)> {
+        let mut output: Option<(&entity::Entity, entity::ClosestPoint)> = None;
+
+        for entity in self.entities_near_point(point, threshold) {
+            if disabled_layers.contains(&entity.layer)
+                || !entity.has_point_within_threshold(point, threshold)
+            {
+                continue;
+            }
+            if let Some(closest) = entity.get_closest_point_on_entity(point) {
+                if output
+                    .as_ref()
+                    .is_none_or(|(_, o)| closest.distance < o.distance)
+                {
+                    output = Some((entity, closest));
+                }
+            }
+        }
+        return output;
+    }
+
+    // Entities on enabled layers passing within threshold of a point in block coordinates
+    pub fn find_entities_near_point(
+        &self,
+        point: &(f32, f32),
+        threshold: f32,
+        disabled_layers: &[i32],
+    ) -> Vec<&entity::Entity> {
+        return self
+            .entities_near_point(point, threshold)
+            .into_iter()
+            .filter(|e| !disabled_layers.contains(&e.layer))
+            .filter(|e| e.has_point_within_threshold(point, threshold))
+            .collect();
+    }
+
+    // A point selects the block if it is near an entity or inside a closed outline
+    pub fn has_entity_at_point(
+        &self,
+        point: &(f32, f32),
+        threshold: f32,
+        disabled_layers: &[i32],
+    ) -> bool {
+        return self.entities_near_point(point, threshold).iter().any(|e| {
+            !disabled_layers.contains(&e.layer)
+                && (e.contains_point(point) || e.has_point_within_threshold(point, threshold))
+        });
+    }
+
     pub fn bbox_intersects_block(&self, bbox: &((f32, f32), (f32, f32))) -> bool {
         return bounding_box::intersect(&self.bounding_box, bbox);
     }
 
+    // Entities that may be on screen where a placement puts the block, None when there is no viewport
+    pub fn get_visible_entities(
+        &self,
+        placement: &Transform,
+        settings: &user_settings::ISettings,
+    ) -> Option<Vec<usize>> {
+        let viewport = settings.viewport?;
+        let padded = bounding_box::pad(&viewport, settings.cross_size);
+        return match placement.inverse() {
+            Some(inverse) => Some(self.entity_index.query_bbox(&inverse.apply_bbox(&padded))),
+            None => Some(vec![]),
+        };
+    }
+
+    // Draw the block where an insert places it, skipping entities culled by get_visible_entities
+    // A highlighted placement draws every entity as selected
     pub fn update_draw_sequence(
         &self,
-        offset: &Array2<f32>,
+        placement: &Transform,
+        highlighted: bool,
+        visible: &Option<Vec<usize>>,
         settings: &user_settings::ISettings,
         draw_params: &IDrawingParameters,
         draw_output: &mut IDrawingOutput,
     ) {
         let block_color = color::rbga_to_float(self.get_color(settings));
-        let total_highlight_offset = &draw_params.highlight_offset + offset;
+        // Rotated and scaled copies are drawn from entities moved into place, plain moves use an offset
+        let offset = if placement.is_translation() {
+            placement.get_offset()
+        } else {
+            Array2::zeros((1, 2))
+        };
+        let total_highlight_offset = &draw_params.highlight_offset + &offset;
 
-        for entity in self.entities.iter() {
+        for (i, entity) in self.entities.iter().enumerate() {
             if settings.disabled_layers.contains(&entity.layer) {
                 continue;
             };
-            let entity_color = entity.get_color(settings, &block_color);
-            let entity_offset = if entity.highlighted {
+            let entity_highlighted = highlighted || entity.highlighted;
+            if !entity_highlighted
+                && visible
+                    .as_ref()
+                    .is_some_and(|v| v.binary_search(&i).is_err())
+            {
+                continue;
+            }
+            let entity_offset = if entity_highlighted {
                 &total_highlight_offset
             } else {
-                offset
+                &offset
+            };
+            let placed_entity: entity::Entity;
+            let entity = if placement.is_translation() && entity_highlighted == entity.highlighted {
+                entity
+            } else {
+                let mut placed = if placement.is_translation() {
+                    entity.clone()
+                } else {
+                    entity.transformed(placement)
+                };
+                placed.highlighted = entity_highlighted;
+                placed_entity = placed;
+                &placed_entity
+            };
+            let entity_color = entity.get_color(settings, &block_color);
+            // Highlighted entities are always checked after being moved, the others were culled above
+            let viewport = if entity_highlighted {
+                settings.viewport
+            } else {
+                None
             };
 
             entity.update_draw_sequence(
                 entity_color,
                 entity_offset,
-                &draw_params,
+                draw_params,
                 &settings.cross_size,
+                &viewport,
                 draw_output,
             );
         }
@@ -199,7 +609,7 @@ impl Block {
         }
     }
 
-    fn get_color<'a>(&self, settings: &'a user_settings::ISettings) -> &'a (u8, u8, u8, u8) {
+    pub fn get_color<'a>(&self, settings: &'a user_settings::ISettings) -> &'a (u8, u8, u8, u8) {
         let mut block_color: &(u8, u8, u8, u8) = &settings.default_color;
         if settings.layer_colors.contains_key(&self.layer) {
             block_color = &settings.layer_colors[&self.layer]
@@ -212,6 +622,9 @@ impl Block {
     }
 
     pub fn highlight(&mut self) {
+        if !self.highlighted || self.entities.iter().any(|e| !e.highlighted) {
+            self.draw_dirty = true;
+        }
         self.highlighted = true;
         for entity in self.entities.iter_mut() {
             entity.highlighted = true;
@@ -219,30 +632,159 @@ impl Block {
     }
 
     pub fn remove_highlight(&mut self) {
+        if self.highlighted || self.has_highlighted_entities() {
+            self.draw_dirty = true;
+        }
         self.highlighted = false;
         for entity in self.entities.iter_mut() {
             entity.remove_highlight();
         }
     }
 
+    pub fn is_draw_dirty(&self) -> bool {
+        return self.draw_dirty;
+    }
+
+    pub fn mark_draw_dirty(&mut self) {
+        self.draw_dirty = true;
+    }
+
+    pub fn clear_draw_dirty(&mut self) {
+        self.draw_dirty = false;
+    }
+
+    pub fn has_highlighted_entities(&self) -> bool {
+        return self.entities.iter().any(|e| e.highlighted);
+    }
+
+    pub fn get_highlighted_entity_indexes(&self) -> Vec<String> {
+        return self
+            .entities
+            .iter()
+            .filter(|e| e.highlighted)
+            .map(|e| e.entity_index_as_uuid())
+            .collect();
+    }
+
+    // Highlight single entities without selecting the whole block
+    pub fn set_entity_highlight(&mut self, entity_index: &[u8; 32], status: bool) -> bool {
+        let mut entity_exists = false;
+        for entity in self.entities.iter_mut() {
+            if &entity.entity_index == entity_index {
+                self.draw_dirty |= entity.highlighted != status;
+                entity.highlighted = status;
+                entity_exists = true;
+            }
+        }
+        if !status {
+            self.highlighted = false;
+        }
+        return entity_exists;
+    }
+
+    // Entities in block coordinates selected by a box, fully inside for window and touching for crossing
+    pub fn find_entities_with_bbox(
+        &self,
+        bbox: &((f32, f32), (f32, f32)),
+        crossing: bool,
+        disabled_layers: &[i32],
+    ) -> Vec<&entity::Entity> {
+        return self
+            .entity_index
+            .query_bbox(bbox)
+            .into_iter()
+            .map(|i| &self.entities[i])
+            .filter(|e| !disabled_layers.contains(&e.layer))
+            .filter(|e| {
+                if crossing {
+                    e.intersects_bbox(bbox)
+                } else {
+                    e.inside_bbox(bbox)
+                }
+            })
+            .collect();
+    }
+
+    pub fn get_highlighted_entities_bounding_box(&self) -> Option<((f32, f32), (f32, f32))> {
+        let mut output: Option<((f32, f32), (f32, f32))> = None;
+        for entity in self.entities.iter().filter(|e| e.highlighted) {
+            output = Some(match output {
+                Some(current) => bounding_box::union(&current, &entity.bounding_box),
+                None => entity.bounding_box,
+            });
+        }
+        return output;
+    }
+
+    pub fn offset_highlighted_entities(&mut self, offset: &Array2<f32>) {
+        self.edit_keeping_labels(|entities| {
+            for entity in entities.iter_mut().filter(|e| e.highlighted) {
+                entity.offset_vertices(offset);
+            }
+        });
+    }
+
+    pub fn scale_highlighted_entities(&mut self, scale: &Array2<f32>, anchor: &Array2<f32>) {
+        self.edit_keeping_labels(|entities| {
+            for entity in entities.iter_mut().filter(|e| e.highlighted) {
+                entity.scale_vertices(scale, anchor);
+            }
+        });
+    }
+
+    pub fn rotate_highlighted_entities(
+        &mut self,
+        rot_matrix: &Array2<f32>,
+        rot_center: &Array2<f32>,
+    ) {
+        self.edit_keeping_labels(|entities| {
+            for entity in entities.iter_mut().filter(|e| e.highlighted) {
+                entity.rotate_vertices(rot_matrix, rot_center);
+            }
+        });
+    }
+
+    // General affine edit p' = p . matrix + translation in block coordinates
+    pub fn transform_highlighted_entities(&mut self, edit: &Transform) {
+        self.edit_keeping_labels(|entities| {
+            for entity in entities.iter_mut().filter(|e| e.highlighted) {
+                entity.transform_vertices(&edit.matrix, &edit.translation);
+            }
+        });
+    }
+
+    pub fn transform_entities(&mut self, edit: &Transform) {
+        for entity in self.entities.iter_mut() {
+            entity.transform_vertices(&edit.matrix, &edit.translation);
+        }
+        self.update_bounding_box();
+    }
+
     pub fn offset_entities(&mut self, offset: &Array2<f32>) {
         for entity in self.entities.iter_mut() {
             entity.offset_vertices(offset);
         }
-        self.bounding_box = bounding_box::offset_bbox(&self.bounding_box, offset);
+        self.update_bounding_box();
     }
 
     pub fn scale_entities(&mut self, scale: &Array2<f32>, anchor: &Array2<f32>) {
         for entity in self.entities.iter_mut() {
             entity.scale_vertices(scale, anchor);
         }
-        self.bounding_box = bounding_box::scale_bbox(&self.bounding_box, scale, anchor);
+        self.update_bounding_box();
     }
 
     pub fn rotate_entities(&mut self, rot_matrix: &Array2<f32>, rot_center: &Array2<f32>) {
         for entity in self.entities.iter_mut() {
             entity.rotate_vertices(rot_matrix, rot_center);
         }
-        self.bounding_box = self.calculate_bounding_box();
+        self.update_bounding_box();
+    }
+}
+
+fn first_vertex(entity: &entity::Entity) -> Option<(f32, f32)> {
+    if entity.vertices.shape()[0] == 0 {
+        return None;
     }
+    return Some((entity.vertices[(0, 0)], entity.vertices[(0, 1)]));
 }
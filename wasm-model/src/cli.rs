@@ -0,0 +1,302 @@
+// Commands of the cad-pattern tool, kept in the library so scripts and tests share them
+use std::fs;
+use std::path::Path;
+
+use crate::editor::Editor;
+use crate::load_report::{LoadIssueKind, LoadReport};
+use crate::pattern;
+use crate::user_settings;
+
+pub const USAGE: &str = "Usage: cad-pattern <command> [arguments]
+
+Commands:
+  info <input>                       Count blocks, inserts and entities and list layers
+  validate <input>                   Print the load report, fails when parts could not be read
+  convert <input> <output>           Convert between JSON, DXF and SVG (SVG is output only)
+      --view <view>                  View to export to SVG, Model or Block=>name
+  transform <input> <output>         Edit named blocks and write the result
+      --block <name>                 Block to edit, may be repeated
+      --offset <x,y>                 Move the blocks
+      --scale <sx,sy>                Scale the blocks about --anchor
+      --anchor <x,y>                 Anchor of the scale, the centre of the blocks by default
+      --rotate <degrees>             Rotate counterclockwise about --center
+      --center <x,y>                 Centre of the rotation, the centre of the blocks by default
+
+Inputs are read as DXF when they end in .dxf and as JSON otherwise.
+Edits are applied in the order they are given.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Dxf,
+    Svg,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Edit {
+    Offset((f32, f32)),
+    Scale((f32, f32)),
+    Rotate(f32),
+}
+
+// Runs a command given the arguments after the program name, returns what to print
+pub fn run(args: &[String]) -> Result<String, String> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err(USAGE.to_string()),
+    };
+    return match command {
+        "info" => info(rest),
+        "validate" => validate(rest),
+        "convert" => convert(rest),
+        "transform" => transform(rest),
+        "help" | "--help" | "-h" => Ok(USAGE.to_string()),
+        _ => Err(format!("Unknown command {}\n\n{}", command, USAGE)),
+    };
+}
+
+fn info(args: &[String]) -> Result<String, String> {
+    let (paths, _) = split_options(args, &[])?;
+    let input = single_path(&paths, "info <input>")?;
+    let editor = load_editor(input)?;
+    let p = editor.get_pattern();
+
+    let layers: Vec<String> = p.get_all_layers().iter().map(|l| l.to_string()).collect();
+    let mut output: Vec<String> = vec![
+        format!("blocks: {}", p.get_number_blocks()),
+        format!("inserts: {}", p.get_number_inserts()),
+        format!("entities: {}", p.get_number_entities()),
+        format!("layers: {}", layers.join(", ")),
+    ];
+    for name in p.get_all_block_names().iter() {
+        output.push(format!(
+            "block {}: {} placements",
+            name,
+            p.get_block_placements(name).len()
+        ));
+    }
+    return Ok(output.join("\n"));
+}
+
+fn validate(args: &[String]) -> Result<String, String> {
+    let (paths, _) = split_options(args, &[])?;
+    let input = single_path(&paths, "validate <input>")?;
+    let report = match input_format(input)? {
+        Format::Dxf => pattern::Pattern::try_from_dxf(&read_file(input)?),
+        _ => pattern::Pattern::try_new(&read_file(input)?),
+    };
+    let report: LoadReport = match report {
+        Ok((_, report)) | Err(report) => report,
+    };
+
+    let mut output: Vec<String> = vec![format!(
+        "{}: {} errors, {} warnings",
+        input,
+        report.errors.len(),
+        report.warnings.len()
+    )];
+    output.extend(report.get_messages());
+    let output = output.join("\n");
+
+    if !report.errors.is_empty() {
+        return Err(output);
+    }
+    return Ok(output);
+}
+
+fn convert(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(args, &["--view"])?;
+    let (input, output) = two_paths(&paths, "convert <input> <output>")?;
+    let mut editor = load_editor(input)?;
+    for (_, view) in options.iter() {
+        if view != "Model"
+            && !editor
+                .get_all_block_names()
+                .iter()
+                .any(|n| view == &format!("Block=>{}", n))
+        {
+            return Err(format!("Unknown view {}", view));
+        }
+        editor.set_view(view.clone());
+    }
+    return write_output(&editor, output);
+}
+
+fn transform(args: &[String]) -> Result<String, String> {
+    let (paths, options) = split_options(
+        args,
+        &[
+            "--block", "--offset", "--scale", "--anchor", "--rotate", "--center",
+        ],
+    )?;
+    let (input, output) = two_paths(&paths, "transform <input> <output> --block <name>")?;
+    let mut editor = load_editor(input)?;
+
+    let mut blocks: Vec<String> = vec![];
+    let mut edits: Vec<Edit> = vec![];
+    let mut anchor: Option<(f32, f32)> = None;
+    let mut center: Option<(f32, f32)> = None;
+    for (name, value) in options.iter() {
+        match name.as_str() {
+            "--block" => blocks.push(value.clone()),
+            "--offset" => edits.push(Edit::Offset(parse_pair(name, value)?)),
+            "--scale" => edits.push(Edit::Scale(parse_pair(name, value)?)),
+            "--anchor" => anchor = Some(parse_pair(name, value)?),
+            "--rotate" => edits.push(Edit::Rotate(parse_number(name, value)?)),
+            "--center" => center = Some(parse_pair(name, value)?),
+            _ => {}
+        }
+    }
+    if blocks.is_empty() {
+        return Err("transform needs at least one --block".to_string());
+    }
+    if edits.is_empty() {
+        return Err("transform needs --offset, --scale or --rotate".to_string());
+    }
+
+    let block_names = editor.get_all_block_names();
+    for block in blocks.iter() {
+        if !block_names.contains(block) {
+            return Err(format!("Unknown block {}", block));
+        }
+        editor.highlight_block(block.clone(), true);
+    }
+
+    for edit in edits.iter() {
+        match edit {
+            Edit::Offset((x, y)) => {
+                editor.set_highlight_offset(*x, *y);
+                editor.offset_highlights();
+            }
+            Edit::Scale((x, y)) => {
+                let (anchor_x, anchor_y) = anchor.unwrap_or_else(|| selection_center(&mut editor));
+                editor.set_highlight_anchor(anchor_x, anchor_y);
+                editor.set_highlight_scale(*x, *y);
+                editor.scale_highlights();
+            }
+            Edit::Rotate(degrees) => {
+                let (center_x, center_y) = center.unwrap_or_else(|| selection_center(&mut editor));
+                editor.set_highlight_rotation_center(center_x, center_y);
+                // Positive angles in the editor turn clockwise
+                editor.set_highlight_rotation_angle(-degrees.to_radians());
+                editor.rotate_highlights_bbox();
+            }
+        }
+    }
+    editor.reset_selection();
+    return write_output(&editor, output);
+}
+
+fn selection_center(editor: &mut Editor) -> (f32, f32) {
+    return match editor.get_highlighted_bounding_box() {
+        Some(((min_x, max_x), (min_y, max_y))) => ((min_x + max_x) / 2., (min_y + max_y) / 2.),
+        None => (0., 0.),
+    };
+}
+
+// Options that take a value, by name
+type Options = Vec<(String, String)>;
+
+// Separates paths from options that take a value, in the order they were given
+fn split_options(args: &[String], known: &[&str]) -> Result<(Vec<String>, Options), String> {
+    let mut paths: Vec<String> = vec![];
+    let mut options: Vec<(String, String)> = vec![];
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            paths.push(arg.clone());
+            continue;
+        }
+        if !known.contains(&arg.as_str()) {
+            return Err(format!("Unknown option {}", arg));
+        }
+        match iter.next() {
+            Some(value) => options.push((arg.clone(), value.clone())),
+            None => return Err(format!("Missing value for {}", arg)),
+        }
+    }
+    return Ok((paths, options));
+}
+
+fn single_path<'a>(paths: &'a [String], usage: &str) -> Result<&'a String, String> {
+    return match paths {
+        [input] => Ok(input),
+        _ => Err(format!("Usage: cad-pattern {}", usage)),
+    };
+}
+
+fn two_paths<'a>(paths: &'a [String], usage: &str) -> Result<(&'a String, &'a String), String> {
+    return match paths {
+        [input, output] => Ok((input, output)),
+        _ => Err(format!("Usage: cad-pattern {}", usage)),
+    };
+}
+
+fn parse_number(name: &str, value: &str) -> Result<f32, String> {
+    return match value.trim().parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("{} expects a number, got {}", name, value)),
+    };
+}
+
+// Pairs are written x,y
+fn parse_pair(name: &str, value: &str) -> Result<(f32, f32), String> {
+    let parts: Vec<&str> = value.split(',').collect();
+    if parts.len() != 2 {
+        return Err(format!("{} expects x,y, got {}", name, value));
+    }
+    return Ok((parse_number(name, parts[0])?, parse_number(name, parts[1])?));
+}
+
+fn extension_format(path: &str) -> Option<Format> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    return match extension.as_deref() {
+        Some("json") => Some(Format::Json),
+        Some("dxf") => Some(Format::Dxf),
+        Some("svg") => Some(Format::Svg),
+        _ => None,
+    };
+}
+
+fn input_format(path: &str) -> Result<Format, String> {
+    return match extension_format(path) {
+        Some(Format::Svg) => Err(format!("Can not read SVG input {}", path)),
+        Some(format) => Ok(format),
+        None => Ok(Format::Json),
+    };
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    return fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e));
+}
+
+fn load_editor(path: &str) -> Result<Editor, String> {
+    let settings = serde_json::to_string(&user_settings::ISettings::default()).unwrap();
+    let editor = match input_format(path)? {
+        Format::Dxf => Editor::new_from_dxf(read_file(path)?, settings),
+        _ => Editor::new(read_file(path)?, settings),
+    };
+    // A document that could not be read at all would be written back empty
+    let report = editor.get_report();
+    let failed = report
+        .errors
+        .iter()
+        .find(|i| i.kind == LoadIssueKind::InvalidDocument);
+    if let Some(issue) = failed {
+        return Err(format!("Unable to load {}: {}", path, issue.reason));
+    }
+    return Ok(editor);
+}
+
+fn write_output(editor: &Editor, path: &str) -> Result<String, String> {
+    let contents = match extension_format(path) {
+        Some(Format::Json) => editor.to_json(),
+        Some(Format::Dxf) => editor.export_dxf(),
+        Some(Format::Svg) => editor.export_svg(),
+        None => return Err(format!("Output {} must end in .json, .dxf or .svg", path)),
+    };
+    fs::write(path, contents).map_err(|e| format!("Unable to write {}: {}", path, e))?;
+    return Ok(format!("Wrote {}", path));
+}
@@ -0,0 +1,408 @@
+// Analytic curves, entities keep these so transforms stay exact and drawing can pick its own resolution
+use ndarray::Array2;
+use std::f32::consts::PI;
+
+use crate::spline::Spline;
+
+const TAU: f32 = 2. * PI;
+// Lower bound on segments for a full turn so small curves still look round
+const MIN_SEGMENTS_PER_TURN: f32 = 8.;
+const MAX_SEGMENTS: usize = 4096;
+// Relative difference allowed when checking a transform keeps circles circular
+const SIMILARITY_TOLERANCE: f32 = 1e-4;
+// Sweeps this close to zero or a full turn are taken as a full turn
+const FULL_TURN_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Curve {
+    // Angles in radians, the arc runs counter clockwise from start to end
+    Arc {
+        center: (f32, f32),
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+    },
+    // Parameters in radians, points are center + major_axis * cos(t) + minor_axis * sin(t)
+    // where the minor axis is the major axis turned a quarter counter clockwise and scaled by ratio
+    Ellipse {
+        center: (f32, f32),
+        major_axis: (f32, f32),
+        ratio: f32,
+        start_param: f32,
+        end_param: f32,
+    },
+    Spline(Spline),
+}
+
+// Transforms use row vectors like the rest of the model, p' = p . matrix + translation
+fn apply_matrix(p: &(f32, f32), matrix: &Array2<f32>) -> (f32, f32) {
+    return (
+        p.0 * matrix[(0, 0)] + p.1 * matrix[(1, 0)],
+        p.0 * matrix[(0, 1)] + p.1 * matrix[(1, 1)],
+    );
+}
+
+fn apply_transform(p: &(f32, f32), matrix: &Array2<f32>, translation: &Array2<f32>) -> (f32, f32) {
+    let (x, y) = apply_matrix(p, matrix);
+    return (x + translation[(0, 0)], y + translation[(0, 1)]);
+}
+
+// Scale and orientation of a transform that keeps circles as circles
+pub fn similarity_scale(matrix: &Array2<f32>) -> Option<(f32, bool)> {
+    let (a, b) = (matrix[(0, 0)], matrix[(0, 1)]);
+    let (c, d) = (matrix[(1, 0)], matrix[(1, 1)]);
+    let scale_x = (a * a + b * b).sqrt();
+    let scale_y = (c * c + d * d).sqrt();
+    let largest = scale_x.max(scale_y);
+    if largest == 0.
+        || (scale_x - scale_y).abs() > SIMILARITY_TOLERANCE * largest
+        || (a * c + b * d).abs() > SIMILARITY_TOLERANCE * largest * largest
+    {
+        return None;
+    }
+    let mirrored = a * d - b * c < 0.;
+    return Some((scale_x, mirrored));
+}
+
+pub fn normalize_angle(angle: f32) -> f32 {
+    return angle.rem_euclid(TAU);
+}
+
+fn angle_of(p: &(f32, f32)) -> f32 {
+    return normalize_angle(p.1.atan2(p.0));
+}
+
+fn ellipse_sweep(start_param: f32, end_param: f32) -> f32 {
+    let sweep = normalize_angle(end_param - start_param);
+    if !(FULL_TURN_TOLERANCE..=TAU - FULL_TURN_TOLERANCE).contains(&sweep) {
+        return TAU;
+    }
+    return sweep;
+}
+
+// Ellipse through center + a cos(t) + b sin(t) for any pair of conjugate axes a and b, moved onto
+// its principal axes. Returns None when the axes are degenerate
+fn ellipse_from_conjugate_axes(
+    center: (f32, f32),
+    a: (f32, f32),
+    b: (f32, f32),
+    start_param: f32,
+    end_param: f32,
+) -> Option<Curve> {
+    let dot = a.0 * b.0 + a.1 * b.1;
+    let difference = (a.0 * a.0 + a.1 * a.1) - (b.0 * b.0 + b.1 * b.1);
+    // Parameter where the distance from the center is largest
+    let t0 = 0.5 * (2. * dot).atan2(difference);
+    let (cos_t0, sin_t0) = (t0.cos(), t0.sin());
+    let major_axis = (a.0 * cos_t0 + b.0 * sin_t0, a.1 * cos_t0 + b.1 * sin_t0);
+    let minor_axis = (b.0 * cos_t0 - a.0 * sin_t0, b.1 * cos_t0 - a.1 * sin_t0);
+    let major_length = (major_axis.0 * major_axis.0 + major_axis.1 * major_axis.1).sqrt();
+    let minor_length = (minor_axis.0 * minor_axis.0 + minor_axis.1 * minor_axis.1).sqrt();
+    if major_length == 0. || minor_length == 0. {
+        return None;
+    }
+
+    let full = ellipse_sweep(start_param, end_param) == TAU;
+    let (mut start, mut end) = (start_param - t0, end_param - t0);
+    // A minor axis turned clockwise from the major axis means the parameter runs the other way
+    if major_axis.0 * minor_axis.1 - major_axis.1 * minor_axis.0 < 0. {
+        (start, end) = (-end, -start);
+    }
+    let start = normalize_angle(start);
+    return Some(Curve::Ellipse {
+        center: center,
+        major_axis: major_axis,
+        ratio: (minor_length / major_length).min(1.),
+        start_param: start,
+        end_param: if full { start } else { normalize_angle(end) },
+    });
+}
+
+// Segments needed so no chord is further than the tolerance from a circle of this radius
+fn segment_count(radius: f32, sweep: f32, tolerance: f32) -> usize {
+    let max_step = if tolerance <= 0. || tolerance >= radius {
+        PI / 2.
+    } else {
+        2. * (1. - tolerance / radius).acos()
+    };
+    let min_segments = (MIN_SEGMENTS_PER_TURN * sweep / TAU).ceil();
+    let segments = (sweep / max_step).ceil().max(min_segments).max(1.);
+    return (segments as usize).min(MAX_SEGMENTS);
+}
+
+impl Curve {
+    // Arc of a polyline segment with a DXF bulge, the tangent of a quarter of the included angle.
+    // The arc is stored counter clockwise, for negative bulges it runs from end to start
+    pub fn from_bulge(start: &(f32, f32), end: &(f32, f32), bulge: f32) -> Option<Curve> {
+        let (d_x, d_y) = (end.0 - start.0, end.1 - start.1);
+        let chord = (d_x * d_x + d_y * d_y).sqrt();
+        if bulge == 0. || chord == 0. {
+            return None;
+        }
+        // Signed distance from the middle of the chord to the center, measured to the left
+        let center_distance = chord * (1. - bulge * bulge) / (4. * bulge);
+        let center = (
+            (start.0 + end.0) / 2. - d_y / chord * center_distance,
+            (start.1 + end.1) / 2. + d_x / chord * center_distance,
+        );
+        let radius = chord * (1. + bulge * bulge) / (4. * bulge.abs());
+        let start_angle = angle_of(&(start.0 - center.0, start.1 - center.1));
+        let end_angle = angle_of(&(end.0 - center.0, end.1 - center.1));
+        let (start_angle, end_angle) = if bulge > 0. {
+            (start_angle, end_angle)
+        } else {
+            (end_angle, start_angle)
+        };
+        return Some(Curve::Arc {
+            center: center,
+            radius: radius,
+            start_angle: start_angle,
+            end_angle: end_angle,
+        });
+    }
+
+    // Splines have no center
+    pub fn get_center(&self) -> Option<(f32, f32)> {
+        match self {
+            Curve::Arc { center, .. }
+            | Curve::Circle { center, .. }
+            | Curve::Ellipse { center, .. } => return Some(*center),
+            Curve::Spline(_) => return None,
+        }
+    }
+
+    // Angle covered by the curve in radians, ellipses and splines use their parameter instead
+    pub fn get_sweep(&self) -> f32 {
+        match self {
+            Curve::Arc {
+                start_angle,
+                end_angle,
+                ..
+            } => {
+                let sweep = normalize_angle(end_angle - start_angle);
+                // Matching start and end angles describe a full turn
+                return if sweep == 0. { TAU } else { sweep };
+            }
+            Curve::Circle { .. } => return TAU,
+            Curve::Ellipse {
+                start_param,
+                end_param,
+                ..
+            } => return ellipse_sweep(*start_param, *end_param),
+            Curve::Spline(spline) => {
+                let (start, end) = spline.get_domain();
+                return end - start;
+            }
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Curve::Arc { .. } => return false,
+            Curve::Circle { .. } => return true,
+            Curve::Ellipse { .. } => return self.get_sweep() == TAU,
+            Curve::Spline(spline) => return spline.closed,
+        }
+    }
+
+    // Ellipses and splines take their parameter in place of the angle
+    pub fn point_at_angle(&self, angle: f32) -> (f32, f32) {
+        match self {
+            Curve::Arc { center, radius, .. } | Curve::Circle { center, radius } => {
+                return (
+                    center.0 + radius * angle.cos(),
+                    center.1 + radius * angle.sin(),
+                );
+            }
+            Curve::Ellipse {
+                center,
+                major_axis,
+                ratio,
+                ..
+            } => {
+                let (cos_t, sin_t) = (angle.cos(), angle.sin());
+                return (
+                    center.0 + major_axis.0 * cos_t - ratio * major_axis.1 * sin_t,
+                    center.1 + major_axis.1 * cos_t + ratio * major_axis.0 * sin_t,
+                );
+            }
+            Curve::Spline(spline) => return spline.evaluate(angle),
+        }
+    }
+
+    pub fn get_start_angle(&self) -> f32 {
+        match self {
+            Curve::Arc { start_angle, .. } => return *start_angle,
+            Curve::Circle { .. } => return 0.,
+            Curve::Ellipse { start_param, .. } => return *start_param,
+            Curve::Spline(spline) => return spline.get_domain().0,
+        }
+    }
+
+    fn contains_angle(&self, angle: f32) -> bool {
+        return normalize_angle(angle - self.get_start_angle()) <= self.get_sweep();
+    }
+
+    pub fn get_start_point(&self) -> (f32, f32) {
+        return self.point_at_angle(self.get_start_angle());
+    }
+
+    pub fn get_end_point(&self) -> (f32, f32) {
+        return self.point_at_angle(self.get_start_angle() + self.get_sweep());
+    }
+
+    // Points along the curve with no chord further than the tolerance from it,
+    // closed curves do not repeat their first point
+    pub fn tessellate(&self, tolerance: f32) -> Array2<f32> {
+        let (radius, sweep) = match self {
+            Curve::Arc { radius, .. } | Curve::Circle { radius, .. } => (*radius, self.get_sweep()),
+            // No chord of the ellipse is further from it than the same chord of its major circle
+            Curve::Ellipse { major_axis, .. } => (
+                (major_axis.0 * major_axis.0 + major_axis.1 * major_axis.1).sqrt(),
+                self.get_sweep(),
+            ),
+            Curve::Spline(spline) => return spline.tessellate(tolerance),
+        };
+        let segments = segment_count(radius, sweep, tolerance);
+        let num_points = if self.is_closed() {
+            segments
+        } else {
+            segments + 1
+        };
+        let start = self.get_start_angle();
+
+        let mut vertex_data: Vec<f32> = Vec::with_capacity(num_points * 2);
+        for i in 0..num_points {
+            let (x, y) = self.point_at_angle(start + sweep * i as f32 / segments as f32);
+            vertex_data.push(x);
+            vertex_data.push(y);
+        }
+        return Array2::from_shape_vec((num_points, 2), vertex_data).unwrap();
+    }
+
+    // Angles or parameters where the full circle or ellipse reaches its furthest x and y
+    fn extreme_angles(&self) -> Vec<f32> {
+        match self {
+            Curve::Ellipse {
+                major_axis, ratio, ..
+            } => {
+                let minor_axis = (-ratio * major_axis.1, ratio * major_axis.0);
+                let t_x = minor_axis.0.atan2(major_axis.0);
+                let t_y = minor_axis.1.atan2(major_axis.1);
+                return vec![t_x, t_x + PI, t_y, t_y + PI];
+            }
+            _ => return (0..4).map(|quadrant| quadrant as f32 * PI / 2.).collect(),
+        }
+    }
+
+    // Tight box including the extremes of the circle or ellipse that the curve passes through
+    pub fn bounding_box(&self) -> ((f32, f32), (f32, f32)) {
+        if let Curve::Spline(spline) = self {
+            return spline.bounding_box();
+        }
+        let mut points = vec![self.get_start_point(), self.get_end_point()];
+        for angle in self.extreme_angles() {
+            if self.contains_angle(angle) {
+                points.push(self.point_at_angle(angle));
+            }
+        }
+        let min_x = points.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+        let max_x = points.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+        return ((min_x, max_x), (min_y, max_y));
+    }
+
+    // Circles and arcs written as an ellipse, which any affine transform maps onto another ellipse
+    fn as_ellipse(&self) -> Option<Curve> {
+        match self {
+            Curve::Circle { center, radius } => {
+                return Some(Curve::Ellipse {
+                    center: *center,
+                    major_axis: (*radius, 0.),
+                    ratio: 1.,
+                    start_param: 0.,
+                    end_param: 0.,
+                });
+            }
+            Curve::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+            } => {
+                return Some(Curve::Ellipse {
+                    center: *center,
+                    major_axis: (*radius, 0.),
+                    ratio: 1.,
+                    start_param: *start_angle,
+                    end_param: *end_angle,
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    // Exact image of the curve under an affine transform. Circles and arcs stay circular under
+    // similarities and become ellipses otherwise, None if the transform collapses the curve
+    pub fn transform(&self, matrix: &Array2<f32>, translation: &Array2<f32>) -> Option<Curve> {
+        if let Curve::Spline(spline) = self {
+            let transformed = spline.transform(|p| apply_transform(p, matrix, translation));
+            return Some(Curve::Spline(transformed));
+        }
+        if let Curve::Ellipse {
+            center,
+            major_axis,
+            ratio,
+            start_param,
+            end_param,
+        } = self
+        {
+            let minor_axis = (-ratio * major_axis.1, ratio * major_axis.0);
+            return ellipse_from_conjugate_axes(
+                apply_transform(center, matrix, translation),
+                apply_matrix(major_axis, matrix),
+                apply_matrix(&minor_axis, matrix),
+                *start_param,
+                *end_param,
+            );
+        }
+        let Some((scale, mirrored)) = similarity_scale(matrix) else {
+            return self.as_ellipse()?.transform(matrix, translation);
+        };
+        match self {
+            Curve::Circle { center, radius } => {
+                return Some(Curve::Circle {
+                    center: apply_transform(center, matrix, translation),
+                    radius: radius * scale,
+                });
+            }
+            Curve::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+            } => {
+                let start_direction = apply_matrix(&(start_angle.cos(), start_angle.sin()), matrix);
+                let end_direction = apply_matrix(&(end_angle.cos(), end_angle.sin()), matrix);
+                // A mirror reverses the direction the arc runs in
+                let (new_start, new_end) = if mirrored {
+                    (angle_of(&end_direction), angle_of(&start_direction))
+                } else {
+                    (angle_of(&start_direction), angle_of(&end_direction))
+                };
+                return Some(Curve::Arc {
+                    center: apply_transform(center, matrix, translation),
+                    radius: radius * scale,
+                    start_angle: new_start,
+                    end_angle: new_end,
+                });
+            }
+            Curve::Ellipse { .. } | Curve::Spline(_) => return None,
+        }
+    }
+}
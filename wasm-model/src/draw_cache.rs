@@ -0,0 +1,297 @@
+// Tracks where each block sits in the vertex and index buffers so only changed blocks are redrawn
+use std::collections::HashMap;
+
+use crate::drawing_output::IDrawingStatistics;
+use crate::user_settings;
+use crate::utils::memory::{IndexBuffer, VertexBuffer};
+
+// Each vertex is written as x, y, color
+const FLOATS_PER_VERTEX: usize = 3;
+const BYTES_PER_ELEMENT: usize = 4;
+
+#[derive(Debug, Clone)]
+struct BlockRange {
+    vertex_start: usize,
+    vertex_len: usize,
+    index_start: usize,
+    index_len: usize,
+    // False when the block is culled or not part of the current view
+    drawn: bool,
+    // Entities left after culling each placement against the viewport, None without a viewport
+    visible_entities: Vec<Option<Vec<usize>>>,
+    statistics: IDrawingStatistics,
+}
+
+// Settings that change how every block is drawn, the viewport only changes the blocks it cuts
+#[derive(Debug, Clone, PartialEq)]
+struct DrawSettingsKey {
+    view: String,
+    disabled_layers: Vec<i32>,
+    layer_colors: HashMap<i32, (u8, u8, u8, u8)>,
+    default_color: (u8, u8, u8, u8),
+    highlight_color: (u8, u8, u8, u8),
+    cross_size: f32,
+    chord_tolerance: f32,
+    grade_overlay: bool,
+    grade_overlay_color: (u8, u8, u8, u8),
+}
+
+impl DrawSettingsKey {
+    fn new(settings: &user_settings::ISettings) -> DrawSettingsKey {
+        return DrawSettingsKey {
+            view: settings.view.clone(),
+            disabled_layers: settings.disabled_layers.clone(),
+            layer_colors: settings.layer_colors.clone(),
+            default_color: settings.default_color,
+            highlight_color: settings.highlight_color,
+            cross_size: settings.cross_size,
+            chord_tolerance: settings.chord_tolerance,
+            grade_overlay: settings.grade_overlay,
+            grade_overlay_color: settings.grade_overlay_color,
+        };
+    }
+}
+
+// Settings that only change how the selection is drawn, selected entities are culled after
+// being moved so the viewport is one of them
+type HighlightKey = (
+    Option<((f32, f32), (f32, f32))>,
+    (f32, f32),
+    (f32, f32),
+    (bool, bool),
+    (f32, f32),
+    (f32, f32),
+    f32,
+);
+
+fn highlight_key(settings: &user_settings::ISettings) -> HighlightKey {
+    return (
+        settings.viewport,
+        settings.highlight_offset,
+        settings.highlight_scale,
+        settings.highlight_flip,
+        settings.highlight_anchor,
+        settings.highlight_rotation_center,
+        settings.highlight_rotation_angle,
+    );
+}
+
+// Add a byte range to a sorted list, merging it with the last range if they touch
+fn push_range(ranges: &mut Vec<(usize, usize)>, start: usize, end: usize) {
+    if start >= end {
+        return;
+    }
+    if let Some(last) = ranges.last_mut() {
+        if start <= last.1 {
+            last.1 = last.1.max(end);
+            return;
+        }
+    }
+    ranges.push((start, end));
+}
+
+fn flatten_ranges(ranges: &[(usize, usize)]) -> Vec<u32> {
+    return ranges
+        .iter()
+        .flat_map(|(start, end)| [*start as u32, *end as u32])
+        .collect();
+}
+
+pub struct DrawCache {
+    ranges: Vec<BlockRange>,
+    settings_key: Option<DrawSettingsKey>,
+    highlight_key: Option<HighlightKey>,
+    // Byte ranges of the buffers written during the last pass
+    changed_vertex_bytes: Vec<(usize, usize)>,
+    changed_index_bytes: Vec<(usize, usize)>,
+}
+
+impl Default for DrawCache {
+    fn default() -> Self {
+        return DrawCache::new();
+    }
+}
+
+impl DrawCache {
+    pub fn new() -> DrawCache {
+        return DrawCache {
+            ranges: vec![],
+            settings_key: None,
+            highlight_key: None,
+            changed_vertex_bytes: vec![],
+            changed_index_bytes: vec![],
+        };
+    }
+
+    // Start a drawing pass, returns whether every block needs to be drawn and whether the selection moved
+    pub fn begin_pass(
+        &mut self,
+        settings: &user_settings::ISettings,
+        nr_blocks: usize,
+        vertex_buffer: &mut VertexBuffer,
+        index_buffer: &mut IndexBuffer,
+    ) -> (bool, bool) {
+        self.changed_vertex_bytes.clear();
+        self.changed_index_bytes.clear();
+
+        let settings_key = DrawSettingsKey::new(settings);
+        let full_rebuild =
+            self.ranges.len() != nr_blocks || self.settings_key.as_ref() != Some(&settings_key);
+        let new_highlight_key = highlight_key(settings);
+        let highlight_changed = self.highlight_key != Some(new_highlight_key);
+
+        self.settings_key = Some(settings_key);
+        self.highlight_key = Some(new_highlight_key);
+
+        if full_rebuild {
+            // Blocks are appended in order from an empty buffer
+            self.ranges.clear();
+            vertex_buffer.buffer.clear();
+            index_buffer.buffer.clear();
+        }
+        return (full_rebuild, highlight_changed);
+    }
+
+    pub fn is_drawn(&self, block_ind: usize) -> bool {
+        return self.ranges.get(block_ind).is_some_and(|r| r.drawn);
+    }
+
+    // Whether the block was last drawn with the same entities left after culling
+    pub fn has_visible_entities(
+        &self,
+        block_ind: usize,
+        visible_entities: &[Option<Vec<usize>>],
+    ) -> bool {
+        return self
+            .ranges
+            .get(block_ind)
+            .is_some_and(|r| r.visible_entities == visible_entities);
+    }
+
+    pub fn set_visible_entities(
+        &mut self,
+        block_ind: usize,
+        visible_entities: Vec<Option<Vec<usize>>>,
+    ) {
+        if let Some(range) = self.ranges.get_mut(block_ind) {
+            range.visible_entities = visible_entities;
+        }
+    }
+
+    // First vertex number of a block, indices written for the block start from here
+    pub fn get_first_vertex(&self, block_ind: usize, vertex_buffer: &VertexBuffer) -> u32 {
+        let vertex_start = match self.ranges.get(block_ind) {
+            Some(range) => range.vertex_start,
+            None => vertex_buffer.buffer.len(),
+        };
+        return (vertex_start / FLOATS_PER_VERTEX) as u32;
+    }
+
+    // Write the new data for a block over its old range, moving the blocks after it if the size changed
+    pub fn replace_block(
+        &mut self,
+        block_ind: usize,
+        drawn: bool,
+        block_data: (Vec<f32>, Vec<u32>),
+        statistics: IDrawingStatistics,
+        vertex_buffer: &mut VertexBuffer,
+        index_buffer: &mut IndexBuffer,
+    ) {
+        if block_ind == self.ranges.len() {
+            self.ranges.push(BlockRange {
+                vertex_start: vertex_buffer.buffer.len(),
+                vertex_len: 0,
+                index_start: index_buffer.buffer.len(),
+                index_len: 0,
+                drawn: drawn,
+                visible_entities: vec![],
+                statistics: statistics,
+            });
+        }
+
+        let (vertices, indices) = block_data;
+        let range = self.ranges[block_ind].clone();
+        let (new_vertex_len, new_index_len) = (vertices.len(), indices.len());
+        let vertex_end = range.vertex_start + range.vertex_len;
+        let index_end = range.index_start + range.index_len;
+        // Redrawn blocks often keep the same indices, or the same vertices when only colors change
+        let vertices_unchanged =
+            vertex_buffer.buffer[range.vertex_start..vertex_end] == vertices[..];
+        let indices_unchanged = index_buffer.buffer[range.index_start..index_end] == indices[..];
+        vertex_buffer
+            .buffer
+            .splice(range.vertex_start..vertex_end, vertices);
+        index_buffer
+            .buffer
+            .splice(range.index_start..index_end, indices);
+
+        let vertex_shift = new_vertex_len as isize - range.vertex_len as isize;
+        let index_shift = new_index_len as isize - range.index_len as isize;
+        let first_moved_index = range.index_start + new_index_len;
+
+        if vertex_shift != 0 || index_shift != 0 {
+            let vertex_number_shift = vertex_shift / FLOATS_PER_VERTEX as isize;
+            for later in self.ranges[block_ind + 1..].iter_mut() {
+                later.vertex_start = (later.vertex_start as isize + vertex_shift) as usize;
+                later.index_start = (later.index_start as isize + index_shift) as usize;
+            }
+            if vertex_number_shift != 0 {
+                // Indices of later blocks point at vertices that have moved
+                for index in index_buffer.buffer[first_moved_index..].iter_mut() {
+                    if *index != u32::MAX {
+                        *index = (*index as isize + vertex_number_shift) as u32;
+                    }
+                }
+            }
+        }
+
+        let vertex_changed_end = if vertex_shift == 0 {
+            range.vertex_start + new_vertex_len
+        } else {
+            vertex_buffer.buffer.len()
+        };
+        let index_changed_end = if vertex_shift == 0 && index_shift == 0 {
+            first_moved_index
+        } else {
+            index_buffer.buffer.len()
+        };
+        if !vertices_unchanged {
+            push_range(
+                &mut self.changed_vertex_bytes,
+                range.vertex_start * BYTES_PER_ELEMENT,
+                vertex_changed_end * BYTES_PER_ELEMENT,
+            );
+        }
+        if !indices_unchanged || vertex_shift != 0 {
+            push_range(
+                &mut self.changed_index_bytes,
+                range.index_start * BYTES_PER_ELEMENT,
+                index_changed_end * BYTES_PER_ELEMENT,
+            );
+        }
+
+        let updated = &mut self.ranges[block_ind];
+        updated.vertex_len = new_vertex_len;
+        updated.index_len = new_index_len;
+        updated.drawn = drawn;
+        updated.statistics = statistics;
+    }
+
+    // Combined highlight statistics of every block, including the ones not drawn again this pass
+    pub fn get_statistics(&self) -> IDrawingStatistics {
+        let mut output = IDrawingStatistics::new();
+        for range in self.ranges.iter() {
+            output.merge(&range.statistics);
+        }
+        return output;
+    }
+
+    // Byte ranges as pairs of start and end, flattened into one list
+    pub fn get_changed_vertex_bytes(&self) -> Vec<u32> {
+        return flatten_ranges(&self.changed_vertex_bytes);
+    }
+
+    pub fn get_changed_index_bytes(&self) -> Vec<u32> {
+        return flatten_ranges(&self.changed_index_bytes);
+    }
+}
@@ -35,6 +35,60 @@ impl<'a> IDrawingOutput<'a> {
         self.max_y = self.max_y.max(*y);
     }
 
+    pub fn get_statistics(&self) -> IDrawingStatistics {
+        return IDrawingStatistics {
+            nr_entities: self.nr_entities,
+            min_x: self.min_x,
+            min_y: self.min_y,
+            max_x: self.max_x,
+            max_y: self.max_y,
+        };
+    }
+
+    pub fn get_width_height(&self) -> (f32, f32) {
+        return self.get_statistics().get_width_height();
+    }
+
+    pub fn get_center_bbox(&self) -> (f32, f32) {
+        return self.get_statistics().get_center_bbox();
+    }
+}
+
+// Highlight statistics of a drawing pass, kept for blocks that are not drawn again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IDrawingStatistics {
+    pub nr_entities: u32,
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl Default for IDrawingStatistics {
+    fn default() -> Self {
+        return IDrawingStatistics::new();
+    }
+}
+
+impl IDrawingStatistics {
+    pub fn new() -> Self {
+        return IDrawingStatistics {
+            nr_entities: 0,
+            min_x: f32::INFINITY,
+            min_y: f32::INFINITY,
+            max_x: -f32::INFINITY,
+            max_y: -f32::INFINITY,
+        };
+    }
+
+    pub fn merge(&mut self, other: &IDrawingStatistics) {
+        self.nr_entities += other.nr_entities;
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+
     pub fn get_width_height(&self) -> (f32, f32) {
         let d_x = if self.min_x.is_finite() && self.max_x.is_finite() {
             self.max_x - self.min_x
@@ -9,4 +9,6 @@ pub struct IDrawingParameters {
     pub highlight_rot_matrix: Array2<f32>,
     pub rot_center: Array2<f32>,
     pub highlight_rot_offset: Array2<f32>,
+    // Largest distance between a drawn chord and the curve it approximates
+    pub chord_tolerance: f32,
 }
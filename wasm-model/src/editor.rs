@@ -0,0 +1,546 @@
+// Editing session over a pattern: settings, draw buffers and history, usable from plain Rust
+use ndarray::Array2;
+
+use crate::draw_cache::DrawCache;
+use crate::entity;
+use crate::history;
+use crate::load_report::LoadReport;
+use crate::measure;
+use crate::pattern;
+use crate::seam_allowance;
+use crate::snap;
+use crate::user_settings;
+use crate::utils::bounding_box;
+use crate::utils::color;
+use crate::utils::log;
+use crate::utils::memory::{IndexBuffer, VertexBuffer};
+
+pub struct Editor {
+    pattern: pattern::Pattern,
+    settings: user_settings::ISettings,
+    vertex_buffer: VertexBuffer,
+    index_buffer: IndexBuffer,
+    draw_cache: DrawCache,
+    history: history::History,
+    // What could not be read when the pattern was loaded
+    load_report: LoadReport,
+    // Temporary entities drawn over the pattern in view coordinates
+    overlay: Vec<entity::Entity>,
+}
+
+impl Editor {
+    pub fn new(pattern_payload: String, settings_payload: String) -> Editor {
+        let loaded = pattern::Pattern::try_new(&pattern_payload);
+        return Editor::from_loaded(loaded, settings_payload);
+    }
+
+    pub fn new_from_dxf(dxf_payload: String, settings_payload: String) -> Editor {
+        let loaded = pattern::Pattern::try_from_dxf(&dxf_payload);
+        return Editor::from_loaded(loaded, settings_payload);
+    }
+
+    fn from_loaded(
+        loaded: Result<(pattern::Pattern, LoadReport), LoadReport>,
+        settings_payload: String,
+    ) -> Editor {
+        let load_report = match &loaded {
+            Ok((_, report)) | Err(report) => report.clone(),
+        };
+        let pattern = pattern::Pattern::or_empty(loaded);
+        let mut settings = user_settings::ISettings::default();
+
+        if let Ok(parsed_settings) = user_settings::ISettings::parse_settings(&settings_payload) {
+            settings = parsed_settings;
+        } else {
+            log::warning("Settings in incorrect format");
+        }
+
+        let vertex_buffer: VertexBuffer = VertexBuffer::new();
+        let index_buffer: IndexBuffer = IndexBuffer::new();
+
+        return Editor {
+            pattern: pattern,
+            settings: settings,
+            vertex_buffer: vertex_buffer,
+            index_buffer: index_buffer,
+            draw_cache: DrawCache::new(),
+            history: history::History::new(history::DEFAULT_HISTORY_DEPTH),
+            load_report: load_report,
+            overlay: vec![],
+        };
+    }
+
+    pub fn get_number_entities(&self) -> usize {
+        return self.pattern.get_number_entities();
+    }
+
+    pub fn to_json(&self) -> String {
+        return self.pattern.to_json();
+    }
+
+    pub fn export_dxf(&self) -> String {
+        return self.pattern.export_dxf();
+    }
+
+    pub fn export_svg(&self) -> String {
+        return self.pattern.export_svg(&self.settings);
+    }
+
+    pub fn update_draw_sequence(&mut self) {
+        self.pattern.update_draw_sequence(
+            &mut self.settings,
+            &self.overlay,
+            &mut self.vertex_buffer,
+            &mut self.index_buffer,
+            &mut self.draw_cache,
+        );
+    }
+
+    // Byte ranges of the vertex buffer written by the last draw as start, end pairs
+    pub fn get_changed_vertex_ranges(&self) -> Vec<u32> {
+        return self.draw_cache.get_changed_vertex_bytes();
+    }
+
+    // Byte ranges of the index buffer written by the last draw as start, end pairs
+    pub fn get_changed_index_ranges(&self) -> Vec<u32> {
+        return self.draw_cache.get_changed_index_bytes();
+    }
+
+    pub fn get_all_layers(&self) -> Vec<i32> {
+        return self.pattern.get_all_layers();
+    }
+
+    pub fn get_all_block_names(&self) -> Vec<String> {
+        return self.pattern.get_all_block_names();
+    }
+
+    pub fn get_vertex_buffer_ptr(&self) -> *const f32 {
+        return self.vertex_buffer.get_ptr();
+    }
+
+    pub fn get_vertex_buffer_len(&self) -> usize {
+        return self.vertex_buffer.get_len();
+    }
+
+    pub fn get_index_buffer_ptr(&self) -> *const u32 {
+        return self.index_buffer.get_ptr();
+    }
+
+    pub fn get_index_buffer_len(&self) -> usize {
+        return self.index_buffer.get_len();
+    }
+
+    // Only geometry inside this area of the current view is added to the draw sequence
+    pub fn set_viewport(&mut self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) {
+        self.settings.viewport = Some((
+            (min_x.min(max_x), min_x.max(max_x)),
+            (min_y.min(max_y), min_y.max(max_y)),
+        ));
+    }
+
+    pub fn clear_viewport(&mut self) {
+        self.settings.viewport = None;
+    }
+
+    pub fn set_view(&mut self, name: String) {
+        self.settings.view = name;
+    }
+
+    pub fn set_layer_color(&mut self, layer: i32, color_hex: String) {
+        if let Ok(col_array) = color::hex_to_rgba(&color_hex) {
+            self.settings.layer_colors.insert(layer, col_array);
+        }
+    }
+
+    pub fn reset_selection(&mut self) {
+        self.pattern.reset_selection();
+    }
+
+    // Selects the placements under the point and returns the names of their blocks
+    pub fn select_block_with_point(&mut self, point: Vec<f32>) -> Vec<String> {
+        if point.len() != 2 {
+            self.pattern.highlight_instances(&[]);
+            return vec![];
+        }
+        let point_tuple = (point[0], point[1]);
+        let instances = self
+            .pattern
+            .find_instances_with_point(&point_tuple, &self.settings);
+        self.pattern.highlight_instances(&instances);
+        return self.pattern.get_instance_block_keys(&instances);
+    }
+
+    pub fn get_closest_entity(&self, point: Vec<f32>) -> Option<pattern::EntityHit> {
+        if point.len() != 2 {
+            return None;
+        }
+        let point_tuple = (point[0], point[1]);
+        return self
+            .pattern
+            .find_closest_entity(&point_tuple, &self.settings);
+    }
+
+    pub fn select_block_with_two_points(
+        &mut self,
+        v1: Vec<f32>,
+        v2: Vec<f32>,
+    ) -> (Vec<String>, Option<bounding_box::BoundingBox>) {
+        if let Some(bbox) = bounding_box::construct_from_vectors(v1, v2) {
+            let (instances, union_bbox) = self
+                .pattern
+                .find_instances_with_bbox(&bbox, &self.settings.view);
+
+            self.pattern.highlight_instances(&instances);
+            let block_keys = self.pattern.get_instance_block_keys(&instances);
+            return (block_keys, union_bbox);
+        }
+        self.pattern.highlight_instances(&[]);
+        return (vec![], None);
+    }
+
+    pub fn select_entity_with_point(&mut self, point: Vec<f32>) -> Vec<String> {
+        let mut entity_indexes: Vec<String> = vec![];
+        if point.len() == 2 {
+            let point_tuple = (point[0], point[1]);
+            if let Some(hit) = self
+                .pattern
+                .find_closest_entity(&point_tuple, &self.settings)
+            {
+                entity_indexes.push(hit.entity_index);
+            }
+        }
+        self.pattern.highlight_entity_selection(&entity_indexes);
+        return entity_indexes;
+    }
+
+    // Dragging to the right selects entities inside the box, to the left entities touching it
+    pub fn select_entities_with_two_points(
+        &mut self,
+        v1: Vec<f32>,
+        v2: Vec<f32>,
+    ) -> (Vec<String>, Option<bounding_box::BoundingBox>) {
+        if v1.len() == 2 && v2.len() == 2 {
+            let crossing = v2[0] < v1[0];
+            if let Some(bbox) = bounding_box::construct_from_vectors(v1, v2) {
+                let (entity_indexes, union_bbox) =
+                    self.pattern
+                        .find_entities_with_bbox(&bbox, &self.settings, crossing);
+
+                self.pattern.highlight_entity_selection(&entity_indexes);
+                return (entity_indexes, union_bbox);
+            }
+        }
+        self.pattern.highlight_entity_selection(&[]);
+        return (vec![], None);
+    }
+
+    pub fn highlight_entity(&mut self, entity_index: String, status: bool) -> bool {
+        return self.pattern.set_entity_highlight(&entity_index, status);
+    }
+
+    pub fn get_highlighted_entities(&self) -> Vec<String> {
+        return self.pattern.get_highlighted_entity_indexes();
+    }
+
+    // Text of the label written on a point, labels move along when the point is edited
+    pub fn get_point_label(&self, entity_index: String) -> Option<String> {
+        return self.pattern.get_point_label(&entity_index);
+    }
+
+    pub fn disable_layer(&mut self, layer: i32) {
+        if !self.settings.disabled_layers.contains(&layer) {
+            self.settings.disabled_layers.push(layer);
+        }
+    }
+
+    pub fn enable_layer(&mut self, layer: i32) {
+        self.settings.disabled_layers.retain(|&x| x != layer);
+    }
+
+    // Selects every placement of a block, edits change the block itself
+    pub fn highlight_block(&mut self, block_key: String, status: bool) {
+        self.pattern.set_highlight(&block_key, status);
+    }
+
+    // Selects a single placement of a block by the index of its insert
+    pub fn highlight_insert(&mut self, insert_index: String, status: bool) -> bool {
+        return self.pattern.set_insert_highlight(&insert_index, status);
+    }
+
+    pub fn get_highlighted_inserts(&self) -> Vec<String> {
+        return self.pattern.get_highlighted_insert_indexes();
+    }
+
+    pub fn set_highlight_offset(&mut self, offset_x: f32, offset_y: f32) {
+        self.settings.highlight_offset = (offset_x, offset_y);
+    }
+
+    pub fn offset_highlights(&mut self) {
+        let before = self.pattern.snapshot_highlighted();
+        self.pattern
+            .offset_highlighted_objects(self.settings.highlight_offset, &self.settings.view);
+        self.record_edit(before);
+        self.settings.highlight_offset = (0., 0.);
+    }
+
+    // Moves the selection so the base point lands on the target, usually both snapped
+    pub fn offset_highlights_between(&mut self, base: Vec<f32>, target: Vec<f32>) {
+        if base.len() != 2 || target.len() != 2 {
+            return;
+        }
+        self.settings.highlight_offset = (target[0] - base[0], target[1] - base[1]);
+        self.offset_highlights();
+    }
+
+    // Closest point of the geometry on visible layers for the snap modes given by name,
+    // perpendicular snaps drop from the base point
+    pub fn snap_point(
+        &self,
+        point: Vec<f32>,
+        modes: Vec<String>,
+        base: Option<Vec<f32>>,
+    ) -> Option<snap::SnapTarget> {
+        if point.len() != 2 {
+            return None;
+        }
+        let mut snap_modes: Vec<snap::SnapMode> = vec![];
+        for name in modes.iter() {
+            match snap::SnapMode::parse(name) {
+                Some(mode) => snap_modes.push(mode),
+                None => log::warning(&format!("Unknown snap mode {}", name)),
+            }
+        }
+        let base = base.filter(|b| b.len() == 2).map(|b| (b[0], b[1]));
+        return self.pattern.find_snap(
+            &(point[0], point[1]),
+            &snap_modes,
+            base.as_ref(),
+            &self.settings,
+        );
+    }
+
+    pub fn set_highlight_scale(&mut self, scale_x: f32, scale_y: f32) {
+        self.settings.highlight_scale = (scale_x, scale_y);
+    }
+
+    pub fn set_highlight_flip(&mut self, flip_x: bool, flip_y: bool) {
+        self.settings.highlight_flip = (flip_x, flip_y);
+    }
+
+    pub fn set_highlight_rotation_center(&mut self, rot_center_x: f32, rot_center_y: f32) {
+        self.settings.highlight_rotation_center = (rot_center_x, rot_center_y);
+    }
+
+    pub fn set_highlight_rotation_angle(&mut self, angle_rad: f32) {
+        self.settings.highlight_rotation_angle = angle_rad;
+    }
+
+    pub fn set_highlight_anchor(&mut self, anchor_x: f32, anchor_y: f32) {
+        self.settings.highlight_anchor = (anchor_x, anchor_y);
+    }
+
+    pub fn scale_highlights(&mut self) {
+        let before = self.pattern.snapshot_highlighted();
+        self.pattern.scale_highlighted_objects(
+            &self.settings.highlight_scale_array(),
+            &self.settings.highlight_anchor_array(),
+            &self.settings.view,
+        );
+        self.record_edit(before);
+
+        // ToDo - make a common reset highlight function
+        self.settings.highlight_scale = (1., 1.);
+        self.settings.highlight_flip = (false, false);
+        self.settings.highlight_anchor = (0., 0.);
+        self.settings.highlight_width_height = (0., 0.);
+        self.settings.highlight_rotation_center = (0., 0.);
+    }
+
+    // Adds the seam allowance around a closed entity on the seam allowance layer, widths hold
+    // one value for every edge or one per edge and joins are miter, round or bevel
+    pub fn add_seam_allowance(
+        &mut self,
+        entity_index: String,
+        widths: Vec<f32>,
+        join: String,
+    ) -> Option<String> {
+        let join_type = match seam_allowance::JoinType::parse(&join) {
+            Some(join_type) => join_type,
+            None => {
+                log::warning(&format!("Unknown seam allowance join {}", join));
+                return None;
+            }
+        };
+        let block_name = self.pattern.get_entity_block_name(&entity_index)?;
+        let before = self.pattern.snapshot_blocks(&[block_name]);
+        let output = self.pattern.add_seam_allowance(
+            &entity_index,
+            &widths,
+            join_type,
+            self.settings.seam_allowance_layer,
+        );
+        if output.is_some() {
+            self.record_edit(before);
+        }
+        return output;
+    }
+
+    pub fn set_seam_allowance_layer(&mut self, layer: i32) {
+        self.settings.seam_allowance_layer = layer;
+    }
+
+    // Area, perimeter and lengths of the entities of a block, in pattern units as the block is
+    // drawn in the current view
+    pub fn measure_block(&self, block_name: String) -> Option<measure::BlockMeasurement> {
+        return self.pattern.measure_block(&block_name, &self.settings.view);
+    }
+
+    pub fn measure_entity(&self, entity_index: String) -> Option<measure::Measurement> {
+        return self
+            .pattern
+            .measure_entity(&entity_index, &self.settings.view);
+    }
+
+    // Distance and angle between two picks in view coordinates, each snapped to a vertex, a
+    // midpoint or a point on an entity within the point threshold. The measurement is drawn
+    // over the pattern until it is cleared
+    pub fn measure_distance(
+        &mut self,
+        start: Vec<f32>,
+        end: Vec<f32>,
+    ) -> Option<measure::DistanceMeasurement> {
+        if start.len() != 2 || end.len() != 2 {
+            return None;
+        }
+        let start = self
+            .pattern
+            .snap_pick(&(start[0], start[1]), &self.settings);
+        let end = self.pattern.snap_pick(&(end[0], end[1]), &self.settings);
+        let output = measure::measure_distance(start, end);
+        self.overlay = measure::get_overlay(&output);
+        return Some(output);
+    }
+
+    pub fn clear_measurement(&mut self) {
+        self.overlay.clear();
+    }
+
+    // Grade rules as JSON, returns the rules or points that could not be read. The pattern goes
+    // back to the base size and earlier edits can no longer be undone
+    pub fn set_grade_rules(&mut self, rules_payload: String) -> LoadReport {
+        self.history.clear();
+        return self.pattern.set_grade_rules(&rules_payload);
+    }
+
+    pub fn clear_grade_rules(&mut self) {
+        self.history.clear();
+        self.pattern.clear_grade_rules();
+    }
+
+    pub fn get_grade_sizes(&self) -> Vec<String> {
+        return match self.pattern.get_grading() {
+            Some(grading) => grading.get_sizes().clone(),
+            None => vec![],
+        };
+    }
+
+    pub fn get_active_size(&self) -> Option<String> {
+        return self
+            .pattern
+            .get_grading()
+            .map(|g| g.get_active_size().clone());
+    }
+
+    // Changing size moves the grade points, so it is undone like any other edit
+    pub fn set_active_size(&mut self, size: String) -> bool {
+        let before = self
+            .pattern
+            .snapshot_blocks(&self.pattern.get_graded_block_names());
+        let changed = self.pattern.set_active_size(&size);
+        if changed {
+            self.record_edit(before);
+        }
+        return changed;
+    }
+
+    pub fn set_grade_overlay(&mut self, show: bool) {
+        self.settings.grade_overlay = show;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if let Some(command) = self.history.undo() {
+            self.pattern.restore_changes(command, true);
+            return true;
+        }
+        return false;
+    }
+
+    pub fn redo(&mut self) -> bool {
+        if let Some(command) = self.history.redo() {
+            self.pattern.restore_changes(command, false);
+            return true;
+        }
+        return false;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return self.history.can_undo();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return self.history.can_redo();
+    }
+
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    pub fn get_pattern(&self) -> &pattern::Pattern {
+        return &self.pattern;
+    }
+
+    pub fn get_user_settings(&self) -> &user_settings::ISettings {
+        return &self.settings;
+    }
+
+    pub fn get_report(&self) -> &LoadReport {
+        return &self.load_report;
+    }
+
+    // Buffers the draw sequence is written into, read by javascript through the pointers above
+    pub fn get_buffers(&self) -> (&Vec<f32>, &Vec<u32>) {
+        return (&self.vertex_buffer.buffer, &self.index_buffer.buffer);
+    }
+
+    // Bounding box of the selection in the current view
+    pub fn get_highlighted_bounding_box(&mut self) -> Option<((f32, f32), (f32, f32))> {
+        return self
+            .pattern
+            .get_highlighted_bounding_box(&self.settings.view);
+    }
+
+    // Rotates the selection and returns the bounding box of the rotated selection
+    pub fn rotate_highlights_bbox(&mut self) -> Option<((f32, f32), (f32, f32))> {
+        let rot_matrix: &Array2<f32> = &self.settings.highlight_rot_matrix();
+        let center: &Array2<f32> = &self.settings.highlight_rot_center_array();
+        let rotate_offset: &Array2<f32> = &(center - center.dot(rot_matrix));
+
+        let before = self.pattern.snapshot_highlighted();
+        self.pattern
+            .rotate_highlights(rot_matrix, rotate_offset, &self.settings.view);
+        self.record_edit(before);
+
+        let rotated_bbox = self
+            .pattern
+            .get_highlighted_bounding_box(&self.settings.view);
+
+        self.settings.highlight_rotation_angle = 0.;
+        return rotated_bbox;
+    }
+
+    // Store what an edit changed given the state of the pattern before it was made
+    fn record_edit(&mut self, before: history::Snapshot) {
+        let command = self.pattern.get_changes(&before);
+        self.history.record(command);
+    }
+}
@@ -1,14 +1,18 @@
-use std::i32;
-
-use ndarray::Array2;
+use ndarray::{array, Array2};
+use serde::Serialize;
 
+use crate::curve::{similarity_scale, Curve};
 use crate::drawing_output::IDrawingOutput;
 use crate::drawing_parameters::IDrawingParameters;
+use crate::parse_pattern;
 use crate::user_settings;
 use crate::utils::bounding_box;
 use crate::utils::color;
+use crate::utils::geometry;
+use crate::utils::parse;
+use crate::utils::transform::Transform;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EntityTypes {
     POINT,
     POLYLINE,
@@ -17,9 +21,25 @@ pub enum EntityTypes {
     LWLINE,
     TEXT,
     INSERT,
+    ARC,
+    CIRCLE,
+    ELLIPSE,
+    SPLINE,
 }
 
-#[derive(Debug)]
+// Width of a character as a fraction of the text height
+const TEXT_WIDTH_RATIO: f32 = 0.6;
+// Chord tolerance of the vertices kept for curves, used for picking and selection
+pub const CURVE_VERTEX_TOLERANCE: f32 = 0.01;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ClosestPoint {
+    pub point: (f32, f32),
+    pub distance: f32,
+    pub segment_index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Entity {
     // Design Settings
     pub entity_type: EntityTypes,
@@ -29,6 +49,11 @@ pub struct Entity {
     pub text_height: f32,
     pub entity_index: [u8; 32],
     pub text: String,
+    // Analytic shape of curved entities, vertices then hold a fine tessellation of it
+    pub curve: Option<Curve>,
+    // Bulge of the segment starting at each vertex, positive bulges curve counter clockwise
+    // Only set along with the vertices so there is always one for each of them
+    bulges: Option<Vec<f32>>,
 
     // Cached variables
     pub bounding_box: ((f32, f32), (f32, f32)),
@@ -56,24 +81,182 @@ impl Entity {
             text_height: text_height,
             entity_index: entity_index,
             text: text,
+            curve: None,
+            bulges: None,
             bounding_box: bounding_box,
             highlighted: false,
         };
     }
 
+    // One bulge for every vertex, polylines without arc segments are stored without bulges
+    pub fn set_bulges(&mut self, bulges: Vec<f32>) {
+        self.bulges = Some(bulges)
+            .filter(|b| b.len() == self.vertices.shape()[0] && b.iter().any(|bulge| *bulge != 0.));
+        self.update_bounding_box();
+    }
+
+    pub fn has_bulges(&self) -> bool {
+        return self
+            .bulges
+            .as_ref()
+            .is_some_and(|b| b.iter().any(|bulge| *bulge != 0.));
+    }
+
+    // Bulge of the segment starting at a vertex, zero for straight segments
+    pub fn get_bulge(&self, vertex_ind: usize) -> f32 {
+        return self.bulges.as_ref().map_or(0., |b| b[vertex_ind]);
+    }
+
+    fn num_segments(&self) -> usize {
+        let num_rows = self.vertices.shape()[0];
+        if num_rows < 2 {
+            return 0;
+        }
+        return if self.shape { num_rows } else { num_rows - 1 };
+    }
+
+    fn get_segment_arc(&self, segment_ind: usize) -> Option<Curve> {
+        let bulge = self.bulges.as_ref()?[segment_ind];
+        let num_rows = self.vertices.shape()[0];
+        return Curve::from_bulge(
+            &self.vertex(segment_ind),
+            &self.vertex((segment_ind + 1) % num_rows),
+            bulge,
+        );
+    }
+
+    // Vertices with bulged segments replaced by points along their arcs, along with the
+    // segment of the original vertices each point starts
+    pub fn get_outline(&self, tolerance: f32) -> (Array2<f32>, Vec<usize>) {
+        let num_rows = self.vertices.shape()[0];
+        if !self.has_bulges() {
+            return (self.vertices.clone(), (0..num_rows).collect());
+        }
+
+        let mut vertex_data: Vec<f32> = vec![];
+        let mut segments: Vec<usize> = vec![];
+        for i in 0..self.num_segments() {
+            let (x, y) = self.vertex(i);
+            vertex_data.extend([x, y]);
+            segments.push(i);
+            if let Some(arc) = self.get_segment_arc(i) {
+                let arc_points = arc.tessellate(tolerance);
+                let n = arc_points.shape()[0];
+                // Arcs run counter clockwise, clockwise bulges are walked backwards
+                let clockwise = self.bulges.as_ref().unwrap()[i] < 0.;
+                for k in 1..n - 1 {
+                    let row = if clockwise { n - 1 - k } else { k };
+                    vertex_data.extend([arc_points[(row, 0)], arc_points[(row, 1)]]);
+                    segments.push(i);
+                }
+            }
+        }
+        if !self.shape {
+            let (x, y) = self.vertex(num_rows - 1);
+            vertex_data.extend([x, y]);
+            segments.push(num_rows - 1);
+        }
+        let num_points = segments.len();
+        let outline = Array2::from_shape_vec((num_points, 2), vertex_data).unwrap();
+        return (outline, segments);
+    }
+
+    // Box around the vertices, widened by any arc segment that bulges past them
+    fn update_bounding_box(&mut self) {
+        self.bounding_box = bounding_box::from_array(&self.vertices);
+        if !self.has_bulges() {
+            return;
+        }
+        for i in 0..self.num_segments() {
+            if let Some(arc) = self.get_segment_arc(i) {
+                self.bounding_box = bounding_box::union(&self.bounding_box, &arc.bounding_box());
+            }
+        }
+    }
+
+    // Bulges can't describe the ellipses arcs turn into under a non uniform scale,
+    // those segments become plain vertices along the arc instead
+    fn flatten_bulges(&mut self) {
+        if !self.has_bulges() {
+            self.bulges = None;
+            return;
+        }
+        let (outline, _) = self.get_outline(CURVE_VERTEX_TOLERANCE);
+        self.vertices = outline;
+        self.bulges = None;
+    }
+
+    pub fn new_curve(layer: i32, curve: Curve, entity_index: [u8; 32]) -> Entity {
+        let mut entity = Entity::new(
+            EntityTypes::LWPOLYLINE,
+            layer,
+            false,
+            Array2::zeros((0, 2)),
+            0.,
+            entity_index,
+            "".to_string(),
+        );
+        entity.set_curve(curve);
+        return entity;
+    }
+
+    // Arcs become ellipses under a non uniform scale, so the type follows the curve
+    fn set_curve(&mut self, curve: Curve) {
+        self.entity_type = match curve {
+            Curve::Arc { .. } => EntityTypes::ARC,
+            Curve::Circle { .. } => EntityTypes::CIRCLE,
+            Curve::Ellipse { .. } => EntityTypes::ELLIPSE,
+            Curve::Spline(_) => EntityTypes::SPLINE,
+        };
+        self.shape = curve.is_closed();
+        self.vertices = curve.tessellate(CURVE_VERTEX_TOLERANCE);
+        self.bounding_box = curve.bounding_box();
+        self.curve = Some(curve);
+    }
+
+    // Apply p' = p . matrix + translation to a curve, keeping the outline if the curve can't follow
+    fn transform_curve(&mut self, matrix: &Array2<f32>, translation: &Array2<f32>) {
+        let transformed = match &self.curve {
+            Some(curve) => curve.transform(matrix, translation),
+            None => return,
+        };
+        if let Some(curve) = transformed {
+            self.set_curve(curve);
+            return;
+        }
+        self.vertices = translation + &self.vertices.dot(matrix);
+        self.bounding_box = bounding_box::from_array(&self.vertices);
+        self.curve = None;
+        self.entity_type = EntityTypes::LWPOLYLINE;
+    }
+
     pub fn update_draw_sequence(
         &self,
         color: f32,
         offset: &Array2<f32>,
         draw_params: &IDrawingParameters,
         cross_size: &f32,
+        viewport: &Option<bounding_box::BoundingBox>,
         draw_output: &mut IDrawingOutput,
     ) {
-        if self.vertices.len() == 0 {
+        if self.vertices.is_empty() {
             return;
         }
 
-        let mut offset_vertices: Array2<f32> = &self.vertices + offset;
+        // Curves are drawn at the resolution asked for by the drawing pass
+        let tessellated: Array2<f32>;
+        let vertices = match &self.curve {
+            Some(curve) => {
+                tessellated = curve.tessellate(draw_params.chord_tolerance);
+                &tessellated
+            }
+            None if self.has_bulges() => {
+                tessellated = self.get_outline(draw_params.chord_tolerance).0;
+                &tessellated
+            }
+            None => &self.vertices,
+        };
+        let mut offset_vertices: Array2<f32> = vertices + offset;
 
         if self.highlighted {
             if draw_params.highlight_scale[(0, 0)] != 1.
@@ -91,16 +274,28 @@ impl Entity {
                     &draw_params.highlight_rot_offset,
                 );
             }
+            // Selection statistics include entities that end up outside the viewport
             draw_output.nr_entities += 1;
+            for v in offset_vertices.rows().into_iter() {
+                draw_output.update_min_max(&v[0], &v[1]);
+            }
         }
 
-        let num_rows: usize = self.vertices.shape()[0];
+        if let Some(viewport) = viewport {
+            let ((min_x, max_x), (min_y, max_y)) = bounding_box::from_array(&offset_vertices);
+            let drawn_bbox = (
+                (min_x - cross_size, max_x + cross_size),
+                (min_y - cross_size, max_y + cross_size),
+            );
+            if !bounding_box::intersect(&drawn_bbox, viewport) {
+                return;
+            }
+        }
+
+        let num_rows: usize = vertices.shape()[0];
         if num_rows == 1 {
             let x: f32 = offset_vertices[(0, 0)];
             let y: f32 = offset_vertices[(0, 1)];
-            if self.highlighted {
-                draw_output.update_min_max(&x, &y);
-            }
 
             // draw a cross using vertex data format x, y, r, g, b, a
             draw_output.vertex_buffer.buffer.extend([
@@ -130,9 +325,6 @@ impl Entity {
             draw_output.last_index += 4;
         } else {
             for v in offset_vertices.rows().into_iter() {
-                if self.highlighted {
-                    draw_output.update_min_max(&v[0], &v[1]);
-                }
                 draw_output.vertex_buffer.buffer.extend([v[0], v[1], color]);
                 draw_output.index_buffer.buffer.push(draw_output.last_index);
                 draw_output.last_index += 1;
@@ -151,12 +343,133 @@ impl Entity {
         }
     }
 
+    pub fn entity_index_as_uuid(&self) -> String {
+        return parse::index_as_uuid(&self.entity_index);
+    }
+
+    pub fn to_parse_entity(&self) -> parse_pattern::ParseEntity {
+        let vertices: Vec<parse_pattern::Vertex> = self
+            .vertices
+            .rows()
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| parse_pattern::Vertex {
+                x: v[0],
+                y: v[1],
+                bulge: self.bulges.as_ref().map(|b| b[i]).filter(|b| *b != 0.),
+            })
+            .collect();
+
+        let mut output = parse_pattern::ParseEntity {
+            entity_type: "".to_string(),
+            layer: self.layer.to_string(),
+            entity_index: self.entity_index_as_uuid(),
+            shape: None,
+            vertices: None,
+            position: None,
+            start_point: None,
+            ..Default::default()
+        };
+
+        if let Some((c_x, c_y)) = self.curve.as_ref().and_then(|c| c.get_center()) {
+            output.center = Some(parse_pattern::Vertex {
+                x: c_x,
+                y: c_y,
+                bulge: None,
+            });
+        }
+        match &self.curve {
+            Some(Curve::Arc {
+                radius,
+                start_angle,
+                end_angle,
+                ..
+            }) => {
+                output.entity_type = "ARC".to_string();
+                output.radius = Some(*radius);
+                output.start_angle = Some(start_angle.to_degrees());
+                output.end_angle = Some(end_angle.to_degrees());
+                return output;
+            }
+            Some(Curve::Circle { radius, .. }) => {
+                output.entity_type = "CIRCLE".to_string();
+                output.radius = Some(*radius);
+                return output;
+            }
+            Some(Curve::Ellipse {
+                major_axis, ratio, ..
+            }) => {
+                let curve = self.curve.as_ref().unwrap();
+                let start_param = curve.get_start_angle();
+                output.entity_type = "ELLIPSE".to_string();
+                output.major_axis = Some(parse_pattern::Vertex {
+                    x: major_axis.0,
+                    y: major_axis.1,
+                    bulge: None,
+                });
+                output.ratio = Some(*ratio);
+                output.start_param = Some(start_param);
+                // Full ellipses run from the start a whole turn round as in DXF
+                output.end_param = Some(start_param + curve.get_sweep());
+                return output;
+            }
+            Some(Curve::Spline(spline)) => {
+                let to_vertices = |points: &Vec<(f32, f32)>| {
+                    points
+                        .iter()
+                        .map(|(x, y)| parse_pattern::Vertex {
+                            x: *x,
+                            y: *y,
+                            bulge: None,
+                        })
+                        .collect::<Vec<parse_pattern::Vertex>>()
+                };
+                output.entity_type = "SPLINE".to_string();
+                output.shape = Some(spline.closed);
+                output.degree = Some(spline.degree as u32);
+                output.knots = Some(spline.knots.clone());
+                output.control_points = Some(to_vertices(&spline.control_points));
+                if spline.is_rational() {
+                    output.weights = Some(spline.weights.clone());
+                }
+                if !spline.fit_points.is_empty() {
+                    output.fit_points = Some(to_vertices(&spline.fit_points));
+                }
+                return output;
+            }
+            None => {}
+        }
+
+        match self.entity_type {
+            EntityTypes::POINT => {
+                output.entity_type = "POINT".to_string();
+                output.position = vertices.into_iter().next();
+            }
+            EntityTypes::TEXT => {
+                output.entity_type = "TEXT".to_string();
+                output.start_point = vertices.into_iter().next();
+                output.text_height = Some(self.text_height);
+                output.text = Some(self.text.clone());
+            }
+            EntityTypes::LINE | EntityTypes::LWLINE => {
+                output.entity_type = "LINE".to_string();
+                output.vertices = Some(vertices);
+            }
+            _ => {
+                output.entity_type = "LWPOLYLINE".to_string();
+                output.shape = Some(self.shape);
+                output.vertices = Some(vertices);
+            }
+        }
+        return output;
+    }
+
     pub fn remove_highlight(&mut self) {
         self.highlighted = false;
     }
 
     pub fn get_color(&self, settings: &user_settings::ISettings, default_color: &f32) -> f32 {
-        let mut entity_color = default_color.clone();
+        let mut entity_color = *default_color;
 
         if self.highlighted {
             entity_color = color::rbga_to_float(&settings.highlight_color);
@@ -167,6 +480,10 @@ impl Entity {
     }
 
     pub fn offset_vertices(&mut self, offset: &Array2<f32>) {
+        if self.curve.is_some() {
+            self.transform_curve(&Array2::eye(2), offset);
+            return;
+        }
         self.vertices += offset;
         self.bounding_box = bounding_box::offset_bbox(&self.bounding_box, offset);
     }
@@ -183,10 +500,28 @@ impl Entity {
     }
 
     pub fn scale_vertices(&mut self, scale: &Array2<f32>, anchor: &Array2<f32>) {
+        if self.curve.is_some() {
+            let matrix = array![[scale[(0, 0)], 0.], [0., scale[(0, 1)]]];
+            self.transform_curve(&matrix, &(anchor - &(anchor * scale)));
+            return;
+        }
+        let (scale_x, scale_y) = (scale[(0, 0)], scale[(0, 1)]);
+        if scale_x.abs() != scale_y.abs() {
+            self.flatten_bulges();
+        } else if scale_x * scale_y < 0. {
+            // A mirror turns every arc the other way round
+            if let Some(bulges) = self.bulges.as_mut() {
+                bulges.iter_mut().for_each(|b| *b = -*b);
+            }
+        }
         self.vertices -= anchor;
         self.vertices *= scale;
         self.vertices += anchor;
-        self.bounding_box = bounding_box::scale_bbox(&self.bounding_box, scale, anchor);
+        if self.has_bulges() {
+            self.update_bounding_box();
+        } else {
+            self.bounding_box = bounding_box::scale_bbox(&self.bounding_box, scale, anchor);
+        }
     }
 
     fn calculate_rotated_vertices(
@@ -200,15 +535,203 @@ impl Entity {
     }
 
     pub fn rotate_vertices(&mut self, rot_matrix: &Array2<f32>, rot_center: &Array2<f32>) {
+        if self.curve.is_some() {
+            self.transform_curve(rot_matrix, rot_center);
+            return;
+        }
         self.vertices = rot_center + self.vertices.dot(rot_matrix);
-        self.bounding_box = bounding_box::from_array(&self.vertices);
+        self.update_bounding_box();
+    }
+
+    // General affine edit p' = p . matrix + translation, as made by inserts that rotate or scale
+    pub fn transform_vertices(&mut self, matrix: &Array2<f32>, translation: &Array2<f32>) {
+        if self.curve.is_some() {
+            self.transform_curve(matrix, translation);
+            return;
+        }
+        match similarity_scale(matrix) {
+            None => self.flatten_bulges(),
+            Some((_, true)) => {
+                if let Some(bulges) = self.bulges.as_mut() {
+                    bulges.iter_mut().for_each(|b| *b = -*b);
+                }
+            }
+            Some((_, false)) => {}
+        }
+        self.vertices = translation + &self.vertices.dot(matrix);
+        self.update_bounding_box();
+    }
+
+    // Moves a single vertex, as grading does, curves keep their shape
+    pub fn move_vertex(&mut self, row: usize, offset: (f32, f32)) {
+        if self.curve.is_some() || row >= self.vertices.shape()[0] {
+            return;
+        }
+        self.vertices[(row, 0)] += offset.0;
+        self.vertices[(row, 1)] += offset.1;
+        self.update_bounding_box();
+    }
+
+    // Copy of the entity moved to where a block placement puts it
+    pub fn transformed(&self, placement: &Transform) -> Entity {
+        let mut output = self.clone();
+        output.transform_vertices(&placement.matrix, &placement.translation);
+        return output;
+    }
+
+    fn vertex(&self, ind: usize) -> (f32, f32) {
+        return (self.vertices[(ind, 0)], self.vertices[(ind, 1)]);
+    }
+
+    // Approximate outline of text, the font is not known so width is estimated from the height
+    pub fn get_text_bounding_box(&self) -> ((f32, f32), (f32, f32)) {
+        let (x, y) = self.vertex(0);
+        let width = self.text_height * TEXT_WIDTH_RATIO * self.text.chars().count() as f32;
+        return ((x, x + width), (y, y + self.text_height));
+    }
+
+    fn get_closest_point_on_text(&self, point: &(f32, f32)) -> ClosestPoint {
+        let ((min_x, max_x), (min_y, max_y)) = self.get_text_bounding_box();
+        if bounding_box::contains_point(&self.get_text_bounding_box(), point, 0.) {
+            return ClosestPoint {
+                point: *point,
+                distance: 0.,
+                segment_index: 0,
+            };
+        }
+        let closest = (point.0.clamp(min_x, max_x), point.1.clamp(min_y, max_y));
+        return ClosestPoint {
+            point: closest,
+            distance: geometry::distance(point, &closest),
+            segment_index: 0,
+        };
     }
 
-    pub fn get_closest_point_on_entity(&self) {}
+    // Point is in the same coordinates as the entity vertices
+    pub fn get_closest_point_on_entity(&self, point: &(f32, f32)) -> Option<ClosestPoint> {
+        let num_rows = self.vertices.shape()[0];
+        if num_rows == 0 {
+            return None;
+        }
+        if self.entity_type == EntityTypes::TEXT {
+            return Some(self.get_closest_point_on_text(point));
+        }
+        if num_rows == 1 {
+            return self.get_closest_defined_point_on_entity(point);
+        }
+
+        // Segment i runs from vertex i to vertex i + 1, closed shapes wrap back to the start
+        let (outline, segments) = self.get_outline(CURVE_VERTEX_TOLERANCE);
+        let outline_rows = outline.shape()[0];
+        let num_segments = if self.shape {
+            outline_rows
+        } else {
+            outline_rows - 1
+        };
+        let outline_vertex = |i: usize| (outline[(i, 0)], outline[(i, 1)]);
+        let mut output: Option<ClosestPoint> = None;
+
+        for (i, segment_index) in segments.iter().enumerate().take(num_segments) {
+            let (closest, distance) = geometry::closest_point_on_segment(
+                point,
+                &outline_vertex(i),
+                &outline_vertex((i + 1) % outline_rows),
+            );
+            if output.as_ref().is_none_or(|o| distance < o.distance) {
+                output = Some(ClosestPoint {
+                    point: closest,
+                    distance: distance,
+                    segment_index: *segment_index,
+                });
+            }
+        }
+        return output;
+    }
 
-    pub fn has_point_within_threshold(&self, threshold: f32) {}
+    pub fn has_point_within_threshold(&self, point: &(f32, f32), threshold: f32) -> bool {
+        if !bounding_box::contains_point(&self.bounding_box, point, threshold)
+            && self.entity_type != EntityTypes::TEXT
+        {
+            return false;
+        }
+        return self
+            .get_closest_point_on_entity(point)
+            .is_some_and(|c| c.distance <= threshold);
+    }
 
-    pub fn get_closest_defined_point_on_entity(&self) {}
+    // Closest vertex of the entity, segment index is the index of the vertex
+    pub fn get_closest_defined_point_on_entity(&self, point: &(f32, f32)) -> Option<ClosestPoint> {
+        let mut output: Option<ClosestPoint> = None;
 
-    pub fn has_defined_point_within_threshold(&self, threshold: f32) {}
+        for i in 0..self.vertices.shape()[0] {
+            let vertex = self.vertex(i);
+            let distance = geometry::distance(point, &vertex);
+            if output.as_ref().is_none_or(|o| distance < o.distance) {
+                output = Some(ClosestPoint {
+                    point: vertex,
+                    distance: distance,
+                    segment_index: i,
+                });
+            }
+        }
+        return output;
+    }
+
+    pub fn has_defined_point_within_threshold(&self, point: &(f32, f32), threshold: f32) -> bool {
+        if !bounding_box::contains_point(&self.bounding_box, point, threshold) {
+            return false;
+        }
+        return self
+            .get_closest_defined_point_on_entity(point)
+            .is_some_and(|c| c.distance <= threshold);
+    }
+
+    // Box used for window selection, text covers more than its insertion point
+    pub fn get_selection_bounding_box(&self) -> ((f32, f32), (f32, f32)) {
+        if self.entity_type == EntityTypes::TEXT && self.vertices.shape()[0] > 0 {
+            return self.get_text_bounding_box();
+        }
+        return self.bounding_box;
+    }
+
+    // Window selection, the whole entity is inside the box
+    pub fn inside_bbox(&self, bbox: &((f32, f32), (f32, f32))) -> bool {
+        return bounding_box::contains(bbox, &self.get_selection_bounding_box());
+    }
+
+    // Crossing selection, any part of the entity touches the box
+    pub fn intersects_bbox(&self, bbox: &((f32, f32), (f32, f32))) -> bool {
+        let num_rows = self.vertices.shape()[0];
+        if !bounding_box::intersect(&self.get_selection_bounding_box(), bbox) {
+            return false;
+        }
+        if num_rows < 2 || self.entity_type == EntityTypes::TEXT {
+            return true;
+        }
+        let (outline, _) = self.get_outline(CURVE_VERTEX_TOLERANCE);
+        let outline_rows = outline.shape()[0];
+        let outline_vertex = |i: usize| (outline[(i, 0)], outline[(i, 1)]);
+        let num_segments = if self.shape {
+            outline_rows
+        } else {
+            outline_rows - 1
+        };
+        return (0..num_segments).any(|i| {
+            geometry::segment_intersects_bbox(
+                &outline_vertex(i),
+                &outline_vertex((i + 1) % outline_rows),
+                bbox,
+            )
+        });
+    }
+
+    // Only closed shapes have an inside
+    pub fn contains_point(&self, point: &(f32, f32)) -> bool {
+        if !self.shape || !bounding_box::contains_point(&self.bounding_box, point, 0.) {
+            return false;
+        }
+        let (outline, _) = self.get_outline(CURVE_VERTEX_TOLERANCE);
+        let polygon: Vec<(f32, f32)> = outline.rows().into_iter().map(|v| (v[0], v[1])).collect();
+        return geometry::point_in_polygon(point, &polygon);
+    }
 }
@@ -0,0 +1,319 @@
+// Grading of a pattern into a nest of sizes, grade points move by a set amount for each size
+// and take the polyline vertices lying on them along
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use ndarray::Array2;
+
+use crate::block::Block;
+use crate::drawing_output::IDrawingOutput;
+use crate::drawing_parameters::IDrawingParameters;
+use crate::entity::{Entity, EntityTypes};
+use crate::load_report::{json_pointer, LoadIssue, LoadIssueKind, LoadReport};
+use crate::user_settings;
+use crate::utils::color;
+use crate::utils::geometry;
+use crate::utils::parse;
+use crate::utils::transform::Transform;
+
+// Largest distance between a point and the vertices that move with it
+pub const ATTACH_TOLERANCE: f32 = 1e-3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GradeRule {
+    // Entity index of a POINT, or the text of the label next to it
+    pub point: String,
+    // Move from the base size for each size, sizes left out do not move
+    pub moves: BTreeMap<String, (f32, f32)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GradeRules {
+    // Size the pattern is drawn in
+    pub base_size: String,
+    // Sizes of the nest, smallest first
+    pub sizes: Vec<String>,
+    pub rules: Vec<GradeRule>,
+}
+
+// Change to one vertex of an entity
+pub type VertexMove = ([u8; 32], usize, (f32, f32));
+
+#[derive(Debug, Clone, PartialEq)]
+struct GradePoint {
+    block: usize,
+    point_index: [u8; 32],
+    moves: BTreeMap<String, (f32, f32)>,
+    // Entity index and vertex of every polyline vertex on the point
+    attached: Vec<([u8; 32], usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grading {
+    base_size: String,
+    sizes: Vec<String>,
+    points: Vec<GradePoint>,
+    active_size: String,
+}
+
+fn point_position(entity: &Entity) -> Option<(f32, f32)> {
+    if entity.entity_type != EntityTypes::POINT || entity.vertices.shape()[0] == 0 {
+        return None;
+    }
+    return Some((entity.vertices[(0, 0)], entity.vertices[(0, 1)]));
+}
+
+// Points of a block the rule names, by entity index or else by the text of their label
+fn find_points(block: &Block, name: &str) -> Vec<[u8; 32]> {
+    let by_index: Vec<[u8; 32]> = block
+        .get_entities()
+        .iter()
+        .filter(|e| point_position(e).is_some())
+        .filter(|e| e.entity_index_as_uuid().replace('-', "") == name.replace('-', ""))
+        .map(|e| e.entity_index)
+        .collect();
+    if !by_index.is_empty() {
+        return by_index;
+    }
+    return block.find_labelled_points(name);
+}
+
+// Vertices of lines and polylines lying on a point, curves keep their shape and are left out
+fn attached_vertices(block: &Block, position: &(f32, f32)) -> Vec<([u8; 32], usize)> {
+    let mut output: Vec<([u8; 32], usize)> = vec![];
+    for entity in block.get_entities().iter() {
+        let is_line = matches!(
+            entity.entity_type,
+            EntityTypes::LINE
+                | EntityTypes::LWLINE
+                | EntityTypes::LWPOLYLINE
+                | EntityTypes::POLYLINE
+        );
+        if !is_line || entity.curve.is_some() {
+            continue;
+        }
+        for (row, vertex) in entity.vertices.rows().into_iter().enumerate() {
+            if geometry::distance(&(vertex[0], vertex[1]), position) <= ATTACH_TOLERANCE {
+                output.push((entity.entity_index, row));
+            }
+        }
+    }
+    return output;
+}
+
+impl Grading {
+    // Finds the points of every rule in the blocks as they are drawn in the base size
+    pub fn new(rules: &GradeRules, blocks: &[Block]) -> Result<(Grading, LoadReport), LoadReport> {
+        let mut report = LoadReport::new();
+        if !rules.sizes.contains(&rules.base_size) {
+            report.error(LoadIssue::new(
+                LoadIssueKind::InvalidGradeRule,
+                format!("Base size {} is not one of the sizes", rules.base_size),
+                json_pointer(&["base_size"]),
+            ));
+            return Err(report);
+        }
+
+        let mut points: Vec<GradePoint> = vec![];
+        for (rule_ind, rule) in rules.rules.iter().enumerate() {
+            let rule_ind = rule_ind.to_string();
+            let mut moves: BTreeMap<String, (f32, f32)> = BTreeMap::new();
+            for (size, (dx, dy)) in rule.moves.iter() {
+                let path = json_pointer(&["rules", &rule_ind, "moves", size]);
+                if !rules.sizes.contains(size) {
+                    report.warning(LoadIssue::new(
+                        LoadIssueKind::InvalidGradeRule,
+                        format!("Ignoring move for unknown size {}", size),
+                        path,
+                    ));
+                } else if !dx.is_finite() || !dy.is_finite() {
+                    report.error(LoadIssue::new(
+                        LoadIssueKind::InvalidGradeRule,
+                        format!("Move for size {} is not a number", size),
+                        path,
+                    ));
+                } else {
+                    moves.insert(size.clone(), (*dx, *dy));
+                }
+            }
+            // Moves are measured from the base size
+            let base_move = moves.get(&rules.base_size).copied().unwrap_or((0., 0.));
+            let moves: BTreeMap<String, (f32, f32)> = rules
+                .sizes
+                .iter()
+                .map(|size| {
+                    let (dx, dy) = moves.get(size).copied().unwrap_or((0., 0.));
+                    (size.clone(), (dx - base_move.0, dy - base_move.1))
+                })
+                .collect();
+
+            let mut found: Vec<GradePoint> = vec![];
+            for (block_ind, block) in blocks.iter().enumerate() {
+                for point_index in find_points(block, &rule.point) {
+                    let entity = block
+                        .get_entities()
+                        .iter()
+                        .find(|e| e.entity_index == point_index)
+                        .unwrap();
+                    let position = point_position(entity).unwrap();
+                    found.push(GradePoint {
+                        block: block_ind,
+                        point_index: point_index,
+                        moves: moves.clone(),
+                        attached: attached_vertices(block, &position),
+                    });
+                }
+            }
+
+            let path = json_pointer(&["rules", &rule_ind, "point"]);
+            if found.is_empty() {
+                report.error(LoadIssue::new(
+                    LoadIssueKind::UnknownGradePoint,
+                    format!("No point {}", rule.point),
+                    path,
+                ));
+                continue;
+            }
+            if found.len() > 1 {
+                report.warning(LoadIssue::new(
+                    LoadIssueKind::AmbiguousGradePoint,
+                    format!(
+                        "{} points found for {}, all are graded",
+                        found.len(),
+                        rule.point
+                    ),
+                    path,
+                ));
+            }
+            points.extend(found);
+        }
+
+        let grading = Grading {
+            base_size: rules.base_size.clone(),
+            sizes: rules.sizes.clone(),
+            points: points,
+            active_size: rules.base_size.clone(),
+        };
+        return Ok((grading, report));
+    }
+
+    pub fn get_sizes(&self) -> &Vec<String> {
+        return &self.sizes;
+    }
+
+    pub fn get_base_size(&self) -> &String {
+        return &self.base_size;
+    }
+
+    pub fn get_active_size(&self) -> &String {
+        return &self.active_size;
+    }
+
+    // The pattern has been changed to this size elsewhere, as when an edit is undone
+    pub fn set_active_size(&mut self, size: &String) {
+        if self.sizes.contains(size) {
+            self.active_size = size.clone();
+        }
+    }
+
+    // Blocks holding a grade point
+    pub fn get_graded_blocks(&self) -> Vec<usize> {
+        let mut output: Vec<usize> = self.points.iter().map(|p| p.block).collect();
+        output.sort();
+        output.dedup();
+        return output;
+    }
+
+    pub fn get_graded_point_indexes(&self) -> Vec<String> {
+        return self
+            .points
+            .iter()
+            .map(|p| parse::index_as_uuid(&p.point_index))
+            .collect();
+    }
+
+    // Vertex moves of each block that take the pattern from one size to another
+    pub fn get_moves(&self, from: &String, to: &String) -> BTreeMap<usize, Vec<VertexMove>> {
+        let mut output: BTreeMap<usize, Vec<VertexMove>> = BTreeMap::new();
+        for point in self.points.iter() {
+            let (from_x, from_y) = point.moves.get(from).copied().unwrap_or((0., 0.));
+            let (to_x, to_y) = point.moves.get(to).copied().unwrap_or((0., 0.));
+            let offset = (to_x - from_x, to_y - from_y);
+            if offset == (0., 0.) {
+                continue;
+            }
+            let block_moves = output.entry(point.block).or_default();
+            // A vertex lying on more than one point follows the first of them
+            for (entity_index, row) in
+                std::iter::once(&(point.point_index, 0)).chain(point.attached.iter())
+            {
+                if !block_moves
+                    .iter()
+                    .any(|m| m.0 == *entity_index && m.1 == *row)
+                {
+                    block_moves.push((*entity_index, *row, offset));
+                }
+            }
+        }
+        return output;
+    }
+
+    // Copies of the graded entities of a block in every size but the active one
+    pub fn get_nest(&self, block_ind: usize, block: &Block) -> Vec<Entity> {
+        let mut output: Vec<Entity> = vec![];
+        for size in self.sizes.iter().filter(|s| **s != self.active_size) {
+            let moves = match self.get_moves(&self.active_size, size).remove(&block_ind) {
+                Some(moves) => moves,
+                None => continue,
+            };
+            for entity in block.get_entities().iter() {
+                if !moves.iter().any(|m| m.0 == entity.entity_index) {
+                    continue;
+                }
+                let mut graded = entity.clone();
+                graded.highlighted = false;
+                for (_, row, offset) in moves.iter().filter(|m| m.0 == entity.entity_index) {
+                    graded.move_vertex(*row, *offset);
+                }
+                output.push(graded);
+            }
+        }
+        return output;
+    }
+}
+
+// Draws the other sizes of a block where a placement puts it, in the overlay color
+// The few outlines of a nest are not culled so panning never has to draw them again
+pub fn draw_nest(
+    nest: &[Entity],
+    placement: &Transform,
+    settings: &user_settings::ISettings,
+    draw_params: &IDrawingParameters,
+    draw_output: &mut IDrawingOutput,
+) {
+    let nest_color = color::rbga_to_float(&settings.grade_overlay_color);
+    for entity in nest.iter() {
+        if settings.disabled_layers.contains(&entity.layer) {
+            continue;
+        }
+        if placement.is_translation() {
+            entity.update_draw_sequence(
+                nest_color,
+                &placement.get_offset(),
+                draw_params,
+                &settings.cross_size,
+                &None,
+                draw_output,
+            );
+        } else {
+            entity.transformed(placement).update_draw_sequence(
+                nest_color,
+                &Array2::zeros((1, 2)),
+                draw_params,
+                &settings.cross_size,
+                &None,
+                draw_output,
+            );
+        }
+    }
+}
@@ -1,20 +1,12 @@
-use ndarray::Array2;
+// Javascript interface to an editor, values javascript can not hold are passed as JsValue
 use serde_wasm_bindgen::to_value;
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
-use crate::pattern;
-use crate::user_settings;
-use crate::utils::bounding_box;
-use crate::utils::color;
-use crate::utils::memory::{IndexBuffer, VertexBuffer};
+use crate::editor::Editor;
 
 #[wasm_bindgen]
 pub struct Handle {
-    pattern: pattern::Pattern,
-    settings: user_settings::ISettings,
-    vertex_buffer: VertexBuffer,
-    index_buffer: IndexBuffer,
+    editor: Editor,
 }
 
 #[wasm_bindgen]
@@ -22,187 +14,277 @@ impl Handle {
     // Constructor to initialize the struct
     #[wasm_bindgen(constructor)]
     pub fn new(pattern_payload: String, settings_payload: String) -> Handle {
-        let pattern = pattern::Pattern::new(pattern_payload);
-        let mut settings = user_settings::ISettings::default();
-
-        if let Ok(parsed_settings) = user_settings::ISettings::parse_settings(&settings_payload) {
-            settings = parsed_settings;
-        } else {
-            console::log_1(&"Settings in incorrect format".into());
-        }
-
-        let vertex_buffer: VertexBuffer = VertexBuffer::new();
-        let index_buffer: IndexBuffer = IndexBuffer::new();
+        return Handle {
+            editor: Editor::new(pattern_payload, settings_payload),
+        };
+    }
 
+    pub fn new_from_dxf(dxf_payload: String, settings_payload: String) -> Handle {
         return Handle {
-            pattern: pattern,
-            settings: settings,
-            vertex_buffer: vertex_buffer,
-            index_buffer: index_buffer,
+            editor: Editor::new_from_dxf(dxf_payload, settings_payload),
         };
     }
 
     pub fn get_number_entities(&self) -> usize {
-        return self.pattern.get_number_entities();
+        return self.editor.get_number_entities();
+    }
+
+    pub fn to_json(&self) -> String {
+        return self.editor.to_json();
+    }
+
+    pub fn export_dxf(&self) -> String {
+        return self.editor.export_dxf();
+    }
+
+    pub fn export_svg(&self) -> String {
+        return self.editor.export_svg();
+    }
+
+    // Errors and warnings from loading the pattern, each with the path of the value in the document
+    pub fn get_load_report(&self) -> JsValue {
+        return to_value(self.editor.get_report()).unwrap();
     }
 
     pub fn get_settings(&self) -> JsValue {
-        return to_value(&self.settings).unwrap();
+        return to_value(self.editor.get_user_settings()).unwrap();
     }
 
     pub fn update_draw_sequence(&mut self) {
-        let _ = &self.vertex_buffer.buffer.clear();
-        let _ = &self.index_buffer.buffer.clear();
-        self.pattern.update_draw_sequence(
-            &mut self.settings,
-            &mut self.vertex_buffer,
-            &mut self.index_buffer,
-        );
+        self.editor.update_draw_sequence();
+    }
+
+    pub fn get_changed_vertex_ranges(&self) -> Vec<u32> {
+        return self.editor.get_changed_vertex_ranges();
+    }
+
+    pub fn get_changed_index_ranges(&self) -> Vec<u32> {
+        return self.editor.get_changed_index_ranges();
     }
 
     pub fn get_all_layers(&self) -> Vec<i32> {
-        return self.pattern.get_all_layers();
+        return self.editor.get_all_layers();
     }
 
     pub fn get_all_block_names(&self) -> Vec<String> {
-        return self.pattern.get_all_block_names();
+        return self.editor.get_all_block_names();
     }
 
     pub fn get_vertex_buffer_ptr(&self) -> *const f32 {
-        return self.vertex_buffer.get_ptr();
+        return self.editor.get_vertex_buffer_ptr();
     }
 
     pub fn get_vertex_buffer_len(&self) -> usize {
-        return self.vertex_buffer.get_len();
+        return self.editor.get_vertex_buffer_len();
     }
 
     pub fn get_index_buffer_ptr(&self) -> *const u32 {
-        return self.index_buffer.get_ptr();
+        return self.editor.get_index_buffer_ptr();
     }
 
     pub fn get_index_buffer_len(&self) -> usize {
-        return self.index_buffer.get_len();
+        return self.editor.get_index_buffer_len();
+    }
+
+    pub fn set_viewport(&mut self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) {
+        self.editor.set_viewport(min_x, min_y, max_x, max_y);
+    }
+
+    pub fn clear_viewport(&mut self) {
+        self.editor.clear_viewport();
     }
 
     pub fn set_view(&mut self, name: String) {
-        self.settings.view = name;
+        self.editor.set_view(name);
     }
 
     pub fn set_layer_color(&mut self, layer: i32, color_hex: String) {
-        if let Ok(col_array) = color::hex_to_rgba(&color_hex) {
-            self.settings.layer_colors.insert(layer, col_array);
-        }
+        self.editor.set_layer_color(layer, color_hex);
     }
 
     pub fn reset_selection(&mut self) {
-        self.pattern.reset_selection();
+        self.editor.reset_selection();
     }
 
     pub fn select_block_with_point(&mut self, point: Vec<f32>) -> JsValue {
-        let empty_output: Vec<String> = vec![];
-        if point.len() != 2 {
-            self.pattern.highlight_selection(&empty_output);
-            return to_value(&empty_output).unwrap();
-        }
-        let point_tuple = (point[0], point[1]);
-        let block_keys = self
-            .pattern
-            .find_blocks_with_point(&point_tuple, &self.settings);
-        self.pattern.highlight_selection(&block_keys);
-        return to_value(&block_keys).unwrap();
+        return to_value(&self.editor.select_block_with_point(point)).unwrap();
+    }
+
+    pub fn get_closest_entity(&self, point: Vec<f32>) -> JsValue {
+        return to_value(&self.editor.get_closest_entity(point)).unwrap();
     }
 
     pub fn select_block_with_two_points(&mut self, v1: Vec<f32>, v2: Vec<f32>) -> JsValue {
-        let empty_output: Vec<String> = vec![];
-        if let Some(bbox) = bounding_box::construct_from_vectors(v1, v2) {
-            let (block_keys, union_bbox) = self
-                .pattern
-                .find_blocks_with_bbox(&bbox, &self.settings.view);
+        return to_value(&self.editor.select_block_with_two_points(v1, v2)).unwrap();
+    }
 
-            self.pattern.highlight_selection(&block_keys);
-            return to_value(&(block_keys, union_bbox)).unwrap();
-        }
-        self.pattern.highlight_selection(&empty_output);
-        return to_value(&(empty_output, ())).unwrap();
+    pub fn select_entity_with_point(&mut self, point: Vec<f32>) -> JsValue {
+        return to_value(&self.editor.select_entity_with_point(point)).unwrap();
+    }
+
+    pub fn select_entities_with_two_points(&mut self, v1: Vec<f32>, v2: Vec<f32>) -> JsValue {
+        return to_value(&self.editor.select_entities_with_two_points(v1, v2)).unwrap();
+    }
+
+    pub fn highlight_entity(&mut self, entity_index: String, status: bool) -> bool {
+        return self.editor.highlight_entity(entity_index, status);
+    }
+
+    pub fn get_highlighted_entities(&self) -> Vec<String> {
+        return self.editor.get_highlighted_entities();
+    }
+
+    pub fn get_point_label(&self, entity_index: String) -> Option<String> {
+        return self.editor.get_point_label(entity_index);
     }
 
     pub fn disable_layer(&mut self, layer: i32) {
-        if !self.settings.disabled_layers.contains(&layer) {
-            self.settings.disabled_layers.push(layer);
-        }
+        self.editor.disable_layer(layer);
     }
 
     pub fn enable_layer(&mut self, layer: i32) {
-        self.settings.disabled_layers.retain(|&x| x != layer);
+        self.editor.enable_layer(layer);
     }
 
     pub fn highlight_block(&mut self, block_key: String, status: bool) {
-        self.pattern.set_highlight(&block_key, status);
+        self.editor.highlight_block(block_key, status);
+    }
+
+    pub fn highlight_insert(&mut self, insert_index: String, status: bool) -> bool {
+        return self.editor.highlight_insert(insert_index, status);
+    }
+
+    pub fn get_highlighted_inserts(&self) -> Vec<String> {
+        return self.editor.get_highlighted_inserts();
     }
 
     pub fn set_highlight_offset(&mut self, offset_x: f32, offset_y: f32) {
-        self.settings.highlight_offset = (offset_x, offset_y);
+        self.editor.set_highlight_offset(offset_x, offset_y);
     }
 
     pub fn offset_highlights(&mut self) {
-        self.pattern
-            .offset_highlighted_objects(self.settings.highlight_offset, &self.settings.view);
-        self.settings.highlight_offset = (0., 0.);
+        self.editor.offset_highlights();
+    }
+
+    pub fn offset_highlights_between(&mut self, base: Vec<f32>, target: Vec<f32>) {
+        self.editor.offset_highlights_between(base, target);
+    }
+
+    pub fn snap_point(
+        &self,
+        point: Vec<f32>,
+        modes: Vec<String>,
+        base: Option<Vec<f32>>,
+    ) -> JsValue {
+        return to_value(&self.editor.snap_point(point, modes, base)).unwrap();
     }
 
     pub fn set_highlight_scale(&mut self, scale_x: f32, scale_y: f32) {
-        self.settings.highlight_scale = (scale_x, scale_y);
+        self.editor.set_highlight_scale(scale_x, scale_y);
     }
 
     pub fn set_highlight_flip(&mut self, flip_x: bool, flip_y: bool) {
-        self.settings.highlight_flip = (flip_x, flip_y);
+        self.editor.set_highlight_flip(flip_x, flip_y);
     }
 
     pub fn set_highlight_rotation_center(&mut self, rot_center_x: f32, rot_center_y: f32) {
-        self.settings.highlight_rotation_center = (rot_center_x, rot_center_y);
+        self.editor
+            .set_highlight_rotation_center(rot_center_x, rot_center_y);
     }
 
     pub fn set_highlight_rotation_angle(&mut self, angle_rad: f32) {
-        self.settings.highlight_rotation_angle = angle_rad;
+        self.editor.set_highlight_rotation_angle(angle_rad);
     }
 
     pub fn set_highlight_anchor(&mut self, anchor_x: f32, anchor_y: f32) {
-        self.settings.highlight_anchor = (anchor_x, anchor_y);
+        self.editor.set_highlight_anchor(anchor_x, anchor_y);
     }
 
     pub fn scale_highlights(&mut self) {
-        self.pattern.scale_highlighted_objects(
-            &self.settings.highlight_scale_array(),
-            &self.settings.highlight_anchor_array(),
-            &self.settings.view,
-        );
-
-        // ToDo - make a common reset highlight function
-        self.settings.highlight_scale = (1., 1.);
-        self.settings.highlight_flip = (false, false);
-        self.settings.highlight_anchor = (0., 0.);
-        self.settings.highlight_width_height = (0., 0.);
-        self.settings.highlight_rotation_center = (0., 0.);
+        self.editor.scale_highlights();
     }
 
     pub fn rotate_highlights(&mut self) -> JsValue {
-        let rot_matrix: &Array2<f32> = &self.settings.highlight_rot_matrix();
-        let center: &Array2<f32> = &self.settings.highlight_rot_center_array();
-        let rotate_offset: &Array2<f32> = &(center - center.dot(rot_matrix));
+        return to_value(&self.editor.rotate_highlights_bbox()).unwrap();
+    }
+
+    pub fn add_seam_allowance(
+        &mut self,
+        entity_index: String,
+        widths: Vec<f32>,
+        join: String,
+    ) -> Option<String> {
+        return self.editor.add_seam_allowance(entity_index, widths, join);
+    }
 
-        self.pattern
-            .rotate_highlights(rot_matrix, rotate_offset, &self.settings.view);
+    pub fn set_seam_allowance_layer(&mut self, layer: i32) {
+        self.editor.set_seam_allowance_layer(layer);
+    }
 
-        let rotated_bbox = self
-            .pattern
-            .get_highlighted_bounding_box(&self.settings.view);
+    pub fn measure_block(&self, block_name: String) -> JsValue {
+        return to_value(&self.editor.measure_block(block_name)).unwrap();
+    }
+
+    pub fn measure_entity(&self, entity_index: String) -> JsValue {
+        return to_value(&self.editor.measure_entity(entity_index)).unwrap();
+    }
 
-        self.settings.highlight_rotation_angle = 0.;
+    pub fn measure_distance(&mut self, start: Vec<f32>, end: Vec<f32>) -> JsValue {
+        return to_value(&self.editor.measure_distance(start, end)).unwrap();
+    }
+
+    pub fn clear_measurement(&mut self) {
+        self.editor.clear_measurement();
+    }
+
+    pub fn set_grade_rules(&mut self, rules_payload: String) -> JsValue {
+        return to_value(&self.editor.set_grade_rules(rules_payload)).unwrap();
+    }
+
+    pub fn clear_grade_rules(&mut self) {
+        self.editor.clear_grade_rules();
+    }
+
+    pub fn get_grade_sizes(&self) -> Vec<String> {
+        return self.editor.get_grade_sizes();
+    }
 
-        if rotated_bbox == Option::None {
-            return to_value(&()).unwrap();
-        } else {
-            return to_value(&rotated_bbox).unwrap();
-        }
+    pub fn get_active_size(&self) -> Option<String> {
+        return self.editor.get_active_size();
+    }
+
+    pub fn set_active_size(&mut self, size: String) -> bool {
+        return self.editor.set_active_size(size);
+    }
+
+    pub fn set_grade_overlay(&mut self, show: bool) {
+        self.editor.set_grade_overlay(show);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        return self.editor.undo();
+    }
+
+    pub fn redo(&mut self) -> bool {
+        return self.editor.redo();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return self.editor.can_undo();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return self.editor.can_redo();
+    }
+
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.editor.set_history_depth(depth);
+    }
+}
+
+// Access to the editor for Rust code holding a handle
+impl Handle {
+    pub fn get_editor(&self) -> &Editor {
+        return &self.editor;
     }
 }
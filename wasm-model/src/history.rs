@@ -0,0 +1,117 @@
+// Undo and redo of edits to the pattern, each edit stores the entities and inserts it changed
+use std::collections::VecDeque;
+
+use crate::entity::Entity;
+use crate::insert::Insert;
+
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+// State of the parts of the pattern an edit may change, only kept until it has been compared
+// with the pattern after the edit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub blocks: Vec<(String, Vec<Entity>)>,
+    // Inserts in the model and nested in blocks
+    pub inserts: Vec<Insert>,
+    // Size the graded blocks were in
+    pub grade_size: Option<String>,
+}
+
+// Entity of a block before and after an edit, missing on the side where it did not exist
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityChange {
+    pub block: String,
+    pub entity_index: [u8; 32],
+    pub before: Option<Entity>,
+    pub after: Option<Entity>,
+}
+
+// Insert before and after an edit, found again by its insert index
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertChange {
+    pub before: Insert,
+    pub after: Insert,
+}
+
+// Changes are stored whole so that restoring either side gives back exactly the same vertices
+// rather than applying an inverse
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Command {
+    pub entities: Vec<EntityChange>,
+    pub inserts: Vec<InsertChange>,
+    // Size of the graded blocks before and after
+    pub grade_size: Option<(String, String)>,
+}
+
+impl Command {
+    pub fn is_empty(&self) -> bool {
+        return self.entities.is_empty() && self.inserts.is_empty() && self.grade_size.is_none();
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    undo_stack: VecDeque<Command>,
+    redo_stack: Vec<Command>,
+    depth: usize,
+}
+
+impl History {
+    pub fn new(depth: usize) -> History {
+        return History {
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            depth: depth,
+        };
+    }
+
+    pub fn record(&mut self, command: Command) {
+        if command.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push_back(command);
+        self.trim();
+    }
+
+    // Oldest edits are forgotten first
+    fn trim(&mut self) {
+        while self.undo_stack.len() > self.depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+        // The next redo is the last entry, so the furthest undone edits are forgotten first
+        let excess = self.redo_stack.len().saturating_sub(depth);
+        self.redo_stack.drain(..excess);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo_stack.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo_stack.is_empty();
+    }
+
+    // Returns the edit to take back, if there is one
+    pub fn undo(&mut self) -> Option<&Command> {
+        let command = self.undo_stack.pop_back()?;
+        self.redo_stack.push(command);
+        return self.redo_stack.last();
+    }
+
+    pub fn redo(&mut self) -> Option<&Command> {
+        let command = self.redo_stack.pop()?;
+        self.undo_stack.push_back(command);
+        return self.undo_stack.back();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
@@ -2,13 +2,26 @@ use ndarray::{array, Array2};
 
 use crate::entity;
 use crate::parse_pattern;
+use crate::utils::parse;
+use crate::utils::transform::Transform;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Insert {
-    entity_type: entity::EntityTypes,
+    pub entity_type: entity::EntityTypes,
     pub layer: i32,
     pub name: String,
     pub position: Array2<f32>,
+    // Counter clockwise rotation in degrees, applied after scaling the block along its own axes
+    pub rotation: f32,
+    pub scale: (f32, f32),
+    // Rectangular array of copies, spaced along the rotated axes
+    pub columns: u32,
+    pub rows: u32,
+    pub spacing: (f32, f32),
+    // Stable id of this placement, set by the pattern once every insert has been read
+    pub insert_index: [u8; 32],
+    // display variables
+    pub highlighted: bool,
 }
 
 impl Insert {
@@ -18,6 +31,112 @@ impl Insert {
             layer: layer,
             name: name,
             position: array![[position.x, position.y]],
+            rotation: 0.,
+            scale: (1., 1.),
+            columns: 1,
+            rows: 1,
+            spacing: (0., 0.),
+            insert_index: [0u8; 32],
+            highlighted: false,
         };
     }
+
+    // Layer has to be checked by the caller
+    pub fn from_parse_insert(parsed: &parse_pattern::ParseInsertEntity, layer: i32) -> Insert {
+        let mut insert = Insert::new(parsed.name.clone(), layer, &parsed.position);
+        insert.rotation = parsed.rotation.unwrap_or(0.);
+        insert.scale = (parsed.scale_x.unwrap_or(1.), parsed.scale_y.unwrap_or(1.));
+        insert.columns = parsed.columns.unwrap_or(1).max(1);
+        insert.rows = parsed.rows.unwrap_or(1).max(1);
+        insert.spacing = (
+            parsed.column_spacing.unwrap_or(0.),
+            parsed.row_spacing.unwrap_or(0.),
+        );
+        return insert;
+    }
+
+    pub fn to_parse_insert(&self) -> parse_pattern::ParseInsertEntity {
+        // Defaults are left out so plain inserts are written as before
+        let non_default = |value: f32, default: f32| Some(value).filter(|v| *v != default);
+        return parse_pattern::ParseInsertEntity {
+            entity_type: "INSERT".to_string(),
+            name: self.name.clone(),
+            position: parse_pattern::Vertex {
+                x: self.position[(0, 0)],
+                y: self.position[(0, 1)],
+                bulge: None,
+            },
+            layer: self.layer.to_string(),
+            insert_index: Some(self.insert_index_as_uuid()),
+            rotation: non_default(self.rotation, 0.),
+            scale_x: non_default(self.scale.0, 1.),
+            scale_y: non_default(self.scale.1, 1.),
+            columns: Some(self.columns).filter(|c| *c != 1),
+            rows: Some(self.rows).filter(|r| *r != 1),
+            column_spacing: non_default(self.spacing.0, 0.),
+            row_spacing: non_default(self.spacing.1, 0.),
+        };
+    }
+
+    pub fn insert_index_as_uuid(&self) -> String {
+        return parse::index_as_uuid(&self.insert_index);
+    }
+
+    pub fn is_array(&self) -> bool {
+        return self.columns > 1 || self.rows > 1;
+    }
+
+    fn get_rotation_matrix(&self) -> Array2<f32> {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        return array![[cos, sin], [-sin, cos]];
+    }
+
+    // Scale followed by rotation, for row vectors
+    pub fn get_matrix(&self) -> Array2<f32> {
+        return array![[self.scale.0, 0.], [0., self.scale.1]].dot(&self.get_rotation_matrix());
+    }
+
+    // Map from block coordinates into the space holding the insert, one per array copy
+    pub fn get_transforms(&self) -> Vec<Transform> {
+        let matrix = self.get_matrix();
+        let rotation = self.get_rotation_matrix();
+        let mut output: Vec<Transform> = vec![];
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let cell = array![[column as f32 * self.spacing.0, row as f32 * self.spacing.1]];
+                output.push(Transform::new(
+                    matrix.clone(),
+                    cell.dot(&rotation) + &self.position,
+                ));
+            }
+        }
+        return output;
+    }
+
+    // Take position, scale and rotation from the placement of the first copy, a placement
+    // that shears the block can not be written as an insert and is left alone
+    pub fn set_placement(&mut self, placement: &Transform) -> bool {
+        let m = &placement.matrix;
+        let scale_x = m[(0, 0)].hypot(m[(0, 1)]);
+        if scale_x < f32::EPSILON {
+            return false;
+        }
+        let (sin, cos) = (m[(0, 1)] / scale_x, m[(0, 0)] / scale_x);
+        let scale_y = -m[(1, 0)] * sin + m[(1, 1)] * cos;
+        let shear = m[(1, 0)] * cos + m[(1, 1)] * sin;
+        if scale_y.abs() < f32::EPSILON || shear.abs() > 1e-4 * scale_x.max(scale_y.abs()) {
+            return false;
+        }
+        // Array spacing is not scaled with the block, so it is stretched along with it here
+        if self.scale.0 != 0. && self.scale.1 != 0. {
+            self.spacing = (
+                self.spacing.0 * scale_x / self.scale.0,
+                self.spacing.1 * scale_y / self.scale.1,
+            );
+        }
+        self.rotation = sin.atan2(cos).to_degrees();
+        self.scale = (scale_x, scale_y);
+        self.position = placement.translation.clone();
+        return true;
+    }
 }
@@ -1,34 +1,56 @@
+// The codebase favours explicit returns and field names
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+#[cfg(feature = "wasm")]
 use serde_wasm_bindgen::to_value;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
 pub mod block;
+// Only the command line binary uses it, so it stays out of the wasm build
+#[cfg(feature = "native")]
+pub mod cli;
+pub mod curve;
+pub mod draw_cache;
 pub mod drawing_output;
 pub mod drawing_parameters;
+pub mod editor;
 pub mod entity;
+pub mod grading;
+#[cfg(feature = "wasm")]
 pub mod handle;
+pub mod history;
 pub mod insert;
+pub mod load_report;
+pub mod measure;
+pub mod parse_dxf;
 pub mod parse_pattern;
 pub mod pattern;
+pub mod seam_allowance;
+pub mod snap;
+pub mod spline;
 pub mod user_settings;
 pub mod utils;
+pub mod write_dxf;
+pub mod write_svg;
 
+// Check a pattern document and return what could not be read
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
-pub fn read_pattern(json_payload: &str) -> bool {
-    if let Ok(_pattern) = parse_pattern::parse_pattern(json_payload) {
-        console::log_1(&"Parsed the pattern".into());
-        return true;
-    } else {
-        console::log_1(&"Parsing failed".into());
-        return false;
-    }
+pub fn read_pattern(json_payload: &str) -> JsValue {
+    let report = match pattern::Pattern::try_new(json_payload) {
+        Ok((_, report)) | Err(report) => report,
+    };
+    return to_value(&report).unwrap();
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn greet(name: &str) {
-    console::log_1(&format!("Hello to the console {}", name).into());
+    utils::log::info(&format!("Hello to the console {}", name));
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn get_buffer_udpate() -> JsValue {
     return to_value(&vec![1., 1., 1., 10.]).unwrap();
@@ -0,0 +1,129 @@
+// Problems met while loading a pattern, so the user can be told what was left out
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadIssueKind {
+    // The document could not be read at all
+    InvalidDocument,
+    InvalidLayer,
+    InvalidEntityIndex,
+    UnknownEntityType,
+    // Entity fields missing or out of range for its type
+    InvalidGeometry,
+    InvalidInsert,
+    UnknownBlock,
+    DuplicateInsertIndex,
+    // Grade rules, with paths into the rules document
+    InvalidGradeRule,
+    UnknownGradePoint,
+    AmbiguousGradePoint,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LoadIssue {
+    pub kind: LoadIssueKind,
+    pub reason: String,
+    // JSON pointer to the offending value in the pattern document
+    pub path: String,
+    pub block_key: Option<String>,
+    pub entity_index: Option<String>,
+}
+
+// Errors are parts of the document that were left out, warnings were loaded with a change
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct LoadReport {
+    pub errors: Vec<LoadIssue>,
+    pub warnings: Vec<LoadIssue>,
+}
+
+// Segments are escaped as in RFC 6901 so block keys may hold any character
+pub fn json_pointer(segments: &[&str]) -> String {
+    let mut output = String::new();
+    for segment in segments.iter() {
+        output.push('/');
+        output.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+    return output;
+}
+
+pub fn entity_path(block_key: &str, position: usize, field: Option<&str>) -> String {
+    let position = position.to_string();
+    let mut segments = vec!["pattern_json", "blocks", block_key, "entities", &position];
+    segments.extend(field);
+    return json_pointer(&segments);
+}
+
+// Inserts are stored in the model, or in a block when one is given
+pub fn insert_path(block_key: Option<&str>, position: usize) -> String {
+    let position = position.to_string();
+    return match block_key {
+        Some(block_key) => {
+            json_pointer(&["pattern_json", "blocks", block_key, "inserts", &position])
+        }
+        None => json_pointer(&["pattern_json", "entities", &position]),
+    };
+}
+
+impl LoadIssue {
+    pub fn new(kind: LoadIssueKind, reason: String, path: String) -> LoadIssue {
+        return LoadIssue {
+            kind: kind,
+            reason: reason,
+            path: path,
+            block_key: None,
+            entity_index: None,
+        };
+    }
+
+    pub fn in_block(mut self, block_key: &str) -> LoadIssue {
+        self.block_key = Some(block_key.to_string());
+        return self;
+    }
+
+    pub fn for_entity(mut self, entity_index: &str) -> LoadIssue {
+        self.entity_index = Some(entity_index.to_string());
+        return self;
+    }
+}
+
+impl LoadReport {
+    pub fn new() -> LoadReport {
+        return LoadReport {
+            errors: vec![],
+            warnings: vec![],
+        };
+    }
+
+    // Report for a document that could not be read
+    pub fn failed(reason: String) -> LoadReport {
+        let mut report = LoadReport::new();
+        report.error(LoadIssue::new(
+            LoadIssueKind::InvalidDocument,
+            reason,
+            json_pointer(&[]),
+        ));
+        return report;
+    }
+
+    pub fn error(&mut self, issue: LoadIssue) {
+        self.errors.push(issue);
+    }
+
+    pub fn warning(&mut self, issue: LoadIssue) {
+        self.warnings.push(issue);
+    }
+
+    pub fn is_clean(&self) -> bool {
+        return self.errors.is_empty() && self.warnings.is_empty();
+    }
+
+    // One line per issue, errors first
+    pub fn get_messages(&self) -> Vec<String> {
+        let errors = self.errors.iter().map(|i| ("Error", i));
+        let warnings = self.warnings.iter().map(|i| ("Warning", i));
+        return errors
+            .chain(warnings)
+            .map(|(severity, issue)| format!("{} at {}: {}", severity, issue.path, issue.reason))
+            .collect();
+    }
+}
@@ -0,0 +1,216 @@
+// Areas and lengths of entities in pattern units, used to estimate the fabric a piece takes
+use ndarray::array;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{Entity, EntityTypes};
+use crate::utils::geometry;
+use crate::utils::parse;
+
+// Largest distance between a curve and the chords it is measured along
+const MEASURE_TOLERANCE: f32 = 1e-4;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub entity_index: String,
+    pub closed: bool,
+    // Positive when a closed entity runs counter clockwise
+    pub area: Option<f32>,
+    pub perimeter: Option<f32>,
+    // Length along an open entity
+    pub length: Option<f32>,
+    // Length of each segment between vertices, a curve is a single segment
+    pub segment_lengths: Vec<f32>,
+    // Centre of the area of a closed entity
+    pub centroid: Option<(f32, f32)>,
+}
+
+// What a pick of the measuring tool was snapped to, in order of preference
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickSnap {
+    Vertex,
+    Midpoint,
+    OnEntity,
+    // Nothing within the point threshold, the pick is used as it is
+    Free,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnappedPick {
+    pub point: (f32, f32),
+    pub snap: PickSnap,
+    pub entity_index: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DistanceMeasurement {
+    pub start: SnappedPick,
+    pub end: SnappedPick,
+    pub distance: f32,
+    pub dx: f32,
+    pub dy: f32,
+    // Direction from start to end in degrees, counterclockwise from the x axis
+    pub angle: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockMeasurement {
+    pub name: String,
+    // The closed entity enclosing the most area, taken as the outline of the piece
+    pub outline: Option<Measurement>,
+    pub entities: Vec<Measurement>,
+}
+
+// Points along an entity, the segment each point starts and the number of segments
+fn measured_points(entity: &Entity) -> (Vec<(f32, f32)>, Vec<usize>, usize) {
+    let (points, segments, num_segments) = match &entity.curve {
+        Some(curve) => {
+            let points = curve.tessellate(MEASURE_TOLERANCE);
+            let num_rows = points.shape()[0];
+            (points, vec![0; num_rows], 1)
+        }
+        None => {
+            let (points, segments) = entity.get_outline(MEASURE_TOLERANCE);
+            let num_rows = entity.vertices.shape()[0];
+            let num_segments = if entity.shape {
+                num_rows
+            } else {
+                num_rows.saturating_sub(1)
+            };
+            (points, segments, num_segments)
+        }
+    };
+    let points: Vec<(f32, f32)> = points.rows().into_iter().map(|v| (v[0], v[1])).collect();
+    return (points, segments, num_segments);
+}
+
+// Measures lines, polylines and curves, points and texts have nothing to measure
+pub fn measure_entity(entity: &Entity) -> Option<Measurement> {
+    if matches!(entity.entity_type, EntityTypes::POINT | EntityTypes::TEXT) {
+        return None;
+    }
+    let (points, segments, num_segments) = measured_points(entity);
+    let n = points.len();
+    if n < 2 || num_segments == 0 {
+        return None;
+    }
+
+    let closed = entity.shape;
+    let num_edges = if closed { n } else { n - 1 };
+    let mut segment_lengths: Vec<f32> = vec![0.; num_segments];
+    for i in 0..num_edges {
+        segment_lengths[segments[i]] += geometry::distance(&points[i], &points[(i + 1) % n]);
+    }
+    let total_length: f32 = segment_lengths.iter().sum();
+
+    return Some(Measurement {
+        entity_index: parse::index_as_uuid(&entity.entity_index),
+        closed: closed,
+        area: closed.then(|| geometry::signed_area(&points)),
+        perimeter: closed.then_some(total_length),
+        length: (!closed).then_some(total_length),
+        segment_lengths: segment_lengths,
+        centroid: if closed {
+            geometry::polygon_centroid(&points)
+        } else {
+            None
+        },
+    });
+}
+
+pub fn measure_block(name: &str, entities: &[Entity]) -> BlockMeasurement {
+    let measurements: Vec<Measurement> = entities.iter().filter_map(measure_entity).collect();
+    let outline = measurements
+        .iter()
+        .filter(|m| m.area.is_some_and(|a| a != 0.))
+        .max_by(|a, b| {
+            let (area_a, area_b) = (a.area.unwrap().abs(), b.area.unwrap().abs());
+            area_a.total_cmp(&area_b)
+        })
+        .cloned();
+    return BlockMeasurement {
+        name: name.to_string(),
+        outline: outline,
+        entities: measurements,
+    };
+}
+
+// Point half way along a run of points
+fn half_way(points: &[(f32, f32)]) -> Option<(f32, f32)> {
+    let total: f32 = points
+        .windows(2)
+        .map(|w| geometry::distance(&w[0], &w[1]))
+        .sum();
+    let mut remaining = total / 2.;
+    for w in points.windows(2) {
+        let length = geometry::distance(&w[0], &w[1]);
+        if length > 0. && remaining <= length {
+            let t = remaining / length;
+            return Some((
+                w[0].0 + (w[1].0 - w[0].0) * t,
+                w[0].1 + (w[1].1 - w[0].1) * t,
+            ));
+        }
+        remaining -= length;
+    }
+    return None;
+}
+
+// Middle of each segment, measured along arcs and curves. Closed curves have no middle
+pub fn get_midpoints(entity: &Entity) -> Vec<(f32, f32)> {
+    if matches!(entity.entity_type, EntityTypes::POINT | EntityTypes::TEXT)
+        || (entity.curve.is_some() && entity.shape)
+    {
+        return vec![];
+    }
+    let (points, segments, num_segments) = measured_points(entity);
+    let n = points.len();
+    let num_edges = if entity.shape { n } else { n.saturating_sub(1) };
+    let mut output: Vec<(f32, f32)> = vec![];
+    for segment in 0..num_segments {
+        let mut run: Vec<(f32, f32)> = vec![];
+        for i in (0..num_edges).filter(|i| segments[*i] == segment) {
+            if run.is_empty() {
+                run.push(points[i]);
+            }
+            run.push(points[(i + 1) % n]);
+        }
+        output.extend(half_way(&run));
+    }
+    return output;
+}
+
+pub fn measure_distance(start: SnappedPick, end: SnappedPick) -> DistanceMeasurement {
+    let (dx, dy) = (end.point.0 - start.point.0, end.point.1 - start.point.1);
+    return DistanceMeasurement {
+        start: start,
+        end: end,
+        distance: (dx * dx + dy * dy).sqrt(),
+        dx: dx,
+        dy: dy,
+        angle: dy.atan2(dx).to_degrees(),
+    };
+}
+
+// Line between the two picks with a cross on each, drawn until the measurement is cleared
+pub fn get_overlay(measurement: &DistanceMeasurement) -> Vec<Entity> {
+    let (start, end) = (measurement.start.point, measurement.end.point);
+    let overlay_entity = |entity_type: EntityTypes, vertices| {
+        return Entity::new(
+            entity_type,
+            0,
+            false,
+            vertices,
+            0.,
+            [0u8; 32],
+            "".to_string(),
+        );
+    };
+    return vec![
+        overlay_entity(
+            EntityTypes::LINE,
+            array![[start.0, start.1], [end.0, end.1]],
+        ),
+        overlay_entity(EntityTypes::POINT, array![[start.0, start.1]]),
+        overlay_entity(EntityTypes::POINT, array![[end.0, end.1]]),
+    ];
+}
@@ -0,0 +1,641 @@
+// Reader for ASCII DXF files (R12 to R2018) into the same parse structures as the json format
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, ParsePattern, Vertex,
+};
+use crate::write_dxf::XDATA_APP_NAME;
+
+// Block that holds entities found directly in the ENTITIES section
+pub const MODEL_SPACE_BLOCK: &str = "*Model_Space";
+
+const SUPPORTED_VERSIONS: [&str; 9] = [
+    "AC1009", // R12
+    "AC1012", // R13
+    "AC1014", // R14
+    "AC1015", // R2000
+    "AC1018", // R2004
+    "AC1021", // R2007
+    "AC1024", // R2010
+    "AC1027", // R2013
+    "AC1032", // R2018
+];
+
+type GroupCode = (i32, String);
+
+// Entity as a flat list of group codes following its type name
+#[derive(Debug)]
+struct DxfEntity {
+    entity_type: String,
+    groups: Vec<GroupCode>,
+    // Only populated for old style POLYLINE, one set of groups per VERTEX
+    vertices: Vec<Vec<GroupCode>>,
+}
+
+impl DxfEntity {
+    fn get(&self, code: i32) -> Option<&str> {
+        return self
+            .groups
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, v)| v.as_str());
+    }
+
+    fn get_f32(&self, code: i32) -> Option<f32> {
+        return self
+            .get(code)
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|v| v as f32);
+    }
+
+    fn get_i32(&self, code: i32) -> Option<i32> {
+        return self.get(code).and_then(|v| v.parse::<i32>().ok());
+    }
+
+    fn get_vertex(&self, x_code: i32) -> Option<Vertex> {
+        let x = self.get_f32(x_code)?;
+        let y = self.get_f32(x_code + 10)?;
+        return Some(Vertex {
+            x: x,
+            y: y,
+            bulge: None,
+        });
+    }
+
+    fn layer(&self) -> &str {
+        return self.get(8).unwrap_or("0");
+    }
+
+    // Extended data groups written by the editor, empty if the entity has none
+    fn xdata(&self) -> &[GroupCode] {
+        let app = (1001, XDATA_APP_NAME.to_string());
+        if let Some(start) = self.groups.iter().position(|g| g == &app) {
+            let rest = &self.groups[start + 1..];
+            let end = rest
+                .iter()
+                .position(|(c, _)| *c == 1001)
+                .unwrap_or(rest.len());
+            return &rest[..end];
+        }
+        return &[];
+    }
+
+    fn xdata_f32(&self, code: i32) -> Option<f32> {
+        return self
+            .xdata()
+            .iter()
+            .find(|(c, _)| *c == code)
+            .and_then(|(_, v)| v.parse::<f64>().ok())
+            .map(|v| v as f32);
+    }
+}
+
+// Pattern layers are numbered while DXF layers are named. Names that are numbers keep their
+// number, the others are numbered after the largest of those in the order they first appear
+struct LayerIds {
+    ids: HashMap<String, i32>,
+}
+
+impl LayerIds {
+    fn new(names: &[String]) -> LayerIds {
+        let mut ids: HashMap<String, i32> = HashMap::new();
+        for name in names.iter() {
+            if let Some(id) = numbered_layer(name) {
+                ids.insert(name.clone(), id);
+            }
+        }
+        let mut next_id = ids.values().max().map_or(1, |id| id + 1);
+        for name in names.iter() {
+            if !ids.contains_key(name) {
+                ids.insert(name.clone(), next_id);
+                next_id += 1;
+            }
+        }
+        return LayerIds { ids: ids };
+    }
+
+    fn get_id(&self, name: &str) -> String {
+        return self.ids.get(name).copied().unwrap_or(0).to_string();
+    }
+
+    // Names that are not simply the number of their layer, written back on export
+    fn get_names(&self) -> BTreeMap<i32, String> {
+        return self
+            .ids
+            .iter()
+            .filter(|(name, _)| numbered_layer(name).is_none())
+            .map(|(name, id)| (*id, name.clone()))
+            .collect();
+    }
+}
+
+// Layer number of a name that is written the way the number would be
+fn numbered_layer(name: &str) -> Option<i32> {
+    return name.parse::<i32>().ok().filter(|id| id.to_string() == name);
+}
+
+fn tokenize(dxf_str: &str) -> Result<Vec<GroupCode>, String> {
+    if dxf_str.starts_with("AutoCAD Binary DXF") {
+        return Err("Binary DXF is not supported".into());
+    }
+
+    let mut lines = dxf_str.lines();
+    let mut output: Vec<GroupCode> = vec![];
+    let mut line_nr: usize = 0;
+
+    while let Some(code_line) = lines.next() {
+        line_nr += 1;
+        let code_str = code_line.trim();
+        if code_str.is_empty() {
+            continue;
+        }
+        let code = code_str
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid group code '{}' on line {}", code_str, line_nr))?;
+        let value = lines.next().ok_or(format!(
+            "Missing value for group code {} on line {}",
+            code, line_nr
+        ))?;
+        line_nr += 1;
+        output.push((code, value.trim().to_string()));
+    }
+    return Ok(output);
+}
+
+// Split a run of group codes into entities, grouping POLYLINE vertices with their parent
+fn collect_entities(groups: &[GroupCode]) -> Vec<DxfEntity> {
+    let mut output: Vec<DxfEntity> = vec![];
+    let mut in_polyline = false;
+    let mut skip_groups = false;
+
+    for (code, value) in groups.iter() {
+        if *code == 0 {
+            skip_groups = false;
+            if value == "VERTEX" && in_polyline {
+                if let Some(polyline) = output.last_mut() {
+                    polyline.vertices.push(vec![]);
+                }
+                continue;
+            }
+            if value == "SEQEND" {
+                in_polyline = false;
+                skip_groups = true;
+                continue;
+            }
+            in_polyline = value == "POLYLINE";
+            output.push(DxfEntity {
+                entity_type: value.clone(),
+                groups: vec![],
+                vertices: vec![],
+            });
+        } else if skip_groups {
+            continue;
+        } else if let Some(entity) = output.last_mut() {
+            if let Some(vertex) = entity.vertices.last_mut() {
+                vertex.push((*code, value.clone()));
+            } else {
+                entity.groups.push((*code, value.clone()));
+            }
+        }
+    }
+    return output;
+}
+
+// Index saved by the editor if present, otherwise the handle padded with zeros
+fn saved_index(entity: &DxfEntity) -> Option<String> {
+    if let Some((_, index)) = entity.xdata().iter().find(|(c, _)| *c == 1000) {
+        return Some(index.clone());
+    }
+    if let Some(handle) = entity.get(5) {
+        if !handle.is_empty() && handle.len() <= 32 && handle.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Some(format!("{:0>32}", handle.to_lowercase()));
+        }
+    }
+    return None;
+}
+
+// Entity index is a 32 character string, numbered in order when the file has none
+fn entity_index_from_handle(entity: &DxfEntity, counter: &mut usize) -> String {
+    if let Some(index) = saved_index(entity) {
+        return index;
+    }
+    *counter += 1;
+    return format!("{:0>32x}", *counter);
+}
+
+// Straight segments are stored without a bulge
+fn non_zero_bulge(bulge: f32) -> Option<f32> {
+    if bulge == 0. {
+        return None;
+    }
+    return Some(bulge);
+}
+
+fn convert_entity(
+    entity: &DxfEntity,
+    layers: &LayerIds,
+    counter: &mut usize,
+) -> Option<ParseEntity> {
+    let mut output = ParseEntity {
+        entity_type: entity.entity_type.clone(),
+        layer: layers.get_id(entity.layer()),
+        entity_index: entity_index_from_handle(entity, counter),
+        ..Default::default()
+    };
+
+    match entity.entity_type.as_str() {
+        "POINT" => {
+            output.position = Some(entity.get_vertex(10)?);
+        }
+        "LINE" => {
+            output.vertices = Some(vec![entity.get_vertex(10)?, entity.get_vertex(11)?]);
+        }
+        "LWPOLYLINE" => {
+            // Each vertex starts with its x, the y and optional bulge follow it
+            let mut vertices: Vec<Vertex> = vec![];
+            for (code, value) in entity.groups.iter() {
+                match code {
+                    10 => vertices.push(Vertex {
+                        x: value.parse::<f64>().ok()? as f32,
+                        y: 0.,
+                        bulge: None,
+                    }),
+                    20 => vertices.last_mut()?.y = value.parse::<f64>().ok()? as f32,
+                    42 if !vertices.is_empty() => {
+                        vertices.last_mut()?.bulge =
+                            non_zero_bulge(value.parse::<f64>().ok()? as f32)
+                    }
+                    _ => {}
+                }
+            }
+            output.shape = Some(entity.get_i32(70).unwrap_or(0) & 1 == 1);
+            output.vertices = Some(vertices);
+        }
+        "POLYLINE" => {
+            let mut vertices: Vec<Vertex> = vec![];
+            for vertex_groups in entity.vertices.iter() {
+                let vertex = DxfEntity {
+                    entity_type: "VERTEX".to_string(),
+                    groups: vertex_groups.clone(),
+                    vertices: vec![],
+                };
+                let mut position = vertex.get_vertex(10)?;
+                position.bulge = non_zero_bulge(vertex.get_f32(42).unwrap_or(0.));
+                vertices.push(position);
+            }
+            output.shape = Some(entity.get_i32(70).unwrap_or(0) & 1 == 1);
+            output.vertices = Some(vertices);
+        }
+        "TEXT" => {
+            output.start_point = Some(entity.get_vertex(10)?);
+            output.text_height = Some(entity.get_f32(40)?);
+            output.text = Some(entity.get(1).unwrap_or("").to_string());
+        }
+        "SPLINE" => {
+            // Knots, weights, control points and fit points are repeated groups in order
+            let mut knots: Vec<f32> = vec![];
+            let mut weights: Vec<f32> = vec![];
+            let mut control_points: Vec<Vertex> = vec![];
+            let mut fit_points: Vec<Vertex> = vec![];
+            for (code, value) in entity.groups.iter() {
+                let parse_value = || value.parse::<f64>().ok().map(|v| v as f32);
+                match code {
+                    40 => knots.push(parse_value()?),
+                    41 => weights.push(parse_value()?),
+                    10 => control_points.push(Vertex {
+                        x: parse_value()?,
+                        y: 0.,
+                        bulge: None,
+                    }),
+                    20 => control_points.last_mut()?.y = parse_value()?,
+                    11 => fit_points.push(Vertex {
+                        x: parse_value()?,
+                        y: 0.,
+                        bulge: None,
+                    }),
+                    21 => fit_points.last_mut()?.y = parse_value()?,
+                    _ => {}
+                }
+            }
+            let flags = entity.get_i32(70).unwrap_or(0);
+            output.shape = Some(flags & 1 == 1);
+            output.degree = Some(entity.get_i32(71)? as u32);
+            output.knots = Some(knots).filter(|k| !k.is_empty());
+            output.weights = Some(weights).filter(|w| !w.is_empty());
+            output.control_points = Some(control_points).filter(|c| !c.is_empty());
+            output.fit_points = Some(fit_points).filter(|f| !f.is_empty());
+        }
+        "ELLIPSE" => {
+            output.center = Some(entity.get_vertex(10)?);
+            output.major_axis = Some(entity.get_vertex(11)?);
+            output.ratio = Some(entity.get_f32(40)?);
+            output.start_param = Some(entity.get_f32(41).unwrap_or(0.));
+            output.end_param = Some(entity.get_f32(42).unwrap_or(std::f32::consts::TAU));
+        }
+        "CIRCLE" => {
+            output.center = Some(entity.get_vertex(10)?);
+            output.radius = Some(entity.get_f32(40)?);
+        }
+        "ARC" => {
+            output.center = Some(entity.get_vertex(10)?);
+            output.radius = Some(entity.get_f32(40)?);
+            output.start_angle = Some(entity.get_f32(50)?);
+            output.end_angle = Some(entity.get_f32(51)?);
+        }
+        _ => return None,
+    }
+    return Some(output);
+}
+
+// Insert as written in the file, the base point of the block still has to be taken off
+fn read_insert(entity: &DxfEntity, layers: &LayerIds) -> ParseInsertEntity {
+    let count = |code: i32| entity.get_i32(code).filter(|c| *c > 1).map(|c| c as u32);
+    return ParseInsertEntity {
+        entity_type: "INSERT".to_string(),
+        name: entity.get(2).unwrap_or("").to_string(),
+        position: entity.get_vertex(10).unwrap_or(Vertex {
+            x: 0.,
+            y: 0.,
+            bulge: None,
+        }),
+        layer: layers.get_id(entity.layer()),
+        // Inserts without one are given an index when the pattern is built
+        insert_index: saved_index(entity),
+        rotation: entity.get_f32(50).filter(|r| *r != 0.),
+        scale_x: entity.get_f32(41).filter(|s| *s != 1.),
+        scale_y: entity.get_f32(42).filter(|s| *s != 1.),
+        columns: count(70),
+        rows: count(71),
+        column_spacing: entity.get_f32(44).filter(|s| *s != 0.),
+        row_spacing: entity.get_f32(45).filter(|s| *s != 0.),
+    };
+}
+
+// Block geometry is stored relative to origin, so move the base point onto the insert
+fn move_base_point_onto_insert(
+    insert: &mut ParseInsertEntity,
+    base_points: &HashMap<String, Vertex>,
+) {
+    if let Some(base) = base_points.get(&insert.name) {
+        let (sin, cos) = insert.rotation.unwrap_or(0.).to_radians().sin_cos();
+        let (x, y) = (
+            base.x * insert.scale_x.unwrap_or(1.),
+            base.y * insert.scale_y.unwrap_or(1.),
+        );
+        insert.position.x -= x * cos - y * sin;
+        insert.position.y -= x * sin + y * cos;
+    }
+}
+
+fn read_header(groups: &[GroupCode]) -> Result<(), String> {
+    let mut iter = groups.iter();
+    while let Some((code, value)) = iter.next() {
+        if *code == 9 && value == "$ACADVER" {
+            if let Some((_, version)) = iter.next() {
+                if !SUPPORTED_VERSIONS.contains(&version.as_str()) {
+                    return Err(format!("Unsupported DXF version {}", version));
+                }
+            }
+        }
+    }
+    return Ok(());
+}
+
+fn read_layer_table(groups: &[GroupCode]) -> Vec<String> {
+    let mut layers: Vec<String> = vec![];
+    let mut in_layer_table = false;
+    let mut in_layer = false;
+
+    for (code, value) in groups.iter() {
+        match (*code, value.as_str()) {
+            (0, "TABLE") => in_layer = false,
+            (2, "LAYER") if !in_layer => in_layer_table = true,
+            (0, "ENDTAB") => in_layer_table = false,
+            (0, "LAYER") => in_layer = in_layer_table,
+            (0, _) => in_layer = false,
+            (2, name) if in_layer => layers.push(name.to_string()),
+            _ => {}
+        }
+    }
+    return layers;
+}
+
+// Layers in the table followed by any others that entities are drawn on
+fn read_layer_names(groups: &[GroupCode], table_layers: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = table_layers.to_vec();
+    for (code, value) in groups.iter() {
+        if *code == 8 && !names.contains(value) {
+            names.push(value.clone());
+        }
+    }
+    return names;
+}
+
+fn centroid_of_entities(entities: &[ParseEntity]) -> Vertex {
+    let mut sum_x: f64 = 0.;
+    let mut sum_y: f64 = 0.;
+    let mut count: usize = 0;
+
+    for e in entities.iter() {
+        let points = e
+            .vertices
+            .iter()
+            .flatten()
+            .chain(e.position.iter())
+            .chain(e.start_point.iter())
+            .chain(e.center.iter())
+            .chain(e.control_points.iter().flatten());
+        for p in points {
+            sum_x += p.x as f64;
+            sum_y += p.y as f64;
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return Vertex {
+            x: 0.,
+            y: 0.,
+            bulge: None,
+        };
+    }
+    return Vertex {
+        x: (sum_x / count as f64) as f32,
+        y: (sum_y / count as f64) as f32,
+        bulge: None,
+    };
+}
+
+fn read_blocks(
+    groups: &[GroupCode],
+    layers: &LayerIds,
+    counter: &mut usize,
+) -> (BTreeMap<String, ParseBlock>, HashMap<String, Vertex>) {
+    let mut blocks: BTreeMap<String, ParseBlock> = BTreeMap::new();
+    let mut base_points: HashMap<String, Vertex> = HashMap::new();
+
+    let mut start: usize = 0;
+    while start < groups.len() {
+        let block_start = match groups[start..]
+            .iter()
+            .position(|g| g == &(0, "BLOCK".to_string()))
+        {
+            Some(ind) => start + ind,
+            None => break,
+        };
+        let block_end = groups[block_start..]
+            .iter()
+            .position(|g| g == &(0, "ENDBLK".to_string()))
+            .map(|ind| block_start + ind)
+            .unwrap_or(groups.len());
+
+        let mut entities = collect_entities(&groups[block_start..block_end]);
+        let header = entities.remove(0);
+        start = block_end + 1;
+
+        let name = header.get(2).unwrap_or("").to_string();
+        // Anonymous and layout blocks (*Model_Space, *Paper_Space, *D1...) are not pattern pieces
+        if name.is_empty() || name.starts_with('*') {
+            continue;
+        }
+
+        let parsed: Vec<ParseEntity> = entities
+            .iter()
+            .filter(|e| e.entity_type != "INSERT")
+            .filter_map(|e| convert_entity(e, layers, counter))
+            .collect();
+        let inserts: Vec<ParseInsertEntity> = entities
+            .iter()
+            .filter(|e| e.entity_type == "INSERT")
+            .map(|e| read_insert(e, layers))
+            .collect();
+
+        base_points.insert(
+            name.clone(),
+            header.get_vertex(10).unwrap_or(Vertex {
+                x: 0.,
+                y: 0.,
+                bulge: None,
+            }),
+        );
+        blocks.insert(
+            name,
+            ParseBlock {
+                centroid: match (header.xdata_f32(1010), header.xdata_f32(1020)) {
+                    (Some(x), Some(y)) => Vertex {
+                        x: x,
+                        y: y,
+                        bulge: None,
+                    },
+                    _ => centroid_of_entities(&parsed),
+                },
+                entities: parsed,
+                layer: layers.get_id(header.layer()),
+                inserts: inserts,
+            },
+        );
+    }
+
+    return (blocks, base_points);
+}
+
+fn split_sections(groups: &[GroupCode]) -> HashMap<String, &[GroupCode]> {
+    let mut sections: HashMap<String, &[GroupCode]> = HashMap::new();
+    let mut ind: usize = 0;
+
+    while ind + 1 < groups.len() {
+        if groups[ind] == (0, "SECTION".to_string()) && groups[ind + 1].0 == 2 {
+            let name = groups[ind + 1].1.clone();
+            let start = ind + 2;
+            let end = groups[start..]
+                .iter()
+                .position(|g| g == &(0, "ENDSEC".to_string()))
+                .map(|i| start + i)
+                .unwrap_or(groups.len());
+            sections.insert(name, &groups[start..end]);
+            ind = end;
+        }
+        ind += 1;
+    }
+    return sections;
+}
+
+pub fn parse_dxf(dxf_str: &str) -> Result<ParseDocument, String> {
+    let groups = tokenize(dxf_str)?;
+    let sections = split_sections(&groups);
+
+    if !sections.contains_key("ENTITIES") {
+        return Err("DXF has no ENTITIES section".into());
+    }
+    if let Some(header) = sections.get("HEADER") {
+        read_header(header)?;
+    }
+
+    let table_layers: Vec<String> = sections
+        .get("TABLES")
+        .map(|tables| read_layer_table(tables))
+        .unwrap_or_default();
+    let layers = LayerIds::new(&read_layer_names(&groups, &table_layers));
+
+    let mut counter: usize = 0;
+    let (mut blocks, base_points) = sections
+        .get("BLOCKS")
+        .map(|b| read_blocks(b, &layers, &mut counter))
+        .unwrap_or_default();
+
+    let mut inserts: Vec<ParseInsertEntity> = vec![];
+    let mut model_space: Vec<ParseEntity> = vec![];
+
+    for entity in collect_entities(sections["ENTITIES"]).iter() {
+        if entity.entity_type == "INSERT" {
+            let mut insert = read_insert(entity, &layers);
+            move_base_point_onto_insert(&mut insert, &base_points);
+            inserts.push(insert);
+        } else if let Some(parsed) = convert_entity(entity, &layers, &mut counter) {
+            model_space.push(parsed);
+        }
+    }
+
+    for block in blocks.values_mut() {
+        for insert in block.inserts.iter_mut() {
+            move_base_point_onto_insert(insert, &base_points);
+        }
+    }
+
+    if !model_space.is_empty() {
+        let layer = if table_layers.contains(&"0".to_string()) || table_layers.is_empty() {
+            layers.get_id("0")
+        } else {
+            layers.get_id(&table_layers[0])
+        };
+        blocks.insert(
+            MODEL_SPACE_BLOCK.to_string(),
+            ParseBlock {
+                centroid: centroid_of_entities(&model_space),
+                entities: model_space,
+                layer: layer.clone(),
+                inserts: vec![],
+            },
+        );
+        inserts.push(ParseInsertEntity {
+            entity_type: "INSERT".to_string(),
+            name: MODEL_SPACE_BLOCK.to_string(),
+            position: Vertex {
+                x: 0.,
+                y: 0.,
+                bulge: None,
+            },
+            layer: layer,
+            ..Default::default()
+        });
+    }
+
+    return Ok(ParseDocument {
+        pattern_json: ParsePattern {
+            blocks: blocks,
+            entities: inserts,
+            layer_names: layers.get_names(),
+        },
+    });
+}
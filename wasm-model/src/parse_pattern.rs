@@ -1,15 +1,18 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Result;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Vertex {
     pub x: f32,
     pub y: f32,
+    // Polyline bulge of the segment starting at this vertex, tangent of a quarter of the arc angle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bulge: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ParseEntity {
     pub entity_type: String,
     pub layer: String,
@@ -26,6 +29,35 @@ pub struct ParseEntity {
     pub text_height: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center: Option<Vertex>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+    // Arc angles are in degrees as in DXF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_angle: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_angle: Option<f32>,
+    // Ellipse major axis relative to the center, ratio of minor to major and parameters in radians
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub major_axis: Option<Vertex>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_param: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_param: Option<f32>,
+    // Spline definition, closed splines set shape
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degree: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub knots: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_points: Option<Vec<Vertex>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weights: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fit_points: Option<Vec<Vertex>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -33,20 +65,45 @@ pub struct ParseBlock {
     pub entities: Vec<ParseEntity>,
     pub centroid: Vertex,
     pub layer: String,
+    // Other blocks placed inside this one, positions are in this block's coordinates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inserts: Vec<ParseInsertEntity>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ParseInsertEntity {
     pub entity_type: String,
     pub name: String,
     pub position: Vertex,
     pub layer: String,
+    // Identifies each placement of a block, generated on load when missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insert_index: Option<String>,
+    // Rotation in degrees and scale as in DXF group codes 50, 41 and 42
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_x: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_y: Option<f32>,
+    // Rectangular array of copies as in DXF group codes 70, 71, 44 and 45
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column_spacing: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row_spacing: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ParsePattern {
-    pub blocks: HashMap<String, ParseBlock>,
+    pub blocks: BTreeMap<String, ParseBlock>,
     pub entities: Vec<ParseInsertEntity>,
+    // Names of layers read from DXF by layer number, layers without one are named by their number
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub layer_names: BTreeMap<i32, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,6 +111,10 @@ pub struct ParseDocument {
     pub pattern_json: ParsePattern,
 }
 
+pub fn write_pattern(document: &ParseDocument) -> String {
+    return serde_json::to_string(document).expect("Pattern document is always serializable");
+}
+
 // Function to parse a JSON string into the Person struct.
 pub fn parse_pattern(json_str: &str) -> Result<ParseDocument> {
     let parsed_pattern: ParseDocument = serde_json::from_str(json_str)?;
@@ -1,22 +1,104 @@
 use ndarray::{array, Array2};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use web_sys::console;
 
 use crate::block;
+use crate::draw_cache::DrawCache;
 use crate::drawing_output::IDrawingOutput;
 use crate::drawing_parameters::IDrawingParameters;
+use crate::entity;
+use crate::grading;
+use crate::history;
 use crate::insert;
+use crate::load_report::{self, LoadIssue, LoadIssueKind, LoadReport};
+use crate::measure;
+use crate::parse_dxf;
 use crate::parse_pattern;
+use crate::seam_allowance;
+use crate::snap;
+use crate::spline::Spline;
 use crate::user_settings;
 use crate::utils::bounding_box;
+use crate::utils::color;
+use crate::utils::geometry;
+use crate::utils::log;
 use crate::utils::memory::{IndexBuffer, VertexBuffer};
 use crate::utils::parse;
+use crate::utils::spatial_index::SpatialIndex;
+use crate::utils::transform::Transform;
+use crate::write_dxf;
+use crate::write_svg;
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Debug)]
 pub struct Pattern {
     blocks: Vec<block::Block>,
     entities: Vec<insert::Insert>,
+    // Cached lookups, rebuilt whenever blocks or inserts change
+    block_lookup: HashMap<String, usize>,
+    // Every placement of a block in the model view, with the placements of each block
+    instances: Vec<Instance>,
+    block_instances: Vec<Vec<usize>>,
+    instance_index: SpatialIndex,
+    // Grade rules found in the blocks and the size the pattern is in
+    grading: Option<grading::Grading>,
+    // Names of layers that are not just their number, kept from DXF to be written back
+    layer_names: BTreeMap<i32, String>,
+}
+
+// Only the model is compared, the lookups and indexes are rebuilt from it
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        return self.blocks == other.blocks
+            && self.entities == other.entities
+            && self.grading == other.grading
+            && self.layer_names == other.layer_names;
+    }
+}
+
+// Where an insert is stored, in the model or inside another block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertRef {
+    Model(usize),
+    // Index of the block holding the insert and of the insert in that block
+    Nested(usize, usize),
+}
+
+// Placement of a block, found by walking inserts from the top of the hierarchy down
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub block: usize,
+    // Map from block coordinates into the view
+    pub transform: Transform,
+    // Insert that placed the block, None for blocks drawn at the origin
+    pub insert: Option<InsertRef>,
+    // Inserts placing the blocks this one is nested in, outermost first
+    pub ancestors: Vec<InsertRef>,
+    // Map into the view of the space the insert is in
+    pub parent: Transform,
+}
+
+impl Instance {
+    fn at_origin(block: usize) -> Instance {
+        return Instance {
+            block: block,
+            transform: Transform::identity(),
+            insert: None,
+            ancestors: vec![],
+            parent: Transform::identity(),
+        };
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct EntityHit {
+    pub block: String,
+    // Insert that placed the block the entity was found in
+    pub insert_index: Option<String>,
+    pub entity_index: String,
+    pub closest: entity::ClosestPoint,
 }
 
 fn parse_entity_index(entity_ind: &str) -> Option<[u8; 32]> {
@@ -29,125 +111,1271 @@ fn parse_entity_index(entity_ind: &str) -> Option<[u8; 32]> {
     return Some(output);
 }
 
+// Inserts that can not be placed are left out and reported
+fn parse_insert(
+    i: &parse_pattern::ParseInsertEntity,
+    blocks: &BTreeMap<String, parse_pattern::ParseBlock>,
+    path: String,
+) -> Result<insert::Insert, LoadIssue> {
+    let issue = |kind: LoadIssueKind, reason: String| {
+        let issue = LoadIssue::new(kind, reason, path.clone());
+        return match &i.insert_index {
+            Some(index) => issue.for_entity(index),
+            None => issue,
+        };
+    };
+    if i.entity_type != "INSERT" {
+        return Err(issue(
+            LoadIssueKind::InvalidInsert,
+            format!("Invalid insert type {}", i.entity_type),
+        ));
+    }
+    if !blocks.contains_key(&i.name) {
+        return Err(issue(
+            LoadIssueKind::UnknownBlock,
+            format!("Insert of unknown block {}", i.name),
+        ));
+    }
+    if i.layer.parse::<i32>().is_err() {
+        return Err(issue(
+            LoadIssueKind::InvalidLayer,
+            format!("Invalid layer {}", i.layer),
+        ));
+    }
+    return Ok(insert::Insert::from_parse_insert(i, parse_layer(&i.layer)));
+}
+
+// Keep the index read for an insert unless it is malformed or taken, such inserts are numbered
+// once everything has been read
+fn read_insert_index(
+    insert: &mut insert::Insert,
+    i: &parse_pattern::ParseInsertEntity,
+    path: String,
+    used: &mut HashSet<[u8; 32]>,
+) -> Option<LoadIssue> {
+    let text = i.insert_index.as_ref()?;
+    let (kind, reason) = match parse_entity_index(text) {
+        Some(index) if used.insert(index) => {
+            insert.insert_index = index;
+            return None;
+        }
+        Some(_) => (
+            LoadIssueKind::DuplicateInsertIndex,
+            "Insert index used twice",
+        ),
+        None => (LoadIssueKind::InvalidEntityIndex, "Invalid insert index"),
+    };
+    return Some(LoadIssue::new(kind, reason.to_string(), path).for_entity(text));
+}
+
+// An edit made in view coordinates, carried into the coordinates of a block with this placement
+fn edit_in_block(placement: &Transform, edit: &Transform) -> Option<Transform> {
+    return Some(placement.then(edit).then(&placement.inverse()?));
+}
+
+fn point_region(point: &(f32, f32), padding: f32) -> ((f32, f32), (f32, f32)) {
+    return (
+        (point.0 - padding, point.0 + padding),
+        (point.1 - padding, point.1 + padding),
+    );
+}
+
+fn spline_from_parse_entity(e: &parse_pattern::ParseEntity) -> Option<Spline> {
+    let points = |vertices: &Option<Vec<parse_pattern::Vertex>>| -> Vec<(f32, f32)> {
+        return vertices.iter().flatten().map(|v| (v.x, v.y)).collect();
+    };
+    return Spline::new(
+        e.degree? as usize,
+        e.knots.clone().unwrap_or_default(),
+        points(&e.control_points),
+        e.weights.clone().unwrap_or_default(),
+        points(&e.fit_points),
+        e.shape.unwrap_or(false),
+    );
+}
+
 // Private function, will panic if layer has not been checked beforehand
 fn parse_layer(layer: &str) -> i32 {
     return layer.parse::<i32>().expect("Expected an integer for layer");
 }
 
 // ToDo - Refector into smaller functions
-
-#[wasm_bindgen]
 impl Pattern {
-    // Constructor to initialize the struct
-    pub fn new(json_payload: String) -> Pattern {
-        let mut pattern = Pattern {
+    fn empty() -> Pattern {
+        return Pattern {
             blocks: vec![],
             entities: vec![],
+            block_lookup: HashMap::new(),
+            instances: vec![],
+            block_instances: vec![],
+            instance_index: SpatialIndex::default(),
+            grading: None,
+            layer_names: BTreeMap::new(),
         };
+    }
+
+    pub fn from_document(document: &parse_pattern::ParseDocument) -> Pattern {
+        return Pattern::from_document_with_report(document).0;
+    }
 
-        if let Ok(document) = parse_pattern::parse_pattern(&json_payload) {
-            let parsed_pattern = document.pattern_json;
+    // Build a pattern from everything in the document that can be read, along with what could not
+    pub fn from_document_with_report(
+        document: &parse_pattern::ParseDocument,
+    ) -> (Pattern, LoadReport) {
+        let mut pattern = Pattern::empty();
+        let mut report = LoadReport::new();
+        let mut used_insert_indexes: HashSet<[u8; 32]> = HashSet::new();
+        let parsed_pattern = &document.pattern_json;
+        pattern.layer_names = parsed_pattern.layer_names.clone();
 
-            for i in &parsed_pattern.entities {
-                if i.entity_type == "INSERT"
-                    && parsed_pattern.blocks.contains_key(&i.name)
-                    && i.layer.parse::<i32>().is_ok()
-                {
-                    pattern.entities.push(insert::Insert::new(
-                        i.name.clone(),
-                        parse_layer(&i.layer),
-                        &i.position,
-                    ))
+        for (position, i) in parsed_pattern.entities.iter().enumerate() {
+            let path = load_report::insert_path(None, position);
+            match parse_insert(i, &parsed_pattern.blocks, path.clone()) {
+                Ok(mut insert) => {
+                    if let Some(issue) =
+                        read_insert_index(&mut insert, i, path, &mut used_insert_indexes)
+                    {
+                        report.warning(issue);
+                    }
+                    pattern.entities.push(insert);
                 }
+                Err(issue) => report.error(issue),
             }
+        }
 
-            for (block_key, b) in &parsed_pattern.blocks {
-                if b.layer.parse::<i32>().is_ok() {
-                    let mut new_block =
-                        block::Block::new(block_key.clone(), parse_layer(&b.layer), &b.centroid);
+        for (block_key, b) in &parsed_pattern.blocks {
+            if b.layer.parse::<i32>().is_ok() {
+                let mut new_block =
+                    block::Block::new(block_key.clone(), parse_layer(&b.layer), &b.centroid);
+                for (position, i) in b.inserts.iter().enumerate() {
+                    let path = load_report::insert_path(Some(block_key), position);
+                    match parse_insert(i, &parsed_pattern.blocks, path.clone()) {
+                        Ok(mut insert) => {
+                            if let Some(issue) =
+                                read_insert_index(&mut insert, i, path, &mut used_insert_indexes)
+                            {
+                                report.warning(issue.in_block(block_key));
+                            }
+                            new_block.add_insert(insert);
+                        }
+                        Err(issue) => report.error(issue.in_block(block_key)),
+                    }
+                }
 
-                    for e in b.entities.iter().filter(|e| e.layer.parse::<i32>().is_ok()) {
-                        let entity_id = parse_entity_index(&e.entity_index);
-                        if entity_id.is_none() {
-                            console::log_1(&format!("Invalid entity Id {}", e.entity_index).into());
+                for (position, e) in b.entities.iter().enumerate() {
+                    let issue = |kind: LoadIssueKind, reason: String, field: Option<&str>| {
+                        return LoadIssue::new(
+                            kind,
+                            reason,
+                            load_report::entity_path(block_key, position, field),
+                        )
+                        .in_block(block_key)
+                        .for_entity(&e.entity_index);
+                    };
+                    if e.layer.parse::<i32>().is_err() {
+                        report.error(issue(
+                            LoadIssueKind::InvalidLayer,
+                            format!("Invalid layer {}", e.layer),
+                            Some("layer"),
+                        ));
+                        continue;
+                    }
+                    let entity_id = parse_entity_index(&e.entity_index);
+                    if entity_id.is_none() {
+                        report.error(issue(
+                            LoadIssueKind::InvalidEntityIndex,
+                            "Invalid entity Id".to_string(),
+                            Some("entity_index"),
+                        ));
+                        continue;
+                    }
+
+                    if e.entity_type == "POINT" {
+                        if e.position.is_none() {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "No position defined".to_string(),
+                                Some("position"),
+                            ));
+                            continue;
+                        }
+                        new_block.add_point(
+                            parse_layer(&e.layer),
+                            e.position.as_ref().unwrap(),
+                            entity_id.unwrap(),
+                        );
+                    } else if e.entity_type == "LINE" || e.entity_type == "LWLINE" {
+                        if e.vertices.is_none() || e.vertices.as_ref().unwrap().len() != 2 {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid line vertices".to_string(),
+                                None,
+                            ));
+                            continue;
+                        }
+                        let vertices = e.vertices.as_ref().unwrap();
+                        new_block.add_line(parse_layer(&e.layer), vertices, entity_id.unwrap());
+                    } else if e.entity_type == "POLYLINE" || e.entity_type == "LWPOLYLINE" {
+                        if e.vertices.is_none() || e.vertices.as_ref().unwrap().is_empty() {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid polyline vertices".to_string(),
+                                None,
+                            ));
                             continue;
                         }
 
-                        if e.entity_type == "POINT" {
-                            if e.position.is_none() {
-                                console::log_1(
-                                    &format!("No position defined {}", e.entity_index).into(),
-                                );
-                                continue;
-                            }
-                            new_block.add_point(
-                                parse_layer(&e.layer),
-                                e.position.as_ref().unwrap(),
-                                entity_id.unwrap(),
-                            );
-                        } else if e.entity_type == "LINE" || e.entity_type == "LWLINE" {
-                            if e.vertices.is_none() || e.vertices.as_ref().unwrap().len() != 2 {
-                                console::log_1(
-                                    &format!("Invalid line vertices {}", e.entity_index).into(),
-                                );
-                                continue;
-                            }
-                            let vertices = e.vertices.as_ref().unwrap();
-                            new_block.add_line(parse_layer(&e.layer), vertices, entity_id.unwrap());
-                        } else if e.entity_type == "POLYLINE" || e.entity_type == "LWPOLYLINE" {
-                            if e.vertices.is_none() || e.vertices.as_ref().unwrap().len() == 0 {
-                                console::log_1(
-                                    &format!("Invalid polyline vertices {}", e.entity_index).into(),
-                                );
-                                continue;
-                            }
-
-                            let mut shape: bool = false;
-                            if let Some(e_shape) = e.shape {
-                                shape = e_shape;
-                            }
+                        let mut shape: bool = false;
+                        if let Some(e_shape) = e.shape {
+                            shape = e_shape;
+                        }
 
-                            new_block.add_polyline(
-                                parse_layer(&e.layer),
-                                shape,
-                                entity_id.unwrap(),
-                                e.vertices.as_ref().unwrap(),
-                            );
-                        } else if e.entity_type == "TEXT" {
-                            if e.start_point.is_none()
-                                || e.text_height.is_none()
-                                || e.text.is_none()
-                            {
-                                console::log_1(
-                                    &format!("Invalid text entity {}", e.entity_index).into(),
-                                );
-                                continue;
-                            }
-                            new_block.add_text(
-                                parse_layer(&e.layer),
-                                e.start_point.as_ref().unwrap(),
-                                entity_id.unwrap(),
-                                e.text_height.unwrap(),
-                                e.text.clone().unwrap(),
-                            )
-                        } else {
-                            console::log_1(
-                                &format!("Invalid entity type {}", e.entity_type).into(),
-                            );
+                        new_block.add_polyline(
+                            parse_layer(&e.layer),
+                            shape,
+                            entity_id.unwrap(),
+                            e.vertices.as_ref().unwrap(),
+                        );
+                    } else if e.entity_type == "TEXT" {
+                        if e.start_point.is_none() || e.text_height.is_none() || e.text.is_none() {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid text entity".to_string(),
+                                None,
+                            ));
+                            continue;
                         }
+                        new_block.add_text(
+                            parse_layer(&e.layer),
+                            e.start_point.as_ref().unwrap(),
+                            entity_id.unwrap(),
+                            e.text_height.unwrap(),
+                            e.text.clone().unwrap(),
+                        )
+                    } else if e.entity_type == "CIRCLE" {
+                        if e.center.is_none() || e.radius.is_none_or(|r| r <= 0.) {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid circle entity".to_string(),
+                                None,
+                            ));
+                            continue;
+                        }
+                        new_block.add_circle(
+                            parse_layer(&e.layer),
+                            e.center.as_ref().unwrap(),
+                            e.radius.unwrap(),
+                            entity_id.unwrap(),
+                        );
+                    } else if e.entity_type == "ARC" {
+                        if e.center.is_none()
+                            || e.radius.is_none_or(|r| r <= 0.)
+                            || e.start_angle.is_none()
+                            || e.end_angle.is_none()
+                        {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid arc entity".to_string(),
+                                None,
+                            ));
+                            continue;
+                        }
+                        new_block.add_arc(
+                            parse_layer(&e.layer),
+                            e.center.as_ref().unwrap(),
+                            e.radius.unwrap(),
+                            e.start_angle.unwrap(),
+                            e.end_angle.unwrap(),
+                            entity_id.unwrap(),
+                        );
+                    } else if e.entity_type == "ELLIPSE" {
+                        if e.center.is_none()
+                            || e.major_axis.as_ref().is_none_or(|a| a.x == 0. && a.y == 0.)
+                            || e.ratio.is_none_or(|r| r <= 0. || r > 1.)
+                        {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid ellipse entity".to_string(),
+                                None,
+                            ));
+                            continue;
+                        }
+                        new_block.add_ellipse(
+                            parse_layer(&e.layer),
+                            e.center.as_ref().unwrap(),
+                            e.major_axis.as_ref().unwrap(),
+                            e.ratio.unwrap(),
+                            (
+                                e.start_param.unwrap_or(0.),
+                                e.end_param.unwrap_or(std::f32::consts::TAU),
+                            ),
+                            entity_id.unwrap(),
+                        );
+                    } else if e.entity_type == "SPLINE" {
+                        let spline = spline_from_parse_entity(e);
+                        if spline.is_none() {
+                            report.error(issue(
+                                LoadIssueKind::InvalidGeometry,
+                                "Invalid spline entity".to_string(),
+                                None,
+                            ));
+                            continue;
+                        }
+                        new_block.add_spline(
+                            parse_layer(&e.layer),
+                            spline.unwrap(),
+                            entity_id.unwrap(),
+                        );
+                    } else {
+                        report.error(issue(
+                            LoadIssueKind::UnknownEntityType,
+                            format!("Invalid entity type {}", e.entity_type),
+                            Some("entity_type"),
+                        ));
                     }
+                }
 
-                    new_block.update_bounding_box();
-                    pattern.blocks.push(new_block);
-                } else {
-                    console::log_1(&format!("Invalid layer {}", b.layer).into());
+                new_block.link_point_labels();
+                new_block.update_bounding_box();
+                pattern.blocks.push(new_block);
+            } else {
+                report.error(
+                    LoadIssue::new(
+                        LoadIssueKind::InvalidLayer,
+                        format!("Invalid layer {}", b.layer),
+                        load_report::json_pointer(&["pattern_json", "blocks", block_key, "layer"]),
+                    )
+                    .in_block(block_key),
+                );
+            }
+        }
+
+        pattern.assign_insert_indexes();
+        pattern.rebuild_spatial_index();
+        return (pattern, report);
+    }
+
+    // Give inserts without an index, or with one already taken, the next free number
+    fn assign_insert_indexes(&mut self) {
+        let mut used: HashSet<[u8; 32]> = HashSet::new();
+        let mut missing: Vec<&mut insert::Insert> = vec![];
+        let nested = self
+            .blocks
+            .iter_mut()
+            .flat_map(|b| b.get_inserts_mut().iter_mut());
+        for insert in self.entities.iter_mut().chain(nested) {
+            if insert.insert_index == [0u8; 32] || !used.insert(insert.insert_index) {
+                missing.push(insert);
+            }
+        }
+
+        let mut counter: usize = 0;
+        for insert in missing {
+            loop {
+                counter += 1;
+                let mut index = [0u8; 32];
+                index.copy_from_slice(format!("{:032x}", counter).as_bytes());
+                if used.insert(index) {
+                    insert.insert_index = index;
+                    break;
                 }
             }
         }
+    }
+
+    fn get_insert(&self, insert_ref: &InsertRef) -> &insert::Insert {
+        return match insert_ref {
+            InsertRef::Model(insert_ind) => &self.entities[*insert_ind],
+            InsertRef::Nested(block_ind, insert_ind) => {
+                &self.blocks[*block_ind].get_inserts()[*insert_ind]
+            }
+        };
+    }
+
+    fn get_insert_mut(&mut self, insert_ref: &InsertRef) -> &mut insert::Insert {
+        return match insert_ref {
+            InsertRef::Model(insert_ind) => &mut self.entities[*insert_ind],
+            InsertRef::Nested(block_ind, insert_ind) => {
+                &mut self.blocks[*block_ind].get_inserts_mut()[*insert_ind]
+            }
+        };
+    }
+
+    fn find_insert(&self, insert_index: &[u8; 32]) -> Option<InsertRef> {
+        if let Some(insert_ind) = self
+            .entities
+            .iter()
+            .position(|i| &i.insert_index == insert_index)
+        {
+            return Some(InsertRef::Model(insert_ind));
+        }
+        for (block_ind, block) in self.blocks.iter().enumerate() {
+            if let Some(insert_ind) = block
+                .get_inserts()
+                .iter()
+                .position(|i| &i.insert_index == insert_index)
+            {
+                return Some(InsertRef::Nested(block_ind, insert_ind));
+            }
+        }
+        return None;
+    }
+
+    // Selected through its own insert or through one of the blocks it is nested in
+    fn is_instance_highlighted(&self, instance: &Instance) -> bool {
+        return instance
+            .ancestors
+            .iter()
+            .chain(instance.insert.iter())
+            .any(|r| self.get_insert(r).highlighted);
+    }
+
+    // Placements whose own insert is selected and that do not already move with a selected
+    // block they are nested in, the first one found for each insert
+    fn highlighted_placements(&self, view: &str) -> Vec<(InsertRef, Instance)> {
+        let mut output: Vec<(InsertRef, Instance)> = vec![];
+        for instance in self.instances_in_view(view) {
+            let insert_ref = match instance.insert {
+                Some(insert_ref) if self.get_insert(&insert_ref).highlighted => insert_ref,
+                _ => continue,
+            };
+            if instance
+                .ancestors
+                .iter()
+                .any(|r| self.get_insert(r).highlighted)
+                || output.iter().any(|(r, _)| r == &insert_ref)
+            {
+                continue;
+            }
+            output.push((insert_ref, instance));
+        }
+        return output;
+    }
+
+    // Blocks drawn through an insert have to be drawn again when its selection changes
+    fn mark_insert_dirty(&mut self, insert_ref: &InsertRef) {
+        let mut dirty = vec![false; self.blocks.len()];
+        for instance in self.instances.iter() {
+            if instance.insert.as_ref() == Some(insert_ref)
+                || instance.ancestors.contains(insert_ref)
+            {
+                dirty[instance.block] = true;
+            }
+        }
+        if let Some(block_ind) = self.block_lookup.get(&self.get_insert(insert_ref).name) {
+            dirty[*block_ind] = true;
+        }
+        for (block, dirty) in self.blocks.iter_mut().zip(dirty) {
+            if dirty {
+                block.mark_draw_dirty();
+            }
+        }
+    }
+
+    fn set_insert_ref_highlight(&mut self, insert_ref: &InsertRef, status: bool) {
+        if self.get_insert(insert_ref).highlighted != status {
+            self.get_insert_mut(insert_ref).highlighted = status;
+            self.mark_insert_dirty(insert_ref);
+        }
+    }
+
+    fn all_insert_refs(&self) -> Vec<InsertRef> {
+        let mut output: Vec<InsertRef> = (0..self.entities.len()).map(InsertRef::Model).collect();
+        for (block_ind, block) in self.blocks.iter().enumerate() {
+            for insert_ind in 0..block.get_inserts().len() {
+                output.push(InsertRef::Nested(block_ind, insert_ind));
+            }
+        }
+        return output;
+    }
+
+    // Fails when the document can not be read, problems with parts of it are in the report
+    pub fn try_new(json_payload: &str) -> Result<(Pattern, LoadReport), LoadReport> {
+        return match parse_pattern::parse_pattern(json_payload) {
+            Ok(document) => Ok(Pattern::from_document_with_report(&document)),
+            Err(error) => Err(LoadReport::failed(format!("Invalid JSON: {}", error))),
+        };
+    }
+
+    pub fn try_from_dxf(dxf_payload: &str) -> Result<(Pattern, LoadReport), LoadReport> {
+        return match parse_dxf::parse_dxf(dxf_payload) {
+            Ok(document) => Ok(Pattern::from_document_with_report(&document)),
+            Err(message) => Err(LoadReport::failed(format!("Invalid DXF: {}", message))),
+        };
+    }
 
+    // Constructors that can not fail log the report and fall back to an empty pattern
+    pub(crate) fn or_empty(loaded: Result<(Pattern, LoadReport), LoadReport>) -> Pattern {
+        let (pattern, report) = match loaded {
+            Ok(loaded) => loaded,
+            Err(report) => (Pattern::empty(), report),
+        };
+        for issue in report.errors.iter() {
+            log::error(&format!("{} at {}", issue.reason, issue.path));
+        }
+        for issue in report.warnings.iter() {
+            log::warning(&format!("{} at {}", issue.reason, issue.path));
+        }
         return pattern;
     }
 
+    // Bounding box of a placed block in the coordinates of the view
+    fn instance_bounding_box(&self, instance: &Instance) -> ((f32, f32), (f32, f32)) {
+        return instance
+            .transform
+            .apply_bbox(self.blocks[instance.block].get_bounding_box());
+    }
+
+    // Add a placed block and everything inserted in it, a block met again further down
+    // its own branch is a cycle and is not followed
+    fn collect_instances(
+        &self,
+        instance: Instance,
+        path: &mut Vec<usize>,
+        output: &mut Vec<Instance>,
+    ) {
+        let block_ind = instance.block;
+        let transform = instance.transform.clone();
+        let mut ancestors = instance.ancestors.clone();
+        ancestors.extend(instance.insert);
+        output.push(instance);
+        path.push(block_ind);
+        for (insert_ind, insert) in self.blocks[block_ind].get_inserts().iter().enumerate() {
+            let child = match self.block_lookup.get(&insert.name) {
+                Some(child) if !path.contains(child) => *child,
+                _ => continue,
+            };
+            for cell in insert.get_transforms() {
+                let nested = Instance {
+                    block: child,
+                    transform: cell.then(&transform),
+                    insert: Some(InsertRef::Nested(block_ind, insert_ind)),
+                    ancestors: ancestors.clone(),
+                    parent: transform.clone(),
+                };
+                self.collect_instances(nested, path, output);
+            }
+        }
+        path.pop();
+    }
+
+    // Placements of every block below a root block, or of the whole model without one
+    fn build_instances(&self, root: Option<usize>) -> Vec<Instance> {
+        let mut output: Vec<Instance> = vec![];
+        let mut path: Vec<usize> = vec![];
+        if let Some(block_ind) = root {
+            self.collect_instances(Instance::at_origin(block_ind), &mut path, &mut output);
+            return output;
+        }
+
+        for (insert_ind, insert) in self.entities.iter().enumerate() {
+            let block_ind = match self.block_lookup.get(&insert.name) {
+                Some(block_ind) => *block_ind,
+                None => continue,
+            };
+            for cell in insert.get_transforms() {
+                let instance = Instance {
+                    block: block_ind,
+                    transform: cell,
+                    insert: Some(InsertRef::Model(insert_ind)),
+                    ancestors: vec![],
+                    parent: Transform::identity(),
+                };
+                self.collect_instances(instance, &mut path, &mut output);
+            }
+        }
+
+        // Blocks that no insert refers to are drawn at the origin
+        let referenced: HashSet<&String> = self
+            .entities
+            .iter()
+            .chain(self.blocks.iter().flat_map(|b| b.get_inserts().iter()))
+            .map(|i| &i.name)
+            .collect();
+        for (block_ind, block) in self.blocks.iter().enumerate() {
+            if !referenced.contains(&block.name) {
+                self.collect_instances(Instance::at_origin(block_ind), &mut path, &mut output);
+            }
+        }
+        // As are blocks only reachable through a cycle
+        let mut placed = vec![false; self.blocks.len()];
+        for instance in output.iter() {
+            placed[instance.block] = true;
+        }
+        for block_ind in 0..self.blocks.len() {
+            if placed[block_ind] {
+                continue;
+            }
+            let start = output.len();
+            self.collect_instances(Instance::at_origin(block_ind), &mut path, &mut output);
+            for instance in output[start..].iter() {
+                placed[instance.block] = true;
+            }
+        }
+        return output;
+    }
+
+    // Placements in a view, a single block view shows that block at the origin with its inserts
+    fn instances_in_view(&self, view: &str) -> Vec<Instance> {
+        if let Some(key) = parse::view_as_block_key(view) {
+            return match self.block_lookup.get(&key) {
+                Some(block_ind) => self.build_instances(Some(*block_ind)),
+                None => vec![],
+            };
+        }
+        return self.instances.clone();
+    }
+
+    // First placement of each block in a view, edits in view coordinates are carried into the
+    // block through it
+    fn first_placements(&self, view: &str) -> Vec<Option<Transform>> {
+        let mut output: Vec<Option<Transform>> = vec![None; self.blocks.len()];
+        for instance in self.instances_in_view(view) {
+            if output[instance.block].is_none() {
+                output[instance.block] = Some(instance.transform);
+            }
+        }
+        return output;
+    }
+
+    fn rebuild_spatial_index(&mut self) {
+        self.block_lookup = HashMap::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            self.block_lookup.insert(block.name.clone(), i);
+        }
+        self.instances = self.build_instances(None);
+        self.block_instances = vec![vec![]; self.blocks.len()];
+        for (i, instance) in self.instances.iter().enumerate() {
+            self.block_instances[instance.block].push(i);
+        }
+        let boxes = self
+            .instances
+            .iter()
+            .map(|i| self.instance_bounding_box(i))
+            .collect();
+        self.instance_index = SpatialIndex::new(boxes);
+    }
+
+    // Update cached state of blocks after their entities have been edited
+    fn refresh_moved_blocks(&mut self, block_indexes: &[usize]) {
+        for block_ind in block_indexes.iter() {
+            self.blocks[*block_ind].mark_draw_dirty();
+            for instance_ind in self.block_instances[*block_ind].iter() {
+                let bbox = self.instance_bounding_box(&self.instances[*instance_ind]);
+                self.instance_index.update(*instance_ind, bbox);
+            }
+        }
+    }
+
+    fn get_all_placements(&self) -> Vec<Vec<Transform>> {
+        return self
+            .block_instances
+            .iter()
+            .map(|instances| {
+                instances
+                    .iter()
+                    .map(|i| self.instances[*i].transform.clone())
+                    .collect()
+            })
+            .collect();
+    }
+
+    // Update cached state after inserts have moved, blocks placed somewhere new are drawn again
+    fn refresh_instances(&mut self) {
+        let before = self.get_all_placements();
+        self.rebuild_spatial_index();
+        let after = self.get_all_placements();
+        for (block, (old, new)) in self.blocks.iter_mut().zip(before.iter().zip(after.iter())) {
+            if old != new {
+                block.mark_draw_dirty();
+            }
+        }
+    }
+
+    // Transforms a block is drawn with in the model view
+    pub fn get_block_placements(&self, block_key: &String) -> Vec<Transform> {
+        return match self.block_lookup.get(block_key) {
+            Some(block_ind) => self.get_all_placements().swap_remove(*block_ind),
+            None => vec![],
+        };
+    }
+
+    pub fn get_instances(&self) -> &Vec<Instance> {
+        return &self.instances;
+    }
+
+    pub fn to_document(&self) -> parse_pattern::ParseDocument {
+        return parse_pattern::ParseDocument {
+            pattern_json: parse_pattern::ParsePattern {
+                blocks: self
+                    .blocks
+                    .iter()
+                    .map(|b| (b.name.clone(), b.to_parse_block()))
+                    .collect(),
+                entities: self.entities.iter().map(|i| i.to_parse_insert()).collect(),
+                layer_names: self.layer_names.clone(),
+            },
+        };
+    }
+
+    // State of the blocks that the next edit of the selection will change
+    pub fn snapshot_highlighted(&self) -> history::Snapshot {
+        let mut block_names: Vec<String> = self
+            .blocks
+            .iter()
+            .filter(|b| b.is_highlighted() || b.has_highlighted_entities())
+            .map(|b| b.name.clone())
+            .collect();
+        // Blocks of selected inserts take edits their placement can't hold
+        for insert_ref in self.all_insert_refs().iter() {
+            let insert = self.get_insert(insert_ref);
+            if insert.highlighted && !block_names.contains(&insert.name) {
+                block_names.push(insert.name.clone());
+            }
+        }
+        return self.snapshot_blocks(&block_names);
+    }
+
+    pub fn snapshot_blocks(&self, block_names: &[String]) -> history::Snapshot {
+        return history::Snapshot {
+            blocks: self
+                .blocks
+                .iter()
+                .filter(|b| block_names.contains(&b.name))
+                .map(|b| (b.name.clone(), b.get_entities().clone()))
+                .collect(),
+            inserts: self
+                .all_insert_refs()
+                .iter()
+                .map(|r| self.get_insert(r).clone())
+                .collect(),
+            grade_size: self.grading.as_ref().map(|g| g.get_active_size().clone()),
+        };
+    }
+
+    // What an edit changed since the snapshot taken before it
+    pub fn get_changes(&self, before: &history::Snapshot) -> history::Command {
+        let mut command = history::Command::default();
+        for (block_name, entities) in before.blocks.iter() {
+            let block = match self.block_in_pattern(block_name) {
+                Some(block) => block,
+                None => continue,
+            };
+            let previous: HashMap<[u8; 32], &entity::Entity> =
+                entities.iter().map(|e| (e.entity_index, e)).collect();
+            let current: HashSet<[u8; 32]> = block
+                .get_entities()
+                .iter()
+                .map(|e| e.entity_index)
+                .collect();
+            for entity in block.get_entities().iter() {
+                let old = previous.get(&entity.entity_index).copied();
+                if old != Some(entity) {
+                    command.entities.push(history::EntityChange {
+                        block: block_name.clone(),
+                        entity_index: entity.entity_index,
+                        before: old.cloned(),
+                        after: Some(entity.clone()),
+                    });
+                }
+            }
+            for entity in entities
+                .iter()
+                .filter(|e| !current.contains(&e.entity_index))
+            {
+                command.entities.push(history::EntityChange {
+                    block: block_name.clone(),
+                    entity_index: entity.entity_index,
+                    before: Some(entity.clone()),
+                    after: None,
+                });
+            }
+        }
+        for (old, insert_ref) in before.inserts.iter().zip(self.all_insert_refs()) {
+            let insert = self.get_insert(&insert_ref);
+            if old != insert {
+                command.inserts.push(history::InsertChange {
+                    before: old.clone(),
+                    after: insert.clone(),
+                });
+            }
+        }
+        let grade_size = self.grading.as_ref().map(|g| g.get_active_size().clone());
+        if let (Some(old), Some(new)) = (&before.grade_size, grade_size) {
+            if old != &new {
+                command.grade_size = Some((old.clone(), new));
+            }
+        }
+        return command;
+    }
+
+    // Puts back the entities and inserts an edit changed as they were before or after it, the
+    // current selection is kept
+    pub fn restore_changes(&mut self, command: &history::Command, to_before: bool) {
+        let mut changed_blocks: Vec<usize> = vec![];
+        for change in command.entities.iter() {
+            let block_ind = match self.block_lookup.get(&change.block) {
+                Some(block_ind) => *block_ind,
+                None => continue,
+            };
+            let state = if to_before {
+                change.before.as_ref()
+            } else {
+                change.after.as_ref()
+            };
+            self.blocks[block_ind].restore_entity(&change.entity_index, state);
+            if !changed_blocks.contains(&block_ind) {
+                changed_blocks.push(block_ind);
+            }
+        }
+        for block_ind in changed_blocks.iter() {
+            self.blocks[*block_ind].update_bounding_box();
+        }
+        self.refresh_moved_blocks(&changed_blocks);
+
+        for change in command.inserts.iter() {
+            let state = if to_before {
+                &change.before
+            } else {
+                &change.after
+            };
+            if let Some(insert_ref) = self.find_insert(&state.insert_index) {
+                let insert = self.get_insert_mut(&insert_ref);
+                let highlighted = insert.highlighted;
+                *insert = state.clone();
+                insert.highlighted = highlighted;
+            }
+        }
+        if !command.inserts.is_empty() {
+            self.refresh_instances();
+        }
+
+        if let (Some(grading), Some((before, after))) = (self.grading.as_mut(), &command.grade_size)
+        {
+            grading.set_active_size(if to_before { before } else { after });
+        }
+    }
+
+    pub fn get_blocks(&self) -> &Vec<block::Block> {
+        return &self.blocks;
+    }
+
+    // Names of layers read from DXF by layer number, layers without one are named by their number
+    pub fn get_layer_names(&self) -> &BTreeMap<i32, String> {
+        return &self.layer_names;
+    }
+
+    pub fn get_inserts(&self) -> &Vec<insert::Insert> {
+        return &self.entities;
+    }
+
+    // Placements in the current view that may touch a region
+    fn instances_in_region(&self, view: &str, region: &((f32, f32), (f32, f32))) -> Vec<Instance> {
+        if parse::view_as_block_key(view).is_some() {
+            return self.instances_in_view(view);
+        }
+        return self
+            .instance_index
+            .query_bbox(region)
+            .into_iter()
+            .map(|i| self.instances[i].clone())
+            .collect();
+    }
+
+    // Every block drawn in a view along with where it is drawn, once per placement
+    pub(crate) fn get_placements(&self, view: &str) -> Vec<(&block::Block, Transform)> {
+        return self
+            .instances_in_view(view)
+            .into_iter()
+            .map(|i| (&self.blocks[i.block], i.transform))
+            .collect();
+    }
+
+    // Entities selected by a box in world coordinates, window selection unless crossing
+    pub fn find_entities_with_bbox(
+        &self,
+        bbox: &((f32, f32), (f32, f32)),
+        settings: &user_settings::ISettings,
+        crossing: bool,
+    ) -> (Vec<String>, Option<bounding_box::BoundingBox>) {
+        let mut selected_entities: Vec<String> = vec![];
+        let mut union_box = Option::None;
+
+        for instance in self.instances_in_region(&settings.view, bbox) {
+            let (block, placement) = (&self.blocks[instance.block], &instance.transform);
+            let block_bbox = match placement.inverse() {
+                Some(inverse) => inverse.apply_bbox(bbox),
+                None => continue,
+            };
+            if !block.bbox_intersects_block(&block_bbox) {
+                continue;
+            }
+
+            for entity in
+                block.find_entities_with_bbox(&block_bbox, crossing, &settings.disabled_layers)
+            {
+                let placed_bbox = if placement.is_translation() {
+                    placement.apply_bbox(&entity.get_selection_bounding_box())
+                } else {
+                    // The box in block coordinates is wider than the one asked for, so check
+                    // again where the entity is drawn
+                    let placed = entity.transformed(placement);
+                    let selected = if crossing {
+                        placed.intersects_bbox(bbox)
+                    } else {
+                        placed.inside_bbox(bbox)
+                    };
+                    if !selected {
+                        continue;
+                    }
+                    placed.get_selection_bounding_box()
+                };
+                let entity_index = entity.entity_index_as_uuid();
+                if !selected_entities.contains(&entity_index) {
+                    selected_entities.push(entity_index);
+                }
+                union_box = Some(match union_box {
+                    Some(current) => bounding_box::union(&current, &placed_bbox),
+                    None => placed_bbox,
+                });
+            }
+        }
+
+        return (selected_entities, union_box);
+    }
+
+    pub fn highlight_entity_selection(&mut self, entity_indexes: &[String]) {
+        self.reset_selection();
+        for entity_index in entity_indexes.iter() {
+            self.set_entity_highlight(entity_index, true);
+        }
+    }
+
+    pub fn set_entity_highlight(&mut self, entity_index: &str, status: bool) -> bool {
+        let mut entity_exists = false;
+        if let Some(index) = parse_entity_index(entity_index) {
+            for block in self.blocks.iter_mut() {
+                entity_exists |= block.set_entity_highlight(&index, status);
+            }
+        }
+        return entity_exists;
+    }
+
+    pub fn get_entity_block_name(&self, entity_index: &str) -> Option<String> {
+        let index = parse_entity_index(entity_index)?;
+        return self
+            .blocks
+            .iter()
+            .find(|b| b.get_entities().iter().any(|e| e.entity_index == index))
+            .map(|b| b.name.clone());
+    }
+
+    // Entities of a block where it is first placed in the view, blocks the view does not show
+    // are left in their own coordinates
+    fn placed_entities(&self, block_ind: usize, view: &str) -> Vec<entity::Entity> {
+        let entities = self.blocks[block_ind].get_entities();
+        return match &self.first_placements(view)[block_ind] {
+            Some(placement) => entities.iter().map(|e| e.transformed(placement)).collect(),
+            None => entities.clone(),
+        };
+    }
+
+    // Measurements at the size a block is drawn, taking in the scale of its placement
+    pub(crate) fn measure_block(
+        &self,
+        block_name: &String,
+        view: &str,
+    ) -> Option<measure::BlockMeasurement> {
+        let block_ind = *self.block_lookup.get(block_name)?;
+        let entities = self.placed_entities(block_ind, view);
+        return Some(measure::measure_block(block_name, &entities));
+    }
+
+    pub(crate) fn measure_entity(
+        &self,
+        entity_index: &str,
+        view: &str,
+    ) -> Option<measure::Measurement> {
+        let index = parse_entity_index(entity_index)?;
+        let block_ind = self
+            .blocks
+            .iter()
+            .position(|b| b.get_entities().iter().any(|e| e.entity_index == index))?;
+        let entities = self.placed_entities(block_ind, view);
+        let entity = entities.iter().find(|e| e.entity_index == index)?;
+        return measure::measure_entity(entity);
+    }
+
+    // Entities on enabled layers near a point in view coordinates, moved to where they are drawn
+    fn placed_entities_near_point(
+        &self,
+        point: &(f32, f32),
+        settings: &user_settings::ISettings,
+    ) -> Vec<entity::Entity> {
+        let mut output: Vec<entity::Entity> = vec![];
+        let region = point_region(point, settings.point_threshold);
+        for instance in self.instances_in_region(&settings.view, &region) {
+            let (block, placement) = (&self.blocks[instance.block], &instance.transform);
+            let block_point = match placement.inverse() {
+                Some(inverse) => inverse.apply_point(point),
+                None => continue,
+            };
+            let threshold = settings.point_threshold / placement.get_min_scale();
+            output.extend(
+                block
+                    .find_entities_near_point(&block_point, threshold, &settings.disabled_layers)
+                    .into_iter()
+                    .map(|e| e.transformed(placement)),
+            );
+        }
+        return output;
+    }
+
+    // Best snap target within the point threshold of a point in view coordinates
+    pub(crate) fn find_snap(
+        &self,
+        point: &(f32, f32),
+        modes: &[snap::SnapMode],
+        base: Option<&(f32, f32)>,
+        settings: &user_settings::ISettings,
+    ) -> Option<snap::SnapTarget> {
+        let entities = self.placed_entities_near_point(point, settings);
+        return snap::find_snap(point, &entities, modes, base, settings.point_threshold);
+    }
+
+    // Snaps a pick of the measuring tool to a vertex or midpoint, or else a point on an entity
+    pub(crate) fn snap_pick(
+        &self,
+        point: &(f32, f32),
+        settings: &user_settings::ISettings,
+    ) -> measure::SnappedPick {
+        let modes = [
+            snap::SnapMode::Endpoint,
+            snap::SnapMode::Midpoint,
+            snap::SnapMode::Nearest,
+        ];
+        return match self.find_snap(point, &modes, None, settings) {
+            Some(target) => measure::SnappedPick {
+                point: target.point,
+                snap: match target.mode {
+                    snap::SnapMode::Endpoint => measure::PickSnap::Vertex,
+                    snap::SnapMode::Midpoint => measure::PickSnap::Midpoint,
+                    _ => measure::PickSnap::OnEntity,
+                },
+                entity_index: target.entity_indexes.into_iter().next(),
+            },
+            None => measure::SnappedPick {
+                point: *point,
+                snap: measure::PickSnap::Free,
+                entity_index: None,
+            },
+        };
+    }
+
+    // Text of the label written on a point
+    pub fn get_point_label(&self, entity_index: &str) -> Option<String> {
+        let index = parse_entity_index(entity_index)?;
+        return self
+            .blocks
+            .iter()
+            .find_map(|b| b.get_point_label(&index))
+            .map(|label| label.text.clone());
+    }
+
+    // Next free entity index, numbered in the same way as inserts without one
+    fn next_entity_index(&self) -> [u8; 32] {
+        let used: HashSet<[u8; 32]> = self
+            .blocks
+            .iter()
+            .flat_map(|b| b.get_entities().iter().map(|e| e.entity_index))
+            .collect();
+        let mut counter: usize = 0;
+        loop {
+            counter += 1;
+            let mut index = [0u8; 32];
+            index.copy_from_slice(format!("{:032x}", counter).as_bytes());
+            if !used.contains(&index) {
+                return index;
+            }
+        }
+    }
+
+    // Adds the seam allowance around a closed entity to its block as a closed polyline,
+    // returns the index of the new entity
+    pub(crate) fn add_seam_allowance(
+        &mut self,
+        entity_index: &str,
+        widths: &[f32],
+        join: seam_allowance::JoinType,
+        layer: i32,
+    ) -> Option<String> {
+        let index = parse_entity_index(entity_index)?;
+        let (block_ind, entity) = self.blocks.iter().enumerate().find_map(|(i, b)| {
+            b.get_entities()
+                .iter()
+                .find(|e| e.entity_index == index)
+                .map(|e| (i, e))
+        })?;
+        let vertices = seam_allowance::entity_seam_allowance(entity, widths, join)?;
+
+        let new_index = self.next_entity_index();
+        let outline = entity::Entity::new(
+            entity::EntityTypes::LWPOLYLINE,
+            layer,
+            true,
+            vertices,
+            0.,
+            new_index,
+            "".to_string(),
+        );
+        self.blocks[block_ind].add_entity(outline);
+        // The block may have grown past where it was placed
+        self.rebuild_spatial_index();
+        return Some(parse::index_as_uuid(&new_index));
+    }
+
+    // Reads grade rules and finds their points, the pattern goes back to the base size first
+    pub fn set_grade_rules(&mut self, rules_payload: &str) -> LoadReport {
+        self.clear_grade_rules();
+        let rules: grading::GradeRules = match serde_json::from_str(rules_payload) {
+            Ok(rules) => rules,
+            Err(e) => return LoadReport::failed(format!("Invalid grade rules: {}", e)),
+        };
+        return match grading::Grading::new(&rules, &self.blocks) {
+            Ok((grading, report)) => {
+                for block_ind in grading.get_graded_blocks() {
+                    self.blocks[block_ind].mark_draw_dirty();
+                }
+                self.grading = Some(grading);
+                report
+            }
+            Err(report) => report,
+        };
+    }
+
+    pub fn clear_grade_rules(&mut self) {
+        if let Some(grading) = self.grading.as_ref() {
+            let base_size = grading.get_base_size().clone();
+            self.set_active_size(&base_size);
+        }
+        if let Some(grading) = self.grading.take() {
+            for block_ind in grading.get_graded_blocks() {
+                self.blocks[block_ind].mark_draw_dirty();
+            }
+        }
+    }
+
+    pub fn get_grading(&self) -> Option<&grading::Grading> {
+        return self.grading.as_ref();
+    }
+
+    // Names of the blocks a change of size moves
+    pub fn get_graded_block_names(&self) -> Vec<String> {
+        return match self.grading.as_ref() {
+            Some(grading) => grading
+                .get_graded_blocks()
+                .iter()
+                .map(|b| self.blocks[*b].name.clone())
+                .collect(),
+            None => vec![],
+        };
+    }
+
+    // Moves the grade points and the vertices on them to where they are in another size
+    pub fn set_active_size(&mut self, size: &String) -> bool {
+        let grading = match self.grading.as_mut() {
+            Some(grading) if grading.get_sizes().contains(size) => grading,
+            _ => return false,
+        };
+        let moves = grading.get_moves(grading.get_active_size(), size);
+        grading.set_active_size(size);
+        for (block_ind, block_moves) in moves.iter() {
+            self.blocks[*block_ind].move_entity_vertices(block_moves);
+        }
+        if !moves.is_empty() {
+            self.rebuild_spatial_index();
+        }
+        return true;
+    }
+
+    pub fn get_highlighted_entity_indexes(&self) -> Vec<String> {
+        return self
+            .blocks
+            .iter()
+            .flat_map(|b| b.get_highlighted_entity_indexes())
+            .collect();
+    }
+
+    // Nearest entity to a point in world coordinates within the point threshold
+    pub fn find_closest_entity(
+        &self,
+        point: &(f32, f32),
+        settings: &user_settings::ISettings,
+    ) -> Option<EntityHit> {
+        let mut output: Option<EntityHit> = None;
+        let region = point_region(point, settings.point_threshold);
+
+        for instance in self.instances_in_region(&settings.view, &region) {
+            let (block, placement) = (&self.blocks[instance.block], &instance.transform);
+            let block_point = match placement.inverse() {
+                Some(inverse) => inverse.apply_point(point),
+                None => continue,
+            };
+            // The threshold covers at least this much of the block once it is scaled into place
+            let threshold = settings.point_threshold / placement.get_min_scale();
+            if !block.point_in_bounding_box(&block_point, threshold) {
+                continue;
+            }
+
+            let closest =
+                block.get_closest_entity(&block_point, threshold, &settings.disabled_layers);
+            if let Some((entity, mut closest_point)) = closest {
+                closest_point.point = placement.apply_point(&closest_point.point);
+                if !placement.is_translation() {
+                    closest_point.distance = geometry::distance(point, &closest_point.point);
+                    if closest_point.distance > settings.point_threshold {
+                        continue;
+                    }
+                }
+                if output
+                    .as_ref()
+                    .is_none_or(|o| closest_point.distance < o.closest.distance)
+                {
+                    output = Some(EntityHit {
+                        block: block.name.clone(),
+                        insert_index: instance
+                            .insert
+                            .map(|r| self.get_insert(&r).insert_index_as_uuid()),
+                        entity_index: entity.entity_index_as_uuid(),
+                        closest: closest_point,
+                    });
+                }
+            }
+        }
+
+        return output;
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Pattern {
+    // Constructor to initialize the struct
+    pub fn new(json_payload: String) -> Pattern {
+        return Pattern::or_empty(Pattern::try_new(&json_payload));
+    }
+
+    pub fn new_from_dxf(dxf_payload: String) -> Pattern {
+        return Pattern::or_empty(Pattern::try_from_dxf(&dxf_payload));
+    }
+
+    pub fn to_json(&self) -> String {
+        return parse_pattern::write_pattern(&self.to_document());
+    }
+
+    pub fn export_dxf(&self) -> String {
+        return write_dxf::write_dxf(self);
+    }
+
     pub fn get_number_blocks(&self) -> usize {
         return self.blocks.len();
     }
@@ -160,84 +1388,190 @@ impl Pattern {
         return self.blocks.iter().map(|b| b.get_number_entities()).sum();
     }
 
-    pub(crate) fn get_offset_for_block(&self, block_key: &String) -> Array2<f32> {
-        for insert in self.entities.iter() {
-            if &insert.name == block_key {
-                return insert.position.clone();
-            };
+    // Blocks that may be on screen along with any that hold part of the selection
+    fn blocks_to_draw(
+        &self,
+        viewport: &Option<bounding_box::BoundingBox>,
+        cross_size: f32,
+    ) -> Vec<usize> {
+        let viewport = match viewport {
+            Some(viewport) => viewport,
+            None => return (0..self.blocks.len()).collect(),
+        };
+        let mut output: Vec<usize> = self
+            .instance_index
+            .query_bbox(&bounding_box::pad(viewport, cross_size))
+            .into_iter()
+            .map(|i| self.instances[i].block)
+            .collect();
+        for (i, block) in self.blocks.iter().enumerate() {
+            if block.is_highlighted() || block.has_highlighted_entities() {
+                output.push(i);
+            }
+        }
+        for instance in self.instances.iter() {
+            if self.is_instance_highlighted(instance) {
+                output.push(instance.block);
+            }
         }
+        output.sort_unstable();
+        output.dedup();
+        return output;
+    }
 
-        console::log_1(&format!("Block key {} not in inserts", block_key).into());
-        return Array2::zeros((1, 2));
+    // Whether each block is written to the buffers in the current view
+    fn blocks_drawn_in_view(&self, settings: &user_settings::ISettings) -> Vec<bool> {
+        let mut output = vec![false; self.blocks.len()];
+        // View with name Block=>L-1 will attempt to draw L-1
+        if let Some(block_key) = parse::view_as_block_key(&settings.view) {
+            if self.block_in_pattern(&block_key).is_some() {
+                for instance in self.instances_in_view(&settings.view) {
+                    output[instance.block] = true;
+                }
+            }
+        } else {
+            for block_ind in self.blocks_to_draw(&settings.viewport, settings.cross_size) {
+                output[block_ind] = true;
+            }
+        }
+        return output;
     }
 
-    fn update_draw_sequence_model(
-        &self,
+    // Write blocks that changed since the last pass into the buffers, followed by the overlay
+    // of temporary entities in view coordinates
+    pub(crate) fn update_draw_sequence(
+        &mut self,
         settings: &mut user_settings::ISettings,
+        overlay: &[entity::Entity],
         vertex_buffer: &mut VertexBuffer,
         index_buffer: &mut IndexBuffer,
+        draw_cache: &mut DrawCache,
     ) {
+        let drawn_blocks = self.blocks_drawn_in_view(settings);
+        let mut placements: Vec<Vec<(Transform, bool)>> = vec![vec![]; self.blocks.len()];
+        let mut highlighted_placements = vec![false; self.blocks.len()];
+        for instance in self.instances_in_view(&settings.view) {
+            let highlighted = self.is_instance_highlighted(&instance);
+            highlighted_placements[instance.block] |= highlighted;
+            placements[instance.block].push((instance.transform, highlighted));
+        }
         let drawing_parameters: IDrawingParameters = settings.get_drawing_pass_parameters();
-        let mut drawing_output: IDrawingOutput = IDrawingOutput::new(vertex_buffer, index_buffer);
+        // The overlay keeps its own range after the blocks
+        let overlay_ind = self.blocks.len();
+        let (full_rebuild, highlight_changed) =
+            draw_cache.begin_pass(settings, overlay_ind + 1, vertex_buffer, index_buffer);
 
-        for block in self.blocks.iter() {
-            let offset: Array2<f32> = self.get_offset_for_block(&block.name);
-            block.update_draw_sequence(&offset, &settings, &drawing_parameters, &mut drawing_output)
-        }
+        for (block_ind, block) in self.blocks.iter_mut().enumerate() {
+            let drawn = drawn_blocks[block_ind];
+            // Blocks cut by the viewport are drawn again when panning changes what is left of them
+            let visible_entities: Vec<Option<Vec<usize>>> = if drawn {
+                placements[block_ind]
+                    .iter()
+                    .map(|(placement, _)| block.get_visible_entities(placement, settings))
+                    .collect()
+            } else {
+                vec![]
+            };
+            let needs_drawing = full_rebuild
+                || block.is_draw_dirty()
+                || draw_cache.is_drawn(block_ind) != drawn
+                || !draw_cache.has_visible_entities(block_ind, &visible_entities)
+                || (highlight_changed
+                    && (block.is_highlighted()
+                        || block.has_highlighted_entities()
+                        || highlighted_placements[block_ind]));
+            if !needs_drawing {
+                continue;
+            }
 
-        settings.highlight_nr_selected_entities = drawing_output.nr_entities;
-        settings.highlight_width_height = drawing_output.get_width_height();
-        settings.highlight_rotation_center = drawing_output.get_center_bbox();
-    }
+            let mut block_vertices = VertexBuffer::new();
+            let mut block_indices = IndexBuffer::new();
+            let mut drawing_output: IDrawingOutput =
+                IDrawingOutput::new(&mut block_vertices, &mut block_indices);
+            drawing_output.last_index = draw_cache.get_first_vertex(block_ind, vertex_buffer);
 
-    fn update_draw_sequence_block(
-        &self,
-        settings: &mut user_settings::ISettings,
-        block_name: &String,
-        vertex_buffer: &mut VertexBuffer,
-        index_buffer: &mut IndexBuffer,
-    ) {
-        let mut drawing_output: IDrawingOutput = IDrawingOutput::new(vertex_buffer, index_buffer);
-
-        if let Some(block) = self.block_in_pattern(&block_name) {
-            let offset = Array2::zeros((1, 2));
+            if drawn {
+                // Other sizes of a graded block are drawn along with it
+                let nest = match self.grading.as_ref() {
+                    Some(grading) if settings.grade_overlay => grading.get_nest(block_ind, block),
+                    _ => vec![],
+                };
+                // Every placement of the block is written into its range of the buffers
+                for ((placement, highlighted), visible) in
+                    placements[block_ind].iter().zip(visible_entities.iter())
+                {
+                    block.update_draw_sequence(
+                        placement,
+                        *highlighted,
+                        visible,
+                        settings,
+                        &drawing_parameters,
+                        &mut drawing_output,
+                    );
+                    grading::draw_nest(
+                        &nest,
+                        placement,
+                        settings,
+                        &drawing_parameters,
+                        &mut drawing_output,
+                    );
+                }
+            }
+            let statistics = drawing_output.get_statistics();
 
-            let drawing_parameters: IDrawingParameters = settings.get_drawing_pass_parameters();
+            draw_cache.replace_block(
+                block_ind,
+                drawn,
+                (block_vertices.buffer, block_indices.buffer),
+                statistics,
+                vertex_buffer,
+                index_buffer,
+            );
+            draw_cache.set_visible_entities(block_ind, visible_entities);
+            block.clear_draw_dirty();
+        }
 
-            block.update_draw_sequence(
-                &offset,
-                &settings,
+        let mut overlay_vertices = VertexBuffer::new();
+        let mut overlay_indices = IndexBuffer::new();
+        let mut drawing_output: IDrawingOutput =
+            IDrawingOutput::new(&mut overlay_vertices, &mut overlay_indices);
+        drawing_output.last_index = draw_cache.get_first_vertex(overlay_ind, vertex_buffer);
+        let overlay_color = color::rbga_to_float(&settings.overlay_color);
+        for entity in overlay.iter() {
+            entity.update_draw_sequence(
+                overlay_color,
+                &Array2::zeros((1, 2)),
                 &drawing_parameters,
+                &settings.cross_size,
+                &settings.viewport,
                 &mut drawing_output,
             );
         }
+        let statistics = drawing_output.get_statistics();
+        draw_cache.replace_block(
+            overlay_ind,
+            !overlay.is_empty(),
+            (overlay_vertices.buffer, overlay_indices.buffer),
+            statistics,
+            vertex_buffer,
+            index_buffer,
+        );
 
-        settings.highlight_nr_selected_entities = drawing_output.nr_entities;
-        settings.highlight_width_height = drawing_output.get_width_height();
-        settings.highlight_rotation_center = drawing_output.get_center_bbox();
+        let statistics = draw_cache.get_statistics();
+        settings.highlight_nr_selected_entities = statistics.nr_entities;
+        settings.highlight_width_height = statistics.get_width_height();
+        settings.highlight_rotation_center = statistics.get_center_bbox();
     }
 
-    pub(crate) fn update_draw_sequence(
-        &self,
-        settings: &mut user_settings::ISettings,
-        vertex_buffer: &mut VertexBuffer,
-        index_buffer: &mut IndexBuffer,
-    ) {
-        // View with name Block=>L-1 will attempt to draw L-1
-        if let Some(block_key) = parse::view_as_block_key(&settings.view) {
-            self.update_draw_sequence_block(settings, &block_key, vertex_buffer, index_buffer);
-        } else {
-            self.update_draw_sequence_model(settings, vertex_buffer, index_buffer);
-        }
+    pub(crate) fn export_svg(&self, settings: &user_settings::ISettings) -> String {
+        return write_svg::write_svg(self, settings);
     }
 
     pub(crate) fn block_in_pattern(&self, block_name: &String) -> Option<&block::Block> {
-        for block in self.blocks.iter() {
-            if &block.name == block_name {
-                return Some(block);
-            }
+        if let Some(block_ind) = self.block_lookup.get(block_name) {
+            return Some(&self.blocks[*block_ind]);
         }
-        console::log_1(&format!("Block key {} not in pattern", block_name).into());
+        log::warning(&format!("Block key {} not in pattern", block_name));
         return None;
     }
 
@@ -245,6 +1579,28 @@ impl Pattern {
         for block in self.blocks.iter_mut() {
             block.remove_highlight();
         }
+        for insert_ref in self.all_insert_refs() {
+            self.set_insert_ref_highlight(&insert_ref, false);
+        }
+    }
+
+    pub(crate) fn set_insert_highlight(&mut self, insert_index: &str, status: bool) -> bool {
+        let insert_ref = match parse_entity_index(insert_index).and_then(|i| self.find_insert(&i)) {
+            Some(insert_ref) => insert_ref,
+            None => return false,
+        };
+        self.set_insert_ref_highlight(&insert_ref, status);
+        return true;
+    }
+
+    pub fn get_highlighted_insert_indexes(&self) -> Vec<String> {
+        return self
+            .all_insert_refs()
+            .iter()
+            .map(|r| self.get_insert(r))
+            .filter(|i| i.highlighted)
+            .map(|i| i.insert_index_as_uuid())
+            .collect();
     }
 
     pub fn get_all_layers(&self) -> Vec<i32> {
@@ -269,66 +1625,76 @@ impl Pattern {
         return output;
     }
 
-    pub(crate) fn find_blocks_with_point(
+    pub(crate) fn find_instances_with_point(
         &self,
         point: &(f32, f32),
         settings: &user_settings::ISettings,
-    ) -> Vec<String> {
-        let mut selected_block_keys: Vec<String> = vec![];
+    ) -> Vec<Instance> {
+        let mut selected_instances: Vec<Instance> = vec![];
+        let region = point_region(point, settings.point_threshold);
 
-        for block in self.blocks.iter() {
-            let offset = self.get_offset_for_block(&block.name);
-            let offset_point = (point.0 - offset[(0, 0)], point.1 - offset[(0, 1)]);
+        for instance in self.instances_in_region(&settings.view, &region) {
+            let block = &self.blocks[instance.block];
+            let block_point = match instance.transform.inverse() {
+                Some(inverse) => inverse.apply_point(point),
+                None => continue,
+            };
+            let threshold = settings.point_threshold / instance.transform.get_min_scale();
 
-            if block.point_in_bounding_box(&offset_point, settings.point_threshold) {
-                selected_block_keys.push(block.name.clone());
+            if block.point_in_bounding_box(&block_point, threshold)
+                && block.has_entity_at_point(&block_point, threshold, &settings.disabled_layers)
+            {
+                selected_instances.push(instance);
             }
         }
 
-        return selected_block_keys;
+        return selected_instances;
     }
 
-    pub(crate) fn find_blocks_with_bbox(
+    pub(crate) fn find_instances_with_bbox(
         &self,
         bbox: &((f32, f32), (f32, f32)),
-        view: &String,
-    ) -> (Vec<String>, Option<((f32, f32), (f32, f32))>) {
-        let mut selected_block_keys: Vec<String> = vec![];
+        view: &str,
+    ) -> (Vec<Instance>, Option<bounding_box::BoundingBox>) {
+        let mut selected_instances: Vec<Instance> = vec![];
         let mut union_box = Option::None;
-        let view_single_block_key = parse::view_as_block_key(view); // Will be block name if viewing one block
-
-        for block in self.blocks.iter() {
-            let offset: Array2<f32>;
-            if let Some(key) = &view_single_block_key {
-                if &block.name != key {
-                    continue; // Skip current block is viewing only one block with a different name to this
-                }
-                offset = array![[0., 0.]];
-            } else {
-                offset = self.get_offset_for_block(&block.name);
-            }
 
-            let offset_bbox = bounding_box::offset_bbox(block.get_bounding_box(), &offset);
+        for instance in self.instances_in_region(view, bbox) {
+            let placed_bbox = self.instance_bounding_box(&instance);
 
-            if bounding_box::intersect(&offset_bbox, bbox) {
-                selected_block_keys.push(block.name.clone());
+            if bounding_box::intersect(&placed_bbox, bbox) {
+                selected_instances.push(instance);
                 if let Some(current_union) = union_box {
-                    union_box = Some(bounding_box::union(&current_union, &offset_bbox));
+                    union_box = Some(bounding_box::union(&current_union, &placed_bbox));
                 } else {
-                    union_box = Some(offset_bbox);
+                    union_box = Some(placed_bbox);
                 }
             }
         }
 
-        return (selected_block_keys, union_box);
+        return (selected_instances, union_box);
+    }
+
+    // Names of the blocks placed by a list of placements, each once
+    pub(crate) fn get_instance_block_keys(&self, instances: &[Instance]) -> Vec<String> {
+        let mut output: Vec<String> = vec![];
+        for instance in instances.iter() {
+            let block_key = &self.blocks[instance.block].name;
+            if !output.contains(block_key) {
+                output.push(block_key.clone());
+            }
+        }
+        return output;
     }
 
-    pub(crate) fn highlight_selection(&mut self, block_keys: &Vec<String>) {
+    // Select placements through their inserts, blocks drawn at the origin are selected whole
+    pub(crate) fn highlight_instances(&mut self, instances: &[Instance]) {
         self.reset_selection();
 
-        for block in self.blocks.iter_mut() {
-            if block_keys.contains(&block.name) {
-                block.highlight();
+        for instance in instances.iter() {
+            match &instance.insert {
+                Some(insert_ref) => self.set_insert_ref_highlight(insert_ref, true),
+                None => self.blocks[instance.block].highlight(),
             }
         }
     }
@@ -349,26 +1715,65 @@ impl Pattern {
         return block_exists;
     }
 
-    pub(crate) fn offset_highlighted_objects(&mut self, offset: (f32, f32), view: &String) {
+    pub(crate) fn offset_highlighted_objects(&mut self, offset: (f32, f32), view: &str) {
         let (x, y) = offset;
         let arr_offset: Array2<f32> = array![[x, y]];
         let view_single_block_key = parse::view_as_block_key(view);
+        let placements = self.first_placements(view);
+        let mut moved_blocks: Vec<usize> = vec![];
+        let mut moved_inserts: Vec<(InsertRef, Array2<f32>)> = vec![];
 
-        for block in self.blocks.iter_mut() {
+        for (block_ind, block) in self.blocks.iter_mut().enumerate() {
+            // The offset seen from inside the block, the same as in the view unless it is rotated or scaled
+            let block_offset = match placements[block_ind].as_ref().and_then(|p| p.inverse()) {
+                Some(inverse) => inverse.apply_vector(&arr_offset),
+                None => continue,
+            };
             if !block.is_highlighted() {
+                if block.has_highlighted_entities() {
+                    block.offset_highlighted_entities(&block_offset);
+                    moved_blocks.push(block_ind);
+                }
                 continue;
             }
-            if view_single_block_key != Option::None {
+            moved_blocks.push(block_ind);
+            if view_single_block_key.is_some() {
                 // If looking at single block, offset entities in block
-                block.offset_entities(&arr_offset);
-                break;
+                block.offset_entities(&block_offset);
+                continue;
             }
-            for insert in self.entities.iter_mut() {
-                if insert.name == block.name {
-                    insert.position += &arr_offset;
+            // Otherwise move the inserts that place the block, each in its own space
+            for instance_ind in self.block_instances[block_ind].iter() {
+                let instance = &self.instances[*instance_ind];
+                let insert_ref = match instance.insert {
+                    Some(insert_ref) => insert_ref,
+                    None => continue,
+                };
+                if moved_inserts.iter().any(|(r, _)| r == &insert_ref) {
+                    continue;
+                }
+                if let Some(inverse) = instance.parent.inverse() {
+                    moved_inserts.push((insert_ref, inverse.apply_vector(&arr_offset)));
                 }
             }
-            // ToDo - else check individual entities
+        }
+
+        // Selected placements move their own insert only
+        for (insert_ref, instance) in self.highlighted_placements(view) {
+            if moved_inserts.iter().any(|(r, _)| r == &insert_ref) {
+                continue;
+            }
+            if let Some(inverse) = instance.parent.inverse() {
+                moved_inserts.push((insert_ref, inverse.apply_vector(&arr_offset)));
+            }
+        }
+
+        for (insert_ref, insert_offset) in moved_inserts.iter() {
+            self.get_insert_mut(insert_ref).position += insert_offset;
+        }
+        self.refresh_moved_blocks(&moved_blocks);
+        if !moved_inserts.is_empty() {
+            self.refresh_instances();
         }
     }
 
@@ -376,97 +1781,167 @@ impl Pattern {
         &mut self,
         scale: &ndarray::Array2<f32>,
         anchor: &ndarray::Array2<f32>,
-        view: &String,
+        view: &str,
     ) {
-        let view_single_block_key = parse::view_as_block_key(view);
+        let placements = self.first_placements(view);
+        let mut moved_blocks: Vec<usize> = vec![];
+        let edit = Transform::new(
+            array![[scale[(0, 0)], 0.], [0., scale[(0, 1)]]],
+            anchor - &(anchor * scale),
+        );
 
-        for block in self.blocks.iter_mut() {
-            if !block.is_highlighted() {
+        for (block_ind, block) in self.blocks.iter_mut().enumerate() {
+            let partial_selection = !block.is_highlighted() && block.has_highlighted_entities();
+            if !block.is_highlighted() && !partial_selection {
                 continue;
             }
-            if view_single_block_key != Option::None {
-                // If looking at single block, offset entities in block
-                block.scale_entities(scale, anchor);
-                break;
+            let placement = match &placements[block_ind] {
+                Some(placement) => placement,
+                None => continue,
+            };
+            moved_blocks.push(block_ind);
+
+            if placement.is_translation() {
+                let offset_anchor = anchor - &placement.translation;
+                if partial_selection {
+                    block.scale_highlighted_entities(scale, &offset_anchor);
+                } else {
+                    block.scale_entities(scale, &offset_anchor);
+                }
+                continue;
             }
-            // ToDo, Since block does not own its offset, can't safely use a function here
-            // Add a cached offset to a block
-            let mut offset: &Array2<f32> = &array![[0., 0.]];
-            for insert in self.entities.iter() {
-                if insert.name == block.name {
-                    offset = &insert.position;
-                    break;
+            // Scaling along the view axes turns into a general affine edit inside a rotated block
+            if let Some(block_edit) = edit_in_block(placement, &edit) {
+                if partial_selection {
+                    block.transform_highlighted_entities(&block_edit);
+                } else {
+                    block.transform_entities(&block_edit);
                 }
             }
-            let offset_anchor = anchor - offset;
-            block.scale_entities(scale, &offset_anchor);
-            // ToDo - else check individual entities
         }
+        self.refresh_moved_blocks(&moved_blocks);
+        self.transform_highlighted_placements(&edit, view);
     }
 
     pub(crate) fn rotate_highlights(
         &mut self,
         rot_matrix: &ndarray::Array2<f32>,
         rot_offset: &ndarray::Array2<f32>,
-        view: &String,
+        view: &str,
     ) {
-        let view_single_block_key = parse::view_as_block_key(view);
+        let placements = self.first_placements(view);
+        let mut moved_blocks: Vec<usize> = vec![];
+        let edit = Transform::new(rot_matrix.clone(), rot_offset.clone());
 
-        for block in self.blocks.iter_mut() {
-            if !block.is_highlighted() {
+        for (block_ind, block) in self.blocks.iter_mut().enumerate() {
+            let partial_selection = !block.is_highlighted() && block.has_highlighted_entities();
+            if !block.is_highlighted() && !partial_selection {
                 continue;
             }
-            if view_single_block_key != Option::None {
-                // If looking at single block, offset entities in block
-                block.rotate_entities(rot_matrix, rot_offset);
-                break;
-            }
+            let placement = match &placements[block_ind] {
+                Some(placement) => placement,
+                None => continue,
+            };
+            moved_blocks.push(block_ind);
 
-            let mut offset: &Array2<f32> = &array![[0., 0.]];
-            for insert in self.entities.iter() {
-                if insert.name == block.name {
-                    offset = &insert.position;
-                    break;
+            if placement.is_translation() {
+                let offset = &placement.translation;
+                let offset_rot_center: Array2<f32> = rot_offset - offset + offset.dot(rot_matrix);
+                if partial_selection {
+                    block.rotate_highlighted_entities(rot_matrix, &offset_rot_center);
+                } else {
+                    block.rotate_entities(rot_matrix, &offset_rot_center);
+                }
+                continue;
+            }
+            if let Some(block_edit) = edit_in_block(placement, &edit) {
+                if partial_selection {
+                    block.transform_highlighted_entities(&block_edit);
+                } else {
+                    block.transform_entities(&block_edit);
                 }
             }
-            let offset_rot_center: Array2<f32> = rot_offset - offset + offset.dot(rot_matrix);
-            block.rotate_entities(rot_matrix, &offset_rot_center);
-            // ToDo - else check individual entities
+        }
+        self.refresh_moved_blocks(&moved_blocks);
+        self.transform_highlighted_placements(&edit, view);
+    }
+
+    // Selected placements take an edit into their own insert and leave the block alone,
+    // unless the edit shears the placement, then the block geometry takes it instead
+    fn transform_highlighted_placements(&mut self, edit: &Transform, view: &str) {
+        let mut changed = false;
+        let mut moved_blocks: Vec<usize> = vec![];
+        for (insert_ref, instance) in self.highlighted_placements(view) {
+            let insert_edit = match edit_in_block(&instance.parent, edit) {
+                Some(insert_edit) => insert_edit,
+                None => continue,
+            };
+            let insert = self.get_insert_mut(&insert_ref);
+            let local = insert.get_transforms()[0].clone();
+            if insert.set_placement(&local.then(&insert_edit)) {
+                changed = true;
+                continue;
+            }
+
+            let name = insert.name.clone();
+            let block_ind = match self.block_lookup.get(&name) {
+                Some(block_ind) => *block_ind,
+                None => continue,
+            };
+            let block_edit = match edit_in_block(&local, &insert_edit) {
+                Some(block_edit) => block_edit,
+                None => continue,
+            };
+            log::warning(&format!(
+                "Insert of {} can not be sheared, the edit was applied to the block",
+                name
+            ));
+            self.blocks[block_ind].transform_entities(&block_edit);
+            if !moved_blocks.contains(&block_ind) {
+                moved_blocks.push(block_ind);
+            }
+        }
+        self.refresh_moved_blocks(&moved_blocks);
+        if changed {
+            self.refresh_instances();
         }
     }
 
     pub(crate) fn get_highlighted_bounding_box(
         &mut self,
-        view: &String,
+        view: &str,
     ) -> Option<((f32, f32), (f32, f32))> {
-        let view_single_block_key = parse::view_as_block_key(view);
+        let placements = self.first_placements(view);
         let mut output: Option<((f32, f32), (f32, f32))> = Option::None;
 
-        for block in self.blocks.iter_mut() {
-            if !block.is_highlighted() {
+        for (block_ind, block) in self.blocks.iter().enumerate() {
+            let bbox = if block.is_highlighted() {
+                *block.get_bounding_box()
+            } else if let Some(entities_bbox) = block.get_highlighted_entities_bounding_box() {
+                entities_bbox
+            } else {
                 continue;
-            }
-            if view_single_block_key != Option::None {
-                // If looking at single block, offset entities in block
-                return Some(block.get_bounding_box().clone());
-            }
-
-            let mut offset: Array2<f32> = array![[0., 0.]];
-            for insert in self.entities.iter() {
-                if insert.name == block.name {
-                    offset = insert.position.clone();
-                    break;
-                }
-            }
-            let bbox = block.get_bounding_box();
-            let offset_bbox = bounding_box::offset_bbox(bbox, &offset);
+            };
+            let placed_bbox = match &placements[block_ind] {
+                Some(placement) => placement.apply_bbox(&bbox),
+                None => continue,
+            };
 
             if let Some(union_bbox) = output {
-                output = Some(bounding_box::union(&offset_bbox, &union_bbox));
+                output = Some(bounding_box::union(&placed_bbox, &union_bbox));
             } else {
-                output = Some(offset_bbox);
+                output = Some(placed_bbox);
+            }
+        }
+        for instance in self.instances_in_view(view) {
+            if !self.is_instance_highlighted(&instance) {
+                continue;
             }
-            // ToDo - else check individual entities
+            let placed_bbox = self.instance_bounding_box(&instance);
+            output = Some(match output {
+                Some(union_bbox) => bounding_box::union(&placed_bbox, &union_bbox),
+                None => placed_bbox,
+            });
         }
         return output;
     }
@@ -0,0 +1,304 @@
+// Seam allowance around closed pattern pieces, each edge is moved outwards by its own width
+// and the corners are joined up again
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{Entity, CURVE_VERTEX_TOLERANCE};
+use crate::utils::bounding_box;
+use crate::utils::geometry;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    // Edges are extended until they meet
+    Miter,
+    // An arc around the corner
+    Round,
+    // A straight cut across the corner
+    Bevel,
+}
+
+// Miters reaching further from the corner than this many widths are bevelled instead
+pub const MITER_LIMIT: f32 = 4.;
+// Points closer than this are taken to be the same
+const POINT_TOLERANCE: f32 = 1e-5;
+// Vertices closer than this to another edge are taken to lie on it
+const TOUCH_TOLERANCE: f32 = 1e-4;
+
+// Points of a polygon with the width of the edge each one starts
+type WidthEdges = (Vec<(f32, f32)>, Vec<f32>);
+// Point where an outline meets itself and the first and last index of the stretches either side
+type Split = ((f32, f32), (usize, usize), (usize, usize));
+
+impl JoinType {
+    pub fn parse(name: &str) -> Option<JoinType> {
+        return match name.to_lowercase().as_str() {
+            "miter" | "mitre" => Some(JoinType::Miter),
+            "round" => Some(JoinType::Round),
+            "bevel" => Some(JoinType::Bevel),
+            _ => None,
+        };
+    }
+}
+
+// Outline of the seam allowance around a closed entity. Widths hold one value for every edge or
+// one for each segment of the entity, curves only have the one edge
+pub fn entity_seam_allowance(
+    entity: &Entity,
+    widths: &[f32],
+    join: JoinType,
+) -> Option<Array2<f32>> {
+    if !entity.shape || widths.iter().any(|w| !w.is_finite() || *w < 0.) {
+        return None;
+    }
+    let (outline, segments) = match &entity.curve {
+        Some(_) => {
+            let num_rows = entity.vertices.shape()[0];
+            (entity.vertices.clone(), vec![0; num_rows])
+        }
+        None => entity.get_outline(CURVE_VERTEX_TOLERANCE),
+    };
+    let num_edges = match &entity.curve {
+        Some(_) => 1,
+        None => entity.vertices.shape()[0],
+    };
+    if widths.len() != 1 && widths.len() != num_edges {
+        return None;
+    }
+
+    let polygon: Vec<(f32, f32)> = outline.rows().into_iter().map(|v| (v[0], v[1])).collect();
+    let edge_widths: Vec<f32> = segments
+        .iter()
+        .map(|s| {
+            if widths.len() == 1 {
+                widths[0]
+            } else {
+                widths[*s]
+            }
+        })
+        .collect();
+    let offset = offset_polygon(&polygon, &edge_widths, join, CURVE_VERTEX_TOLERANCE)?;
+
+    let vertex_data: Vec<f32> = offset.iter().flat_map(|(x, y)| [*x, *y]).collect();
+    return Array2::from_shape_vec((offset.len(), 2), vertex_data).ok();
+}
+
+// Offsets a closed polygon outwards, edge i runs from vertex i to the next and moves by widths[i].
+// The result runs counter clockwise whichever way round the polygon was given
+pub fn offset_polygon(
+    polygon: &[(f32, f32)],
+    widths: &[f32],
+    join: JoinType,
+    tolerance: f32,
+) -> Option<Vec<(f32, f32)>> {
+    if polygon.len() != widths.len() {
+        return None;
+    }
+    let (points, widths) = counter_clockwise_edges(polygon, widths)?;
+    let n = points.len();
+
+    // Outward normals of a counter clockwise polygon point to the right of each edge
+    let normals: Vec<(f32, f32)> = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            let length = geometry::distance(&a, &b);
+            ((b.1 - a.1) / length, (a.0 - b.0) / length)
+        })
+        .collect();
+    let moved = |p: (f32, f32), normal: (f32, f32), width: f32| {
+        (p.0 + normal.0 * width, p.1 + normal.1 * width)
+    };
+
+    let mut output: Vec<(f32, f32)> = vec![];
+    for i in 0..n {
+        let prev = (i + n - 1) % n;
+        let corner = points[i];
+        let (prev_normal, normal) = (normals[prev], normals[i]);
+        let (prev_width, width) = (widths[prev], widths[i]);
+        // Ends of the moved edges that meet at this corner
+        let prev_end = moved(corner, prev_normal, prev_width);
+        let start = moved(corner, normal, width);
+        // Positive for a left turn, which is an outside corner of a counter clockwise polygon
+        let turn = prev_normal.0 * normal.1 - prev_normal.1 * normal.0;
+        let parallel = turn.abs() < 1e-6;
+        let straight = parallel && prev_normal.0 * normal.0 + prev_normal.1 * normal.1 > 0.;
+        // An edge doubling back on the last one is capped like an outside corner
+        let inside = straight || (turn < 0. && !parallel);
+
+        if geometry::distance(&prev_end, &start) <= POINT_TOLERANCE {
+            output.push(start);
+        } else if inside {
+            // Inside corners overlap, the loop this leaves is cut away afterwards
+            output.extend([prev_end, start]);
+        } else {
+            match join {
+                JoinType::Bevel => output.extend([prev_end, start]),
+                JoinType::Miter => {
+                    let prev_start = moved(points[prev], prev_normal, prev_width);
+                    let next_end = moved(points[(i + 1) % n], normal, width);
+                    let limit = MITER_LIMIT * prev_width.max(width);
+                    match geometry::line_intersection(&prev_start, &prev_end, &start, &next_end) {
+                        Some(tip) if geometry::distance(&tip, &corner) <= limit => output.push(tip),
+                        _ => output.extend([prev_end, start]),
+                    }
+                }
+                JoinType::Round => {
+                    output.extend(round_corner(
+                        corner,
+                        (prev_normal, prev_width),
+                        (normal, width),
+                        tolerance,
+                    ));
+                }
+            }
+        }
+    }
+    return Some(remove_loops(output));
+}
+
+// Drops repeated points and turns the polygon counter clockwise, keeping each width with its edge
+fn counter_clockwise_edges(polygon: &[(f32, f32)], widths: &[f32]) -> Option<WidthEdges> {
+    let n = polygon.len();
+    let mut points: Vec<(f32, f32)> = vec![];
+    let mut edge_widths: Vec<f32> = vec![];
+    for i in 0..n {
+        if geometry::distance(&polygon[i], &polygon[(i + 1) % n]) > POINT_TOLERANCE {
+            points.push(polygon[i]);
+            edge_widths.push(widths[i]);
+        }
+    }
+    if points.len() < 3 {
+        return None;
+    }
+    let area = geometry::signed_area(&points);
+    if area == 0. || !area.is_finite() {
+        return None;
+    }
+    if area < 0. {
+        // Walking backwards edge i runs from vertex i + 1 to vertex i
+        let n = points.len();
+        points.reverse();
+        edge_widths = (0..n).map(|k| edge_widths[(2 * n - 2 - k) % n]).collect();
+    }
+    return Some((points, edge_widths));
+}
+
+// Arc round an outside corner, the radius runs from one edge width to the other
+fn round_corner(
+    corner: (f32, f32),
+    from: ((f32, f32), f32),
+    to: ((f32, f32), f32),
+    tolerance: f32,
+) -> Vec<(f32, f32)> {
+    let ((from_normal, from_width), (to_normal, to_width)) = (from, to);
+    let start_angle = from_normal.1.atan2(from_normal.0);
+    let mut sweep = to_normal.1.atan2(to_normal.0) - start_angle;
+    if sweep <= 0. {
+        sweep += 2. * std::f32::consts::PI;
+    }
+    let radius = from_width.max(to_width);
+    let step = if tolerance < radius {
+        2. * (1. - tolerance / radius).acos()
+    } else {
+        std::f32::consts::FRAC_PI_2
+    };
+    let num_steps = (sweep / step).ceil().max(1.) as usize;
+
+    return (0..=num_steps)
+        .map(|k| {
+            let t = k as f32 / num_steps as f32;
+            let angle = start_angle + sweep * t;
+            let r = from_width + (to_width - from_width) * t;
+            (corner.0 + r * angle.cos(), corner.1 + r * angle.sin())
+        })
+        .collect();
+}
+
+// Splits the outline where it crosses or touches itself and keeps the outer loop, loops that
+// run clockwise are overlaps at inside corners or holes closed off by the allowance
+fn remove_loops(mut points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    while let Some((split, first, second)) = first_split(&points) {
+        let loop_a: Vec<(f32, f32)> = std::iter::once(split)
+            .chain(cyclic_range(&points, first))
+            .collect();
+        let loop_b: Vec<(f32, f32)> = std::iter::once(split)
+            .chain(cyclic_range(&points, second))
+            .collect();
+        let (area_a, area_b) = (
+            geometry::signed_area(&loop_a),
+            geometry::signed_area(&loop_b),
+        );
+        let keep_a = if (area_a > 0.) != (area_b > 0.) {
+            area_a > 0.
+        } else {
+            area_a.abs() > area_b.abs()
+        };
+        points = without_repeats(if keep_a { loop_a } else { loop_b });
+    }
+    return points;
+}
+
+// Points from the first index to the second inclusive, wrapping round the end
+fn cyclic_range(points: &[(f32, f32)], range: (usize, usize)) -> Vec<(f32, f32)> {
+    let n = points.len();
+    let (mut k, last) = range;
+    let mut output: Vec<(f32, f32)> = vec![];
+    loop {
+        output.push(points[k]);
+        if k == last {
+            return output;
+        }
+        k = (k + 1) % n;
+    }
+}
+
+fn without_repeats(points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    let n = points.len();
+    return (0..n)
+        .filter(|i| geometry::distance(&points[*i], &points[(i + 1) % n]) > POINT_TOLERANCE)
+        .map(|i| points[i])
+        .collect();
+}
+
+// Where the outline first meets itself, along with the two stretches of points either side.
+// Edges running over each other never cross, so a vertex lying on another edge counts as well
+fn first_split(points: &[(f32, f32)]) -> Option<Split> {
+    let n = points.len();
+    if n < 4 {
+        return None;
+    }
+    let edge_box = |i: usize| {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        bounding_box::pad(
+            &((a.0.min(b.0), a.0.max(b.0)), (a.1.min(b.1), a.1.max(b.1))),
+            TOUCH_TOLERANCE,
+        )
+    };
+    for i in 0..n {
+        let (a1, a2) = (&points[i], &points[(i + 1) % n]);
+        let a_box = edge_box(i);
+        for j in 0..n {
+            if !bounding_box::intersect(&a_box, &edge_box(j)) {
+                continue;
+            }
+            let (b1, b2) = (&points[j], &points[(j + 1) % n]);
+            let adjacent = j == i || j == (i + 1) % n || i == (j + 1) % n;
+            if j > i && !adjacent {
+                if let Some(crossing) = geometry::segment_crossing(a1, a2, b1, b2) {
+                    return Some((crossing, (i + 1, j), ((j + 1) % n, i)));
+                }
+            }
+            // Vertex j lies inside edge i
+            if j != i && j != (i + 1) % n && touches(b1, a1, a2) {
+                return Some((*b1, ((i + 1) % n, (j + n - 1) % n), ((j + 1) % n, i)));
+            }
+        }
+    }
+    return None;
+}
+
+fn touches(p: &(f32, f32), a: &(f32, f32), b: &(f32, f32)) -> bool {
+    let (closest, distance) = geometry::closest_point_on_segment(p, a, b);
+    return distance <= TOUCH_TOLERANCE
+        && geometry::distance(&closest, a) > TOUCH_TOLERANCE
+        && geometry::distance(&closest, b) > TOUCH_TOLERANCE;
+}
@@ -0,0 +1,215 @@
+// Object snapping, moves a cursor onto exact points of the geometry drawn near it
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{Entity, EntityTypes, CURVE_VERTEX_TOLERANCE};
+use crate::measure;
+use crate::utils::geometry;
+
+// Kinds of snap, in the order they are preferred when two are as close
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SnapMode {
+    // Ends of lines and curves, and the vertices of polylines and points
+    Endpoint,
+    Midpoint,
+    // Where two entities cross
+    Intersection,
+    // Foot of the line from the base point that meets an entity at a right angle
+    Perpendicular,
+    // Closest point on an entity, only used when no other snap is in reach
+    Nearest,
+}
+
+impl SnapMode {
+    pub fn parse(name: &str) -> Option<SnapMode> {
+        return match name.to_lowercase().as_str() {
+            "endpoint" => Some(SnapMode::Endpoint),
+            "midpoint" => Some(SnapMode::Midpoint),
+            "intersection" => Some(SnapMode::Intersection),
+            "perpendicular" => Some(SnapMode::Perpendicular),
+            "nearest" => Some(SnapMode::Nearest),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapTarget {
+    pub point: (f32, f32),
+    pub mode: SnapMode,
+    // Entities the point lies on, both of them for an intersection
+    pub entity_indexes: Vec<String>,
+    // From the cursor to the point
+    pub distance: f32,
+}
+
+type Edge = ((f32, f32), (f32, f32));
+
+// Straight pieces an entity is drawn with, arcs and curves are split into chords
+fn get_edges(entity: &Entity) -> Vec<Edge> {
+    if matches!(entity.entity_type, EntityTypes::POINT | EntityTypes::TEXT) {
+        return vec![];
+    }
+    let outline = match &entity.curve {
+        Some(_) => entity.vertices.clone(),
+        None => entity.get_outline(CURVE_VERTEX_TOLERANCE).0,
+    };
+    let points: Vec<(f32, f32)> = outline.rows().into_iter().map(|v| (v[0], v[1])).collect();
+    let n = points.len();
+    if n < 2 {
+        return vec![];
+    }
+    let num_edges = if entity.shape { n } else { n - 1 };
+    return (0..num_edges)
+        .map(|i| (points[i], points[(i + 1) % n]))
+        .collect();
+}
+
+// Vertices that are part of the entity, of a curve only its two ends are
+fn get_endpoints(entity: &Entity) -> Vec<(f32, f32)> {
+    let vertices: Vec<(f32, f32)> = entity
+        .vertices
+        .rows()
+        .into_iter()
+        .map(|v| (v[0], v[1]))
+        .collect();
+    return match &entity.curve {
+        Some(_) if entity.shape => vec![],
+        Some(_) => vertices
+            .first()
+            .into_iter()
+            .chain(vertices.last())
+            .copied()
+            .collect(),
+        None => vertices,
+    };
+}
+
+// Foot of the perpendicular from a point on each edge it meets inside the edge
+fn get_perpendicular_feet(entity: &Entity, base: &(f32, f32)) -> Vec<(f32, f32)> {
+    let mut output: Vec<(f32, f32)> = vec![];
+    for (a, b) in get_edges(entity) {
+        let (d_x, d_y) = (b.0 - a.0, b.1 - a.1);
+        let length_squared = d_x * d_x + d_y * d_y;
+        if length_squared == 0. {
+            continue;
+        }
+        let t = ((base.0 - a.0) * d_x + (base.1 - a.1) * d_y) / length_squared;
+        if (0. ..=1.).contains(&t) {
+            output.push((a.0 + t * d_x, a.1 + t * d_y));
+        }
+    }
+    return output;
+}
+
+// Snap points of a single entity for the active modes
+fn get_entity_targets(
+    entity: &Entity,
+    cursor: &(f32, f32),
+    modes: &[SnapMode],
+    base: Option<&(f32, f32)>,
+) -> Vec<(SnapMode, (f32, f32))> {
+    let mut output: Vec<(SnapMode, (f32, f32))> = vec![];
+    if modes.contains(&SnapMode::Endpoint) {
+        output.extend(
+            get_endpoints(entity)
+                .into_iter()
+                .map(|p| (SnapMode::Endpoint, p)),
+        );
+    }
+    if modes.contains(&SnapMode::Midpoint) {
+        output.extend(
+            measure::get_midpoints(entity)
+                .into_iter()
+                .map(|p| (SnapMode::Midpoint, p)),
+        );
+    }
+    if let (true, Some(base)) = (modes.contains(&SnapMode::Perpendicular), base) {
+        output.extend(
+            get_perpendicular_feet(entity, base)
+                .into_iter()
+                .map(|p| (SnapMode::Perpendicular, p)),
+        );
+    }
+    if modes.contains(&SnapMode::Nearest) && entity.entity_type != EntityTypes::TEXT {
+        if let Some(closest) = entity.get_closest_point_on_entity(cursor) {
+            output.push((SnapMode::Nearest, closest.point));
+        }
+    }
+    return output;
+}
+
+// Crossings between the edges of different entities that pass within threshold of the cursor
+fn get_intersections(
+    entities: &[Entity],
+    cursor: &(f32, f32),
+    threshold: f32,
+) -> Vec<((f32, f32), usize, usize)> {
+    let near_edges: Vec<Vec<Edge>> = entities
+        .iter()
+        .map(|e| {
+            get_edges(e)
+                .into_iter()
+                .filter(|(a, b)| geometry::closest_point_on_segment(cursor, a, b).1 <= threshold)
+                .collect()
+        })
+        .collect();
+    let mut output: Vec<((f32, f32), usize, usize)> = vec![];
+    for i in 0..entities.len() {
+        for j in i + 1..entities.len() {
+            for (a1, a2) in near_edges[i].iter() {
+                for (b1, b2) in near_edges[j].iter() {
+                    if let Some(crossing) = geometry::segment_crossing(a1, a2, b1, b2) {
+                        output.push((crossing, i, j));
+                    }
+                }
+            }
+        }
+    }
+    return output;
+}
+
+// Best snap within threshold of the cursor among entities in the same coordinates. Nearest
+// points only count when nothing else is in reach, otherwise the closest target wins
+pub fn find_snap(
+    cursor: &(f32, f32),
+    entities: &[Entity],
+    modes: &[SnapMode],
+    base: Option<&(f32, f32)>,
+    threshold: f32,
+) -> Option<SnapTarget> {
+    let mut targets: Vec<SnapTarget> = vec![];
+    for entity in entities.iter() {
+        for (mode, point) in get_entity_targets(entity, cursor, modes, base) {
+            targets.push(SnapTarget {
+                point: point,
+                mode: mode,
+                entity_indexes: vec![entity.entity_index_as_uuid()],
+                distance: geometry::distance(cursor, &point),
+            });
+        }
+    }
+    if modes.contains(&SnapMode::Intersection) {
+        for (point, i, j) in get_intersections(entities, cursor, threshold) {
+            targets.push(SnapTarget {
+                point: point,
+                mode: SnapMode::Intersection,
+                entity_indexes: vec![
+                    entities[i].entity_index_as_uuid(),
+                    entities[j].entity_index_as_uuid(),
+                ],
+                distance: geometry::distance(cursor, &point),
+            });
+        }
+    }
+
+    return targets
+        .into_iter()
+        .filter(|t| t.distance <= threshold)
+        .min_by(|a, b| {
+            let (a_nearest, b_nearest) = (a.mode == SnapMode::Nearest, b.mode == SnapMode::Nearest);
+            a_nearest
+                .cmp(&b_nearest)
+                .then(a.distance.total_cmp(&b.distance))
+                .then(a.mode.cmp(&b.mode))
+        });
+}
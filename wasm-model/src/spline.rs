@@ -0,0 +1,396 @@
+// B-spline and NURBS curves as stored in DXF SPLINE entities
+use ndarray::Array2;
+
+use crate::utils::geometry;
+
+// Every knot span is split at least this many times so S shaped spans are not taken for straight
+const MIN_SUBDIVISIONS: u32 = 2;
+const MAX_SUBDIVISIONS: u32 = 16;
+// Fine enough that the box of the tessellation is the box of the curve for drawing purposes
+const BOUNDING_BOX_TOLERANCE: f32 = 1e-3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    pub degree: usize,
+    pub knots: Vec<f32>,
+    pub control_points: Vec<(f32, f32)>,
+    // One per control point, all ones for a non rational spline
+    pub weights: Vec<f32>,
+    // Points the curve was designed to pass through, only kept to write them out again
+    pub fit_points: Vec<(f32, f32)>,
+    pub closed: bool,
+}
+
+// Index of the knot span holding t, the last non empty span for the end of the domain
+fn find_span(knots: &[f32], degree: usize, num_control_points: usize, t: f32) -> usize {
+    let mut span = degree;
+    while span + 1 < num_control_points && t >= knots[span + 1] {
+        span += 1;
+    }
+    return span;
+}
+
+// Values of the degree + 1 basis functions that are non zero on a knot span
+fn basis_functions(knots: &[f64], degree: usize, span: usize, t: f64) -> Vec<f64> {
+    let mut values = vec![0.; degree + 1];
+    let mut left = vec![0.; degree + 1];
+    let mut right = vec![0.; degree + 1];
+    values[0] = 1.;
+    for j in 1..=degree {
+        left[j] = t - knots[span + 1 - j];
+        right[j] = knots[span + j] - t;
+        let mut saved = 0.;
+        for r in 0..j {
+            let denominator = right[r + 1] + left[j - r];
+            let temp = if denominator == 0. {
+                0.
+            } else {
+                values[r] / denominator
+            };
+            values[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        values[j] = saved;
+    }
+    return values;
+}
+
+// Gaussian elimination with partial pivoting, None if the system is singular
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<(f64, f64)>) -> Option<Vec<(f64, f64)>> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = matrix[row][col] / matrix[col][col];
+            if factor == 0. {
+                continue;
+            }
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (target, source) in lower[0][col..].iter_mut().zip(upper[col][col..].iter()) {
+                *target -= factor * source;
+            }
+            rhs[row].0 -= factor * rhs[col].0;
+            rhs[row].1 -= factor * rhs[col].1;
+        }
+    }
+    let mut output = vec![(0., 0.); n];
+    for row in (0..n).rev() {
+        let (mut x, mut y) = rhs[row];
+        for k in row + 1..n {
+            x -= matrix[row][k] * output[k].0;
+            y -= matrix[row][k] * output[k].1;
+        }
+        output[row] = (x / matrix[row][row], y / matrix[row][row]);
+    }
+    return Some(output);
+}
+
+impl Spline {
+    // Checks the definition is consistent, a spline with only fit points is interpolated through them
+    pub fn new(
+        degree: usize,
+        knots: Vec<f32>,
+        control_points: Vec<(f32, f32)>,
+        weights: Vec<f32>,
+        fit_points: Vec<(f32, f32)>,
+        closed: bool,
+    ) -> Option<Spline> {
+        if control_points.is_empty() {
+            return Spline::interpolate(degree, fit_points, closed);
+        }
+
+        let n = control_points.len();
+        let weights = if weights.is_empty() {
+            vec![1.; n]
+        } else {
+            weights
+        };
+        if degree == 0
+            || n <= degree
+            || knots.len() != n + degree + 1
+            || weights.len() != n
+            || weights.iter().any(|w| *w <= 0.)
+            || knots.windows(2).any(|k| k[1] < k[0])
+            || knots[degree] >= knots[n]
+        {
+            return None;
+        }
+        return Some(Spline {
+            degree: degree,
+            knots: knots,
+            control_points: control_points,
+            weights: weights,
+            fit_points: fit_points,
+            closed: closed,
+        });
+    }
+
+    // Global interpolation with chord length parameters and averaged knots
+    fn interpolate(degree: usize, fit_points: Vec<(f32, f32)>, closed: bool) -> Option<Spline> {
+        if closed {
+            return Spline::interpolate_closed(degree, fit_points);
+        }
+        if fit_points.len() < 2 || degree == 0 {
+            return None;
+        }
+        let n = fit_points.len();
+        let degree = degree.min(n - 1);
+
+        let chords: Vec<f64> = fit_points
+            .windows(2)
+            .map(|p| ((p[1].0 - p[0].0) as f64).hypot((p[1].1 - p[0].1) as f64))
+            .collect();
+        let total: f64 = chords.iter().sum();
+        if total == 0. {
+            return None;
+        }
+        let mut params = vec![0.; n];
+        for k in 1..n {
+            params[k] = params[k - 1] + chords[k - 1] / total;
+        }
+        params[n - 1] = 1.;
+
+        let mut knots = vec![0.; degree + 1];
+        for j in 1..n - degree {
+            knots.push(params[j..j + degree].iter().sum::<f64>() / degree as f64);
+        }
+        knots.extend(vec![1.; degree + 1]);
+
+        let mut matrix = vec![vec![0.; n]; n];
+        for (k, t) in params.iter().enumerate() {
+            let span = (degree..n)
+                .rev()
+                .find(|s| *t >= knots[*s])
+                .unwrap_or(degree)
+                .min(n - 1);
+            for (j, value) in basis_functions(&knots, degree, span, *t).iter().enumerate() {
+                matrix[k][span - degree + j] = *value;
+            }
+        }
+        let rhs = fit_points
+            .iter()
+            .map(|p| (p.0 as f64, p.1 as f64))
+            .collect();
+        let control_points = solve(matrix, rhs)?
+            .iter()
+            .map(|p| (p.0 as f32, p.1 as f32))
+            .collect();
+
+        return Some(Spline {
+            degree: degree,
+            knots: knots.iter().map(|k| *k as f32).collect(),
+            control_points: control_points,
+            weights: vec![1.; n],
+            fit_points: fit_points,
+            closed: closed,
+        });
+    }
+
+    // Periodic interpolation, the curve runs through every fit point and back to the first
+    // without a corner, the first control points are repeated at the end to close it
+    fn interpolate_closed(degree: usize, fit_points: Vec<(f32, f32)>) -> Option<Spline> {
+        let mut points: Vec<(f64, f64)> = fit_points
+            .iter()
+            .map(|p| (p.0 as f64, p.1 as f64))
+            .collect();
+        // A repeated first point only marks the loop as closed
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        let n = points.len();
+        if n < 3 || degree == 0 {
+            return None;
+        }
+        let degree = degree.min(n - 1);
+
+        // Chord length parameters around the loop, repeating every whole unit
+        let chords: Vec<f64> = (0..n)
+            .map(|k| {
+                let (a, b) = (points[k], points[(k + 1) % n]);
+                (b.0 - a.0).hypot(b.1 - a.1)
+            })
+            .collect();
+        let total: f64 = chords.iter().sum();
+        if total == 0. {
+            return None;
+        }
+        let mut params = vec![0.; n];
+        for k in 1..n {
+            params[k] = params[k - 1] + chords[k - 1] / total;
+        }
+        let param =
+            |i: isize| params[i.rem_euclid(n as isize) as usize] + i.div_euclid(n as isize) as f64;
+
+        // Knots averaged from the parameters repeat along with them
+        let knots: Vec<f64> = (0..n + 2 * degree + 1)
+            .map(|j| {
+                let first = j as isize - degree as isize;
+                (first..first + degree as isize).map(param).sum::<f64>() / degree as f64
+            })
+            .collect();
+        let start = knots[degree];
+
+        // Control point n + i is control point i, so its basis functions add to column i
+        let mut matrix = vec![vec![0.; n]; n];
+        for (k, t) in params.iter().enumerate() {
+            let t = if *t < start { t + 1. } else { *t };
+            let span = (degree..n + degree)
+                .rev()
+                .find(|s| t >= knots[*s])
+                .unwrap_or(degree);
+            for (j, value) in basis_functions(&knots, degree, span, t).iter().enumerate() {
+                matrix[k][(span - degree + j) % n] += *value;
+            }
+        }
+        let mut control_points: Vec<(f32, f32)> = solve(matrix, points)?
+            .iter()
+            .map(|p| (p.0 as f32, p.1 as f32))
+            .collect();
+        control_points.extend_from_within(..degree);
+
+        return Some(Spline {
+            degree: degree,
+            knots: knots.iter().map(|k| *k as f32).collect(),
+            weights: vec![1.; control_points.len()],
+            control_points: control_points,
+            fit_points: fit_points,
+            closed: true,
+        });
+    }
+
+    pub fn is_rational(&self) -> bool {
+        return self.weights.iter().any(|w| *w != 1.);
+    }
+
+    // Parameter range the curve is defined on
+    pub fn get_domain(&self) -> (f32, f32) {
+        return (
+            self.knots[self.degree],
+            self.knots[self.control_points.len()],
+        );
+    }
+
+    // De Boor's algorithm in homogeneous coordinates so weights are handled exactly
+    pub fn evaluate(&self, t: f32) -> (f32, f32) {
+        let (start, end) = self.get_domain();
+        let t = t.clamp(start, end);
+        let p = self.degree;
+        let span = find_span(&self.knots, p, self.control_points.len(), t);
+
+        let mut points: Vec<(f32, f32, f32)> = (0..=p)
+            .map(|j| {
+                let i = span - p + j;
+                let w = self.weights[i];
+                (
+                    self.control_points[i].0 * w,
+                    self.control_points[i].1 * w,
+                    w,
+                )
+            })
+            .collect();
+        for r in 1..=p {
+            for j in (r..=p).rev() {
+                let i = span - p + j;
+                let denominator = self.knots[i + p + 1 - r] - self.knots[i];
+                let alpha = if denominator == 0. {
+                    0.
+                } else {
+                    (t - self.knots[i]) / denominator
+                };
+                points[j] = (
+                    (1. - alpha) * points[j - 1].0 + alpha * points[j].0,
+                    (1. - alpha) * points[j - 1].1 + alpha * points[j].1,
+                    (1. - alpha) * points[j - 1].2 + alpha * points[j].2,
+                );
+            }
+        }
+        let (x, y, w) = points[p];
+        return (x / w, y / w);
+    }
+
+    fn subdivide(
+        &self,
+        span: (f32, f32),
+        ends: ((f32, f32), (f32, f32)),
+        depth: u32,
+        tolerance: f32,
+        output: &mut Vec<(f32, f32)>,
+    ) {
+        let middle = (span.0 + span.1) / 2.;
+        let middle_point = self.evaluate(middle);
+        let flat =
+            geometry::closest_point_on_segment(&middle_point, &ends.0, &ends.1).1 <= tolerance;
+        if depth >= MAX_SUBDIVISIONS || (depth >= MIN_SUBDIVISIONS && flat) {
+            output.push(ends.1);
+            return;
+        }
+        self.subdivide(
+            (span.0, middle),
+            (ends.0, middle_point),
+            depth + 1,
+            tolerance,
+            output,
+        );
+        self.subdivide(
+            (middle, span.1),
+            (middle_point, ends.1),
+            depth + 1,
+            tolerance,
+            output,
+        );
+    }
+
+    // Knot spans are split until each chord is within the tolerance of the curve,
+    // closed splines do not repeat their first point
+    pub fn tessellate(&self, tolerance: f32) -> Array2<f32> {
+        let (start, end) = self.get_domain();
+        let mut points = vec![self.evaluate(start)];
+        let mut span_start = start;
+        for knot in self.knots.iter().filter(|k| **k > start && **k <= end) {
+            if *knot == span_start {
+                continue;
+            }
+            let ends = (*points.last().unwrap(), self.evaluate(*knot));
+            self.subdivide((span_start, *knot), ends, 0, tolerance, &mut points);
+            span_start = *knot;
+        }
+        if self.closed && points.len() > 2 {
+            points.pop();
+        }
+
+        let vertex_data: Vec<f32> = points.iter().flat_map(|p| [p.0, p.1]).collect();
+        return Array2::from_shape_vec((points.len(), 2), vertex_data).unwrap();
+    }
+
+    pub fn bounding_box(&self) -> ((f32, f32), (f32, f32)) {
+        let points = self.tessellate(BOUNDING_BOX_TOLERANCE);
+        let column = |c: usize| points.column(c).to_vec();
+        let min = |v: Vec<f32>| v.into_iter().fold(f32::INFINITY, f32::min);
+        let max = |v: Vec<f32>| v.into_iter().fold(f32::NEG_INFINITY, f32::max);
+        return (
+            (min(column(0)), max(column(0))),
+            (min(column(1)), max(column(1))),
+        );
+    }
+
+    // Splines are affine invariant, transforming the control points transforms the curve exactly
+    pub fn transform<F>(&self, transform_point: F) -> Spline
+    where
+        F: Fn(&(f32, f32)) -> (f32, f32),
+    {
+        return Spline {
+            degree: self.degree,
+            knots: self.knots.clone(),
+            control_points: self.control_points.iter().map(&transform_point).collect(),
+            weights: self.weights.clone(),
+            fit_points: self.fit_points.iter().map(&transform_point).collect(),
+            closed: self.closed,
+        };
+    }
+}
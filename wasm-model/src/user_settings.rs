@@ -16,7 +16,11 @@ pub struct ISettings {
     pub disabled_layers: Vec<i32>,
     pub point_threshold: f32,
     pub cross_size: f32,
+    // Largest distance between a drawn chord and the arc or circle it approximates
+    pub chord_tolerance: f32,
     pub view: String,
+    // Area of the current view that is drawn, everything is drawn when not set
+    pub viewport: Option<((f32, f32), (f32, f32))>,
     // selection rectangle
     pub highlight_offset: (f32, f32),
     pub highlight_scale: (f32, f32),
@@ -26,6 +30,13 @@ pub struct ISettings {
     pub highlight_rotation_angle: f32, // In radians
     pub highlight_nr_selected_entities: u32,
     pub highlight_width_height: (f32, f32),
+    // Layer seam allowance outlines are added on
+    pub seam_allowance_layer: i32,
+    // Draw every size of a graded pattern over the active one
+    pub grade_overlay: bool,
+    pub grade_overlay_color: (u8, u8, u8, u8),
+    // Temporary drawings over the pattern, such as a measurement
+    pub overlay_color: (u8, u8, u8, u8),
 }
 
 impl Default for ISettings {
@@ -37,7 +48,9 @@ impl Default for ISettings {
             disabled_layers: vec![],
             point_threshold: 4.,
             cross_size: 0.3,
+            chord_tolerance: 0.05,
             view: "Model".to_string(),
+            viewport: None,
             highlight_offset: (0., 0.),
             highlight_scale: (1., 1.),
             highlight_flip: (false, false),
@@ -46,6 +59,10 @@ impl Default for ISettings {
             highlight_rotation_angle: 0.,
             highlight_nr_selected_entities: 0,
             highlight_width_height: (0., 0.),
+            seam_allowance_layer: 11,
+            grade_overlay: false,
+            grade_overlay_color: (255, 152, 0, 255),
+            overlay_color: (233, 30, 99, 255),
         }
     }
 }
@@ -94,6 +111,7 @@ impl ISettings {
             highlight_rot_offset: &rot_center - &rot_center.dot(&highlight_rot_matrix),
             highlight_rot_matrix: highlight_rot_matrix,
             rot_center: rot_center,
+            chord_tolerance: self.chord_tolerance,
         };
     }
 }
@@ -1,5 +1,8 @@
 use ndarray::{array, Array2};
 
+// ((min_x, max_x), (min_y, max_y))
+pub type BoundingBox = ((f32, f32), (f32, f32));
+
 pub fn from_array(vertices: &Array2<f32>) -> ((f32, f32), (f32, f32)) {
     let mut min_x: f32 = f32::INFINITY;
     let mut min_y: f32 = f32::INFINITY;
@@ -31,6 +34,14 @@ pub fn contains_point(bbox: &((f32, f32), (f32, f32)), p: &(f32, f32), padding:
         && p.1 <= max_y + padding;
 }
 
+pub fn pad(bbox: &((f32, f32), (f32, f32)), padding: f32) -> ((f32, f32), (f32, f32)) {
+    let ((min_x, max_x), (min_y, max_y)) = bbox;
+    return (
+        (min_x - padding, max_x + padding),
+        (min_y - padding, max_y + padding),
+    );
+}
+
 pub fn offset_bbox(
     bbox: &((f32, f32), (f32, f32)),
     offset: &Array2<f32>,
@@ -48,16 +59,12 @@ pub fn scale_bbox(
     anchor: &Array2<f32>,
 ) -> ((f32, f32), (f32, f32)) {
     let ((min_x, max_x), (min_y, max_y)) = bbox;
-    return (
-        (
-            (min_x - anchor[(0, 0)]) * scale[(0, 0)] + anchor[(0, 0)],
-            (max_x - anchor[(0, 0)]) * scale[(0, 0)] + anchor[(0, 0)],
-        ),
-        (
-            (min_y - anchor[(0, 1)]) * scale[(0, 1)] + anchor[(0, 1)],
-            (max_y - anchor[(0, 1)]) * scale[(0, 1)] + anchor[(0, 1)],
-        ),
-    );
+    let x1 = (min_x - anchor[(0, 0)]) * scale[(0, 0)] + anchor[(0, 0)];
+    let x2 = (max_x - anchor[(0, 0)]) * scale[(0, 0)] + anchor[(0, 0)];
+    let y1 = (min_y - anchor[(0, 1)]) * scale[(0, 1)] + anchor[(0, 1)];
+    let y2 = (max_y - anchor[(0, 1)]) * scale[(0, 1)] + anchor[(0, 1)];
+    // A negative scale flips the box so the corners swap over
+    return ((x1.min(x2), x1.max(x2)), (y1.min(y2), y1.max(y2)));
 }
 
 pub fn rotate_bbox(
@@ -67,14 +74,10 @@ pub fn rotate_bbox(
 ) -> ((f32, f32), (f32, f32)) {
     let offset_center = center - center.dot(rot_matrix);
     let ((min_x, max_x), (min_y, max_y)) = bbox;
-    let bottom_right: Array2<f32> =
-        array![[min_x.clone(), min_y.clone()]].dot(rot_matrix) + &offset_center;
-    let bottom_left: Array2<f32> =
-        array![[max_x.clone(), min_y.clone()]].dot(rot_matrix) + &offset_center;
-    let top_right: Array2<f32> =
-        array![[min_x.clone(), max_y.clone()]].dot(rot_matrix) + &offset_center;
-    let top_left: Array2<f32> =
-        array![[max_x.clone(), max_y.clone()]].dot(rot_matrix) + &offset_center;
+    let bottom_right: Array2<f32> = array![[*min_x, *min_y]].dot(rot_matrix) + &offset_center;
+    let bottom_left: Array2<f32> = array![[*max_x, *min_y]].dot(rot_matrix) + &offset_center;
+    let top_right: Array2<f32> = array![[*min_x, *max_y]].dot(rot_matrix) + &offset_center;
+    let top_left: Array2<f32> = array![[*max_x, *max_y]].dot(rot_matrix) + &offset_center;
     let xs: [f32; 4] = [
         bottom_right[(0, 0)],
         bottom_left[(0, 0)],
@@ -107,8 +110,8 @@ pub fn union(
     let ((min_x1, max_x1), (min_y1, max_y1)) = bbox1;
     let ((min_x2, max_x2), (min_y2, max_y2)) = bbox2;
     return (
-        (min_x1.min(*min_x2).clone(), max_x1.max(*max_x2).clone()),
-        (min_y1.min(*min_y2).clone(), max_y1.max(*max_y2).clone()),
+        (min_x1.min(*min_x2), max_x1.max(*max_x2)),
+        (min_y1.min(*min_y2), max_y1.max(*max_y2)),
     );
 }
 
@@ -127,3 +130,10 @@ pub fn construct_from_vectors(v1: Vec<f32>, v2: Vec<f32>) -> Option<((f32, f32),
     let max_y = v1[1].max(v2[1]);
     return Option::Some(((min_x, max_x), (min_y, max_y)));
 }
+
+// True if the inner box lies entirely inside the outer box
+pub fn contains(outer: &((f32, f32), (f32, f32)), inner: &((f32, f32), (f32, f32))) -> bool {
+    let ((min_x1, max_x1), (min_y1, max_y1)) = outer;
+    let ((min_x2, max_x2), (min_y2, max_y2)) = inner;
+    return min_x1 <= min_x2 && max_x2 <= max_x1 && min_y1 <= min_y2 && max_y2 <= max_y1;
+}
@@ -13,6 +13,11 @@ pub fn hex_to_rgba(hex: &str) -> Result<(u8, u8, u8, u8), String> {
     Ok((r, g, b, a))
 }
 
+pub fn rgba_to_hex(rgba: &(u8, u8, u8, u8)) -> String {
+    let (r, g, b, _) = rgba;
+    return format!("#{:02x}{:02x}{:02x}", r, g, b);
+}
+
 pub fn rbga_to_float(rgba: &(u8, u8, u8, u8)) -> f32 {
     let (r, g, b, a) = rgba;
     let packed_integer: u32 =
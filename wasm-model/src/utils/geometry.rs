@@ -0,0 +1,187 @@
+// Distance and intersection queries between points, line segments and boxes
+use crate::utils::bounding_box;
+
+pub fn distance(p1: &(f32, f32), p2: &(f32, f32)) -> f32 {
+    return ((p1.0 - p2.0).powi(2) + (p1.1 - p2.1).powi(2)).sqrt();
+}
+
+// Closest point on segment a -> b to p, along with its distance to p
+pub fn closest_point_on_segment(
+    p: &(f32, f32),
+    a: &(f32, f32),
+    b: &(f32, f32),
+) -> ((f32, f32), f32) {
+    let (d_x, d_y) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = d_x * d_x + d_y * d_y;
+
+    if length_sq == 0. {
+        return (*a, distance(p, a));
+    }
+
+    let t = (((p.0 - a.0) * d_x + (p.1 - a.1) * d_y) / length_sq).clamp(0., 1.);
+    let closest = (a.0 + t * d_x, a.1 + t * d_y);
+    return (closest, distance(p, &closest));
+}
+
+// Even-odd test of a point against a closed polygon
+pub fn point_in_polygon(p: &(f32, f32), polygon: &[(f32, f32)]) -> bool {
+    let mut inside = false;
+    let n = polygon.len();
+    if n < 3 {
+        return false;
+    }
+
+    let mut j = n - 1;
+    for i in 0..n {
+        let (x_i, y_i) = polygon[i];
+        let (x_j, y_j) = polygon[j];
+        if (y_i > p.1) != (y_j > p.1) && p.0 < (x_j - x_i) * (p.1 - y_i) / (y_j - y_i) + x_i {
+            inside = !inside;
+        }
+        j = i;
+    }
+    return inside;
+}
+
+fn orientation(a: &(f32, f32), b: &(f32, f32), c: &(f32, f32)) -> f32 {
+    return (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+}
+
+fn on_segment(a: &(f32, f32), b: &(f32, f32), p: &(f32, f32)) -> bool {
+    return p.0 >= a.0.min(b.0)
+        && p.0 <= a.0.max(b.0)
+        && p.1 >= a.1.min(b.1)
+        && p.1 <= a.1.max(b.1);
+}
+
+pub fn segments_intersect(
+    a1: &(f32, f32),
+    a2: &(f32, f32),
+    b1: &(f32, f32),
+    b2: &(f32, f32),
+) -> bool {
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+
+    if ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.))
+        && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.))
+    {
+        return true;
+    }
+    // Collinear touching cases
+    return (d1 == 0. && on_segment(b1, b2, a1))
+        || (d2 == 0. && on_segment(b1, b2, a2))
+        || (d3 == 0. && on_segment(a1, a2, b1))
+        || (d4 == 0. && on_segment(a1, a2, b2));
+}
+
+pub fn segment_intersects_bbox(
+    a: &(f32, f32),
+    b: &(f32, f32),
+    bbox: &((f32, f32), (f32, f32)),
+) -> bool {
+    if bounding_box::contains_point(bbox, a, 0.) || bounding_box::contains_point(bbox, b, 0.) {
+        return true;
+    }
+    let ((min_x, max_x), (min_y, max_y)) = *bbox;
+    let corners = [
+        (min_x, min_y),
+        (max_x, min_y),
+        (max_x, max_y),
+        (min_x, max_y),
+    ];
+    for i in 0..4 {
+        if segments_intersect(a, b, &corners[i], &corners[(i + 1) % 4]) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Area enclosed by a polygon, positive when its vertices run counter clockwise
+pub fn signed_area(polygon: &[(f32, f32)]) -> f32 {
+    let n = polygon.len();
+    let mut area = 0.;
+    for i in 0..n {
+        let (x_i, y_i) = polygon[i];
+        let (x_j, y_j) = polygon[(i + 1) % n];
+        area += x_i * y_j - x_j * y_i;
+    }
+    return area / 2.;
+}
+
+// Centre of the area of a polygon, None when it encloses nothing. Sums are taken about the
+// first vertex to keep the precision of polygons far from the origin
+pub fn polygon_centroid(polygon: &[(f32, f32)]) -> Option<(f32, f32)> {
+    let n = polygon.len();
+    if n < 3 {
+        return None;
+    }
+    let (x_0, y_0) = (polygon[0].0 as f64, polygon[0].1 as f64);
+    let (mut area, mut c_x, mut c_y) = (0f64, 0f64, 0f64);
+    for i in 0..n {
+        let (x_i, y_i) = (polygon[i].0 as f64 - x_0, polygon[i].1 as f64 - y_0);
+        let (x_j, y_j) = (
+            polygon[(i + 1) % n].0 as f64 - x_0,
+            polygon[(i + 1) % n].1 as f64 - y_0,
+        );
+        let cross = x_i * y_j - x_j * y_i;
+        area += cross;
+        c_x += (x_i + x_j) * cross;
+        c_y += (y_i + y_j) * cross;
+    }
+    if area == 0. {
+        return None;
+    }
+    return Some((
+        (x_0 + c_x / (3. * area)) as f32,
+        (y_0 + c_y / (3. * area)) as f32,
+    ));
+}
+
+// Crossing point of the infinite lines through a1 -> a2 and b1 -> b2, with the position along
+// each as a fraction of its length
+fn line_parameters(
+    a1: &(f32, f32),
+    a2: &(f32, f32),
+    b1: &(f32, f32),
+    b2: &(f32, f32),
+) -> Option<((f32, f32), f32, f32)> {
+    let (d_ax, d_ay) = (a2.0 - a1.0, a2.1 - a1.1);
+    let (d_bx, d_by) = (b2.0 - b1.0, b2.1 - b1.1);
+    let denominator = d_ax * d_by - d_ay * d_bx;
+    let scale = (d_ax * d_ax + d_ay * d_ay).sqrt() * (d_bx * d_bx + d_by * d_by).sqrt();
+    if scale == 0. || denominator.abs() <= scale * 1e-6 {
+        return None;
+    }
+    let (d_x, d_y) = (b1.0 - a1.0, b1.1 - a1.1);
+    let t = (d_x * d_by - d_y * d_bx) / denominator;
+    let u = (d_x * d_ay - d_y * d_ax) / denominator;
+    return Some(((a1.0 + t * d_ax, a1.1 + t * d_ay), t, u));
+}
+
+pub fn line_intersection(
+    a1: &(f32, f32),
+    a2: &(f32, f32),
+    b1: &(f32, f32),
+    b2: &(f32, f32),
+) -> Option<(f32, f32)> {
+    return line_parameters(a1, a2, b1, b2).map(|(point, _, _)| point);
+}
+
+// Point where two segments cross, touching at an end or running parallel does not count
+pub fn segment_crossing(
+    a1: &(f32, f32),
+    a2: &(f32, f32),
+    b1: &(f32, f32),
+    b2: &(f32, f32),
+) -> Option<(f32, f32)> {
+    const END_TOLERANCE: f32 = 1e-6;
+    let inside = |t: f32| t > END_TOLERANCE && t < 1. - END_TOLERANCE;
+    return match line_parameters(a1, a2, b1, b2) {
+        Some((point, t, u)) if inside(t) && inside(u) => Some(point),
+        _ => None,
+    };
+}
@@ -0,0 +1,70 @@
+// Diagnostics from the model, written to the browser console in wasm builds and to stderr with
+// the native feature, a logger set by the caller replaces either
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+pub type Logger = Box<dyn Fn(Level, &str) + Send + Sync>;
+
+static LOGGER: RwLock<Option<Logger>> = RwLock::new(None);
+
+pub fn set_logger(logger: Logger) {
+    if let Ok(mut current) = LOGGER.write() {
+        *current = Some(logger);
+    }
+}
+
+// Go back to the default output of the build
+pub fn clear_logger() {
+    if let Ok(mut current) = LOGGER.write() {
+        *current = None;
+    }
+}
+
+pub fn log(level: Level, message: &str) {
+    if let Ok(current) = LOGGER.read() {
+        if let Some(logger) = current.as_ref() {
+            logger(level, message);
+            return;
+        }
+    }
+    default_log(level, message);
+}
+
+pub fn info(message: &str) {
+    log(Level::Info, message);
+}
+
+pub fn warning(message: &str) {
+    log(Level::Warning, message);
+}
+
+pub fn error(message: &str) {
+    log(Level::Error, message);
+}
+
+// The console is only there when running as wasm, calling it anywhere else panics
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn default_log(level: Level, message: &str) {
+    use wasm_bindgen::JsValue;
+    use web_sys::console;
+    let message = JsValue::from_str(message);
+    match level {
+        Level::Info => console::log_1(&message),
+        Level::Warning => console::warn_1(&message),
+        Level::Error => console::error_1(&message),
+    }
+}
+
+#[cfg(all(feature = "native", not(all(feature = "wasm", target_arch = "wasm32"))))]
+fn default_log(level: Level, message: &str) {
+    eprintln!("[{:?}] {}", level, message);
+}
+
+#[cfg(not(any(feature = "native", all(feature = "wasm", target_arch = "wasm32"))))]
+fn default_log(_level: Level, _message: &str) {}
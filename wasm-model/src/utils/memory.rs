@@ -2,6 +2,12 @@ pub struct VertexBuffer {
     pub buffer: Vec<f32>,
 }
 
+impl Default for VertexBuffer {
+    fn default() -> Self {
+        return VertexBuffer::new();
+    }
+}
+
 impl VertexBuffer {
     pub fn new() -> VertexBuffer {
         VertexBuffer { buffer: vec![] }
@@ -20,6 +26,12 @@ pub struct IndexBuffer {
     pub buffer: Vec<u32>,
 }
 
+impl Default for IndexBuffer {
+    fn default() -> Self {
+        return IndexBuffer::new();
+    }
+}
+
 impl IndexBuffer {
     pub fn new() -> IndexBuffer {
         IndexBuffer { buffer: vec![] }
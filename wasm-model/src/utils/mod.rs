@@ -1,4 +1,8 @@
 pub mod bounding_box;
 pub mod color;
+pub mod geometry;
+pub mod log;
 pub mod memory;
 pub mod parse;
+pub mod spatial_index;
+pub mod transform;
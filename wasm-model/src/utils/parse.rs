@@ -1,5 +1,5 @@
 // Return block key if view is named to refer to block
-pub fn view_as_block_key(view: &String) -> Option<String> {
+pub fn view_as_block_key(view: &str) -> Option<String> {
     if view.split("=>").next() == Some("Block") {
         if let Some(ind) = view.find("=>") {
             return Some(view[(ind + "=>".len())..].to_string());
@@ -7,3 +7,19 @@ pub fn view_as_block_key(view: &String) -> Option<String> {
     }
     return Option::None;
 }
+
+// Entity and insert indexes are 32 character strings, shown with the dashes of a uuid
+pub fn index_as_uuid(index: &[u8; 32]) -> String {
+    let index = String::from_utf8_lossy(index);
+    if index.len() != 32 {
+        return index.to_string();
+    }
+    return format!(
+        "{}-{}-{}-{}-{}",
+        &index[0..8],
+        &index[8..12],
+        &index[12..16],
+        &index[16..20],
+        &index[20..32]
+    );
+}
@@ -0,0 +1,90 @@
+// R-tree of bounding boxes keyed by position in the list they were built from
+use rstar::{RTree, RTreeObject, AABB};
+
+#[derive(Debug, Clone, PartialEq)]
+struct IndexedBox {
+    key: usize,
+    bbox: ((f32, f32), (f32, f32)),
+}
+
+impl RTreeObject for IndexedBox {
+    type Envelope = AABB<[f32; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        let ((min_x, max_x), (min_y, max_y)) = self.bbox;
+        return AABB::from_corners([min_x, min_y], [max_x, max_y]);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    tree: RTree<IndexedBox>,
+    // Box currently stored for each key, needed to find the entry again when it moves
+    boxes: Vec<((f32, f32), (f32, f32))>,
+}
+
+fn envelope_of(bbox: &((f32, f32), (f32, f32))) -> AABB<[f32; 2]> {
+    let ((min_x, max_x), (min_y, max_y)) = *bbox;
+    return AABB::from_corners([min_x, min_y], [max_x, max_y]);
+}
+
+impl SpatialIndex {
+    pub fn new(boxes: Vec<((f32, f32), (f32, f32))>) -> SpatialIndex {
+        let entries: Vec<IndexedBox> = boxes
+            .iter()
+            .enumerate()
+            .map(|(key, bbox)| IndexedBox {
+                key: key,
+                bbox: *bbox,
+            })
+            .collect();
+        return SpatialIndex {
+            tree: RTree::bulk_load(entries),
+            boxes: boxes,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.boxes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.boxes.is_empty();
+    }
+
+    // Move an existing entry to a new box
+    pub fn update(&mut self, key: usize, bbox: ((f32, f32), (f32, f32))) {
+        if key >= self.boxes.len() || self.boxes[key] == bbox {
+            return;
+        }
+        self.tree.remove(&IndexedBox {
+            key: key,
+            bbox: self.boxes[key],
+        });
+        self.tree.insert(IndexedBox {
+            key: key,
+            bbox: bbox,
+        });
+        self.boxes[key] = bbox;
+    }
+
+    // Keys of boxes that intersect the query box, in ascending order
+    pub fn query_bbox(&self, bbox: &((f32, f32), (f32, f32))) -> Vec<usize> {
+        let mut output: Vec<usize> = self
+            .tree
+            .locate_in_envelope_intersecting(&envelope_of(bbox))
+            .map(|e| e.key)
+            .collect();
+        output.sort_unstable();
+        return output;
+    }
+
+    // Keys of boxes within padding of a point, in ascending order
+    pub fn query_point(&self, point: &(f32, f32), padding: f32) -> Vec<usize> {
+        let bbox = (
+            (point.0 - padding, point.0 + padding),
+            (point.1 - padding, point.1 + padding),
+        );
+        return self.query_bbox(&bbox);
+    }
+}
@@ -0,0 +1,103 @@
+// Affine maps p' = p . matrix + translation on row vectors, used to place blocks through inserts
+use ndarray::{array, Array2};
+
+use crate::utils::bounding_box;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub matrix: Array2<f32>,
+    pub translation: Array2<f32>,
+}
+
+impl Transform {
+    pub fn new(matrix: Array2<f32>, translation: Array2<f32>) -> Transform {
+        return Transform {
+            matrix: matrix,
+            translation: translation,
+        };
+    }
+
+    pub fn identity() -> Transform {
+        return Transform::new(Array2::eye(2), array![[0., 0.]]);
+    }
+
+    pub fn from_offset(offset: &Array2<f32>) -> Transform {
+        return Transform::new(Array2::eye(2), offset.clone());
+    }
+
+    // Map that applies self first and then outer
+    pub fn then(&self, outer: &Transform) -> Transform {
+        return Transform::new(
+            self.matrix.dot(&outer.matrix),
+            self.translation.dot(&outer.matrix) + &outer.translation,
+        );
+    }
+
+    pub fn get_determinant(&self) -> f32 {
+        let m = &self.matrix;
+        return m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)];
+    }
+
+    // None for maps that collapse the plane onto a line
+    pub fn inverse(&self) -> Option<Transform> {
+        let determinant = self.get_determinant();
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let m = &self.matrix;
+        let matrix = array![[m[(1, 1)], -m[(0, 1)]], [-m[(1, 0)], m[(0, 0)]]] / determinant;
+        let translation = -self.translation.dot(&matrix);
+        return Some(Transform::new(matrix, translation));
+    }
+
+    // Plain moves keep the fast paths that only add an offset
+    pub fn is_translation(&self) -> bool {
+        return self.matrix == Array2::<f32>::eye(2);
+    }
+
+    pub fn get_offset(&self) -> Array2<f32> {
+        return self.translation.clone();
+    }
+
+    pub fn apply_point(&self, point: &(f32, f32)) -> (f32, f32) {
+        let (m, t) = (&self.matrix, &self.translation);
+        return (
+            point.0 * m[(0, 0)] + point.1 * m[(1, 0)] + t[(0, 0)],
+            point.0 * m[(0, 1)] + point.1 * m[(1, 1)] + t[(0, 1)],
+        );
+    }
+
+    // Direction vectors only see the matrix
+    pub fn apply_vector(&self, vector: &Array2<f32>) -> Array2<f32> {
+        return vector.dot(&self.matrix);
+    }
+
+    pub fn apply_array(&self, vertices: &Array2<f32>) -> Array2<f32> {
+        return &self.translation + &vertices.dot(&self.matrix);
+    }
+
+    // Box around the four transformed corners of a box
+    pub fn apply_bbox(&self, bbox: &((f32, f32), (f32, f32))) -> ((f32, f32), (f32, f32)) {
+        if self.is_translation() {
+            return bounding_box::offset_bbox(bbox, &self.translation);
+        }
+        let ((min_x, max_x), (min_y, max_y)) = *bbox;
+        let corners = array![
+            [min_x, min_y],
+            [max_x, min_y],
+            [min_x, max_y],
+            [max_x, max_y]
+        ];
+        return bounding_box::from_array(&self.apply_array(&corners));
+    }
+
+    // Least a length can be stretched by, the smallest singular value of the matrix
+    pub fn get_min_scale(&self) -> f32 {
+        let m = &self.matrix;
+        let (a, b, c, d) = (m[(0, 0)], m[(0, 1)], m[(1, 0)], m[(1, 1)]);
+        let sum = a * a + b * b + c * c + d * d;
+        let difference =
+            ((a * a + b * b - c * c - d * d).powi(2) + 4. * (a * c + b * d).powi(2)).sqrt();
+        return ((sum - difference) / 2.).max(0.).sqrt();
+    }
+}
@@ -0,0 +1,425 @@
+// Writer for the pattern model into an ASCII DXF (R2000) document
+use std::collections::BTreeMap;
+
+use crate::block::Block;
+use crate::curve::Curve;
+use crate::entity::{Entity, EntityTypes};
+use crate::insert::Insert;
+use crate::parse_dxf::MODEL_SPACE_BLOCK;
+use crate::pattern::Pattern;
+
+// Application name used for extended data that the editor needs to read a file back
+pub const XDATA_APP_NAME: &str = "CAD_PATTERN_EDITOR";
+
+const DXF_VERSION: &str = "AC1015";
+
+struct DxfWriter {
+    lines: Vec<String>,
+    next_handle: u32,
+    // Names to write for layers that were read with one
+    layer_names: BTreeMap<i32, String>,
+}
+
+impl DxfWriter {
+    fn new(layer_names: BTreeMap<i32, String>) -> DxfWriter {
+        return DxfWriter {
+            lines: vec![],
+            // Low handles are reserved for the fixed tables and dictionaries below
+            next_handle: 0x100,
+            layer_names: layer_names,
+        };
+    }
+
+    fn layer_name(&self, layer: i32) -> String {
+        return match self.layer_names.get(&layer) {
+            Some(name) => name.clone(),
+            None => layer.to_string(),
+        };
+    }
+
+    fn group<T: std::fmt::Display>(&mut self, code: i32, value: T) {
+        self.lines.push(format!("{:>3}", code));
+        self.lines.push(value.to_string());
+    }
+
+    fn point(&mut self, x_code: i32, x: f32, y: f32) {
+        self.group(x_code, x);
+        self.group(x_code + 10, y);
+        self.group(x_code + 20, 0.);
+    }
+
+    fn handle(&mut self) -> String {
+        let handle = format!("{:X}", self.next_handle);
+        self.next_handle += 1;
+        self.group(5, &handle);
+        return handle;
+    }
+
+    fn begin_section(&mut self, name: &str) {
+        self.group(0, "SECTION");
+        self.group(2, name);
+    }
+
+    fn end_section(&mut self) {
+        self.group(0, "ENDSEC");
+    }
+
+    fn begin_table(&mut self, name: &str, handle: &str, count: usize) {
+        self.group(0, "TABLE");
+        self.group(2, name);
+        self.group(5, handle);
+        self.group(330, 0);
+        self.group(100, "AcDbSymbolTable");
+        self.group(70, count);
+    }
+
+    fn end_table(&mut self) {
+        self.group(0, "ENDTAB");
+    }
+
+    fn table_record(&mut self, record_type: &str, owner: &str, subclass: &str) {
+        self.group(0, record_type);
+        self.handle();
+        self.group(330, owner);
+        self.group(100, "AcDbSymbolTableRecord");
+        self.group(100, subclass);
+    }
+
+    fn entity_start(&mut self, entity_type: &str, owner: &str, layer: i32, subclass: &str) {
+        self.group(0, entity_type);
+        self.handle();
+        self.group(330, owner);
+        self.group(100, "AcDbEntity");
+        self.group(8, self.layer_name(layer));
+        self.group(100, subclass);
+    }
+
+    fn xdata_string(&mut self, value: &str) {
+        self.group(1001, XDATA_APP_NAME);
+        self.group(1000, value);
+    }
+
+    fn finish(mut self) -> String {
+        self.group(0, "EOF");
+        let mut output = self.lines.join("\n");
+        output.push('\n');
+        return output;
+    }
+}
+
+fn write_entity(writer: &mut DxfWriter, entity: &Entity, offset: (f32, f32), owner: &str) {
+    let (dx, dy) = offset;
+    let rows = entity.vertices.shape()[0];
+    if rows == 0 {
+        return;
+    }
+    let x = |i: usize| entity.vertices[(i, 0)] + dx;
+    let y = |i: usize| entity.vertices[(i, 1)] + dy;
+
+    match (&entity.curve, &entity.entity_type) {
+        (Some(Curve::Circle { center, radius }), _) => {
+            writer.entity_start("CIRCLE", owner, entity.layer, "AcDbCircle");
+            writer.point(10, center.0 + dx, center.1 + dy);
+            writer.group(40, radius);
+        }
+        (
+            Some(Curve::Arc {
+                center,
+                radius,
+                start_angle,
+                end_angle,
+            }),
+            _,
+        ) => {
+            writer.entity_start("ARC", owner, entity.layer, "AcDbCircle");
+            writer.point(10, center.0 + dx, center.1 + dy);
+            writer.group(40, radius);
+            writer.group(100, "AcDbArc");
+            writer.group(50, start_angle.to_degrees());
+            writer.group(51, end_angle.to_degrees());
+        }
+        (
+            Some(
+                curve @ Curve::Ellipse {
+                    center,
+                    major_axis,
+                    ratio,
+                    ..
+                },
+            ),
+            _,
+        ) => {
+            let start_param = curve.get_start_angle();
+            writer.entity_start("ELLIPSE", owner, entity.layer, "AcDbEllipse");
+            writer.point(10, center.0 + dx, center.1 + dy);
+            writer.point(11, major_axis.0, major_axis.1);
+            writer.group(40, ratio);
+            writer.group(41, start_param);
+            writer.group(42, start_param + curve.get_sweep());
+        }
+        (Some(Curve::Spline(spline)), _) => {
+            // Closed, and rational when weighted, planar is always set
+            let flags =
+                8 + if spline.closed { 1 } else { 0 } + if spline.is_rational() { 4 } else { 0 };
+            writer.entity_start("SPLINE", owner, entity.layer, "AcDbSpline");
+            writer.group(70, flags);
+            writer.group(71, spline.degree);
+            writer.group(72, spline.knots.len());
+            writer.group(73, spline.control_points.len());
+            writer.group(74, spline.fit_points.len());
+            for knot in spline.knots.iter() {
+                writer.group(40, knot);
+            }
+            if spline.is_rational() {
+                for weight in spline.weights.iter() {
+                    writer.group(41, weight);
+                }
+            }
+            for (x, y) in spline.control_points.iter() {
+                writer.point(10, x + dx, y + dy);
+            }
+            for (x, y) in spline.fit_points.iter() {
+                writer.point(11, x + dx, y + dy);
+            }
+        }
+        (None, EntityTypes::POINT) => {
+            writer.entity_start("POINT", owner, entity.layer, "AcDbPoint");
+            writer.point(10, x(0), y(0));
+        }
+        (None, EntityTypes::TEXT) => {
+            writer.entity_start("TEXT", owner, entity.layer, "AcDbText");
+            writer.point(10, x(0), y(0));
+            writer.group(40, entity.text_height);
+            writer.group(1, &entity.text);
+            writer.group(100, "AcDbText");
+        }
+        (None, EntityTypes::LINE | EntityTypes::LWLINE) if rows == 2 && !entity.shape => {
+            writer.entity_start("LINE", owner, entity.layer, "AcDbLine");
+            writer.point(10, x(0), y(0));
+            writer.point(11, x(1), y(1));
+        }
+        (None, EntityTypes::INSERT) => return,
+        _ => {
+            writer.entity_start("LWPOLYLINE", owner, entity.layer, "AcDbPolyline");
+            writer.group(90, rows);
+            writer.group(70, if entity.shape { 1 } else { 0 });
+            for i in 0..rows {
+                writer.group(10, x(i));
+                writer.group(20, y(i));
+                if entity.get_bulge(i) != 0. {
+                    writer.group(42, entity.get_bulge(i));
+                }
+            }
+        }
+    }
+    writer.xdata_string(&entity.entity_index_as_uuid());
+}
+
+fn write_header(writer: &mut DxfWriter) {
+    writer.begin_section("HEADER");
+    writer.group(9, "$ACADVER");
+    writer.group(1, DXF_VERSION);
+    writer.group(9, "$INSUNITS");
+    writer.group(70, 0);
+    writer.group(9, "$HANDSEED");
+    // Upper bound on handles used by this writer, patterns never get near this
+    writer.group(5, "FFFFFF");
+    writer.end_section();
+}
+
+fn write_tables(writer: &mut DxfWriter, layers: &[i32], blocks: &[&Block]) -> Vec<String> {
+    writer.begin_section("TABLES");
+
+    writer.begin_table("LTYPE", "5", 1);
+    writer.table_record("LTYPE", "5", "AcDbLinetypeTableRecord");
+    writer.group(2, "CONTINUOUS");
+    writer.group(70, 0);
+    writer.group(3, "Solid line");
+    writer.group(72, 65);
+    writer.group(73, 0);
+    writer.group(40, 0.);
+    writer.end_table();
+
+    writer.begin_table("LAYER", "2", layers.len());
+    for layer in layers.iter() {
+        writer.table_record("LAYER", "2", "AcDbLayerTableRecord");
+        writer.group(2, writer.layer_name(*layer));
+        writer.group(70, 0);
+        writer.group(62, 7);
+        writer.group(6, "CONTINUOUS");
+    }
+    writer.end_table();
+
+    writer.begin_table("STYLE", "3", 1);
+    writer.table_record("STYLE", "3", "AcDbTextStyleTableRecord");
+    writer.group(2, "STANDARD");
+    writer.group(70, 0);
+    writer.group(40, 0.);
+    writer.group(41, 1.);
+    writer.group(3, "txt");
+    writer.end_table();
+
+    writer.begin_table("APPID", "9", 2);
+    for app in ["ACAD", XDATA_APP_NAME] {
+        writer.table_record("APPID", "9", "AcDbRegAppTableRecord");
+        writer.group(2, app);
+        writer.group(70, 0);
+    }
+    writer.end_table();
+
+    // Every block needs a record, including the model and paper space layouts
+    let mut record_handles: Vec<String> = vec![];
+    writer.begin_table("BLOCK_RECORD", "1", blocks.len() + 2);
+    let names = ["*Model_Space", "*Paper_Space"]
+        .into_iter()
+        .map(|n| n.to_string())
+        .chain(blocks.iter().map(|b| b.name.clone()));
+    for name in names {
+        writer.group(0, "BLOCK_RECORD");
+        record_handles.push(writer.handle());
+        writer.group(330, "1");
+        writer.group(100, "AcDbSymbolTableRecord");
+        writer.group(100, "AcDbBlockTableRecord");
+        writer.group(2, name);
+    }
+    writer.end_table();
+
+    writer.end_section();
+    return record_handles;
+}
+
+fn write_block(writer: &mut DxfWriter, block: &Block, name: &str, owner: &str) {
+    writer.entity_start("BLOCK", owner, block.get_layer(), "AcDbBlockBegin");
+    writer.group(2, name);
+    writer.group(70, 0);
+    writer.point(10, 0., 0.);
+    writer.group(3, name);
+    writer.group(1, "");
+
+    let (c_x, c_y) = block.get_centroid();
+    writer.group(1001, XDATA_APP_NAME);
+    writer.group(1010, c_x);
+    writer.group(1020, c_y);
+    writer.group(1030, 0.);
+
+    for entity in block.get_entities().iter() {
+        write_entity(writer, entity, (0., 0.), owner);
+    }
+    for insert in block.get_inserts().iter() {
+        write_insert(writer, insert, owner);
+    }
+
+    writer.entity_start("ENDBLK", owner, block.get_layer(), "AcDbBlockEnd");
+}
+
+fn write_empty_block(writer: &mut DxfWriter, name: &str, owner: &str) {
+    writer.entity_start("BLOCK", owner, 0, "AcDbBlockBegin");
+    writer.group(2, name);
+    writer.group(70, 0);
+    writer.point(10, 0., 0.);
+    writer.group(3, name);
+    writer.group(1, "");
+    writer.entity_start("ENDBLK", owner, 0, "AcDbBlockEnd");
+}
+
+fn write_insert(writer: &mut DxfWriter, insert: &Insert, owner: &str) {
+    writer.entity_start("INSERT", owner, insert.layer, "AcDbBlockReference");
+    writer.group(2, &insert.name);
+    writer.point(10, insert.position[(0, 0)], insert.position[(0, 1)]);
+    if insert.scale != (1., 1.) {
+        writer.group(41, insert.scale.0);
+        writer.group(42, insert.scale.1);
+    }
+    if insert.rotation != 0. {
+        writer.group(50, insert.rotation);
+    }
+    if insert.is_array() {
+        writer.group(70, insert.columns);
+        writer.group(71, insert.rows);
+        writer.group(44, insert.spacing.0);
+        writer.group(45, insert.spacing.1);
+    }
+    writer.xdata_string(&insert.insert_index_as_uuid());
+}
+
+fn write_objects(writer: &mut DxfWriter) {
+    writer.begin_section("OBJECTS");
+    writer.group(0, "DICTIONARY");
+    writer.group(5, "C");
+    writer.group(330, 0);
+    writer.group(100, "AcDbDictionary");
+    writer.group(281, 1);
+    writer.group(3, "ACAD_GROUP");
+    writer.group(350, "D");
+    writer.group(0, "DICTIONARY");
+    writer.group(5, "D");
+    writer.group(330, "C");
+    writer.group(100, "AcDbDictionary");
+    writer.group(281, 1);
+    writer.end_section();
+}
+
+pub fn write_dxf(pattern: &Pattern) -> String {
+    let mut writer = DxfWriter::new(pattern.get_layer_names().clone());
+
+    // Entities placed directly in model space were read into a block of their own
+    let model_space_block = pattern
+        .get_blocks()
+        .iter()
+        .find(|b| b.name == MODEL_SPACE_BLOCK);
+    let blocks: Vec<&Block> = pattern
+        .get_blocks()
+        .iter()
+        .filter(|b| b.name != MODEL_SPACE_BLOCK)
+        .collect();
+
+    let mut layers = pattern.get_all_layers();
+    let nested_inserts = pattern
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_inserts().iter());
+    for insert in pattern.get_inserts().iter().chain(nested_inserts) {
+        if !layers.contains(&insert.layer) {
+            layers.push(insert.layer);
+        }
+    }
+    layers.sort();
+
+    write_header(&mut writer);
+    let record_handles = write_tables(&mut writer, &layers, &blocks);
+
+    writer.begin_section("BLOCKS");
+    write_empty_block(&mut writer, "*Model_Space", &record_handles[0]);
+    write_empty_block(&mut writer, "*Paper_Space", &record_handles[1]);
+    for (block, owner) in blocks.iter().zip(record_handles[2..].iter()) {
+        write_block(&mut writer, block, &block.name, owner);
+    }
+    writer.end_section();
+
+    writer.begin_section("ENTITIES");
+    let model_space = record_handles[0].clone();
+    for insert in pattern.get_inserts().iter() {
+        if insert.name == MODEL_SPACE_BLOCK {
+            if let Some(block) = model_space_block {
+                for placement in insert.get_transforms() {
+                    let offset = (placement.translation[(0, 0)], placement.translation[(0, 1)]);
+                    for entity in block.get_entities().iter() {
+                        if placement.is_translation() {
+                            write_entity(&mut writer, entity, offset, &model_space);
+                        } else {
+                            let placed = entity.transformed(&placement);
+                            write_entity(&mut writer, &placed, (0., 0.), &model_space);
+                        }
+                    }
+                }
+            }
+        } else {
+            write_insert(&mut writer, insert, &model_space);
+        }
+    }
+    writer.end_section();
+
+    write_objects(&mut writer);
+
+    return writer.finish();
+}
@@ -0,0 +1,275 @@
+// Writer for the current view of the pattern as an SVG document
+use std::collections::HashMap;
+
+use crate::block::Block;
+use crate::curve::Curve;
+use crate::entity::{Entity, EntityTypes};
+use crate::pattern::Pattern;
+use crate::user_settings;
+use crate::utils::bounding_box;
+use crate::utils::color;
+use crate::utils::transform::Transform;
+
+// Lines keep the same on screen width however far the svg is zoomed
+const STROKE_STYLE: &str = "stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"";
+
+fn escape_xml(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+fn color_attributes(rgba: &(u8, u8, u8, u8), attribute: &str) -> String {
+    let (_, _, _, a) = rgba;
+    let mut output = format!("{}=\"{}\"", attribute, color::rgba_to_hex(rgba));
+    if *a != 255 {
+        output.push_str(&format!(" {}-opacity=\"{}\"", attribute, *a as f32 / 255.));
+    }
+    return output;
+}
+
+fn entity_color<'a>(
+    entity: &Entity,
+    settings: &'a user_settings::ISettings,
+    block_color: &'a (u8, u8, u8, u8),
+) -> &'a (u8, u8, u8, u8) {
+    if let Some(layer_color) = settings.layer_colors.get(&entity.layer) {
+        return layer_color;
+    }
+    return block_color;
+}
+
+// Opening of the element for a curve, attributes are added by the caller
+fn curve_element(curve: &Curve, chord_tolerance: f32) -> String {
+    match curve {
+        Curve::Circle { center, radius } => {
+            return format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\"",
+                center.0, center.1, radius
+            );
+        }
+        Curve::Arc { radius, .. } => {
+            // Going through the middle point keeps every part under half a turn, which also
+            // covers arcs that go all the way round
+            let start = curve.get_start_point();
+            let middle = curve.point_at_angle(curve.get_start_angle() + curve.get_sweep() / 2.);
+            let end = curve.get_end_point();
+            return format!(
+                "<path d=\"M {} {} A {} {} 0 0 1 {} {} A {} {} 0 0 1 {} {}\"",
+                start.0, start.1, radius, radius, middle.0, middle.1, radius, radius, end.0, end.1
+            );
+        }
+        Curve::Ellipse {
+            major_axis, ratio, ..
+        } => {
+            let radius_x = (major_axis.0 * major_axis.0 + major_axis.1 * major_axis.1).sqrt();
+            let radius_y = radius_x * ratio;
+            let rotation = major_axis.1.atan2(major_axis.0).to_degrees();
+            let start = curve.get_start_point();
+            let middle = curve.point_at_angle(curve.get_start_angle() + curve.get_sweep() / 2.);
+            let end = curve.get_end_point();
+            let mut path = format!("M {} {}", start.0, start.1);
+            for point in [middle, end] {
+                path.push_str(&format!(
+                    " A {} {} {} 0 1 {} {}",
+                    radius_x, radius_y, rotation, point.0, point.1
+                ));
+            }
+            return format!("<path d=\"{}\"", path);
+        }
+        Curve::Spline(spline) => {
+            let points = spline.tessellate(chord_tolerance);
+            let mut path = format!("M {} {}", points[(0, 0)], points[(0, 1)]);
+            for v in points.rows().into_iter().skip(1) {
+                path.push_str(&format!(" L {} {}", v[0], v[1]));
+            }
+            if spline.closed {
+                path.push_str(" Z");
+            }
+            return format!("<path d=\"{}\"", path);
+        }
+    }
+}
+
+// Path command drawing a polyline segment, bulged segments become svg arcs
+fn segment_command(entity: &Entity, start: usize, end: usize) -> String {
+    let (x, y) = (entity.vertices[(end, 0)], entity.vertices[(end, 1)]);
+    let bulge = entity.get_bulge(start);
+    if bulge == 0. {
+        return format!(" L {} {}", x, y);
+    }
+    let d_x = x - entity.vertices[(start, 0)];
+    let d_y = y - entity.vertices[(start, 1)];
+    let chord = (d_x * d_x + d_y * d_y).sqrt();
+    let radius = chord * (1. + bulge * bulge) / (4. * bulge.abs());
+    // Bulges above one cover more than half a circle
+    let large_arc = if bulge.abs() > 1. { 1 } else { 0 };
+    let sweep = if bulge > 0. { 1 } else { 0 };
+    return format!(
+        " A {} {} 0 {} {} {} {}",
+        radius, radius, large_arc, sweep, x, y
+    );
+}
+
+fn write_entity(
+    output: &mut Vec<String>,
+    entity: &Entity,
+    settings: &user_settings::ISettings,
+    block_color: &(u8, u8, u8, u8),
+) {
+    let rows = entity.vertices.shape()[0];
+    if rows == 0 {
+        return;
+    }
+    let rgba = entity_color(entity, settings, block_color);
+    let x = entity.vertices[(0, 0)];
+    let y = entity.vertices[(0, 1)];
+
+    if let Some(curve) = &entity.curve {
+        output.push(format!(
+            "{} {} {}/>",
+            curve_element(curve, settings.chord_tolerance),
+            color_attributes(rgba, "stroke"),
+            STROKE_STYLE
+        ));
+        return;
+    }
+
+    match entity.entity_type {
+        EntityTypes::TEXT => {
+            // Flip text back upright since the whole drawing is mirrored to put y upwards
+            output.push(format!(
+                "<text x=\"0\" y=\"0\" font-size=\"{}\" transform=\"translate({} {}) scale(1 -1)\" {}>{}</text>",
+                entity.text_height,
+                x,
+                y,
+                color_attributes(rgba, "fill"),
+                escape_xml(&entity.text)
+            ));
+        }
+        EntityTypes::POINT => {
+            let c = settings.cross_size;
+            output.push(format!(
+                "<path d=\"M {} {} L {} {} M {} {} L {} {}\" {} {}/>",
+                x - c,
+                y - c,
+                x + c,
+                y + c,
+                x + c,
+                y - c,
+                x - c,
+                y + c,
+                color_attributes(rgba, "stroke"),
+                STROKE_STYLE
+            ));
+        }
+        _ => {
+            let mut path = format!("M {} {}", x, y);
+            for i in 1..rows {
+                path.push_str(&segment_command(entity, i - 1, i));
+            }
+            if entity.shape {
+                if entity.get_bulge(rows - 1) != 0. {
+                    path.push_str(&segment_command(entity, rows - 1, 0));
+                }
+                path.push_str(" Z");
+            }
+            output.push(format!(
+                "<path d=\"{}\" {} {}/>",
+                path,
+                color_attributes(rgba, "stroke"),
+                STROKE_STYLE
+            ));
+        }
+    }
+}
+
+// Plain moves are written as a translation, rotated and scaled placements as a matrix
+fn transform_attribute(placement: &Transform) -> String {
+    let (m, t) = (&placement.matrix, &placement.translation);
+    if placement.is_translation() {
+        return format!("translate({} {})", t[(0, 0)], t[(0, 1)]);
+    }
+    return format!(
+        "matrix({} {} {} {} {} {})",
+        m[(0, 0)],
+        m[(0, 1)],
+        m[(1, 0)],
+        m[(1, 1)],
+        t[(0, 0)],
+        t[(0, 1)]
+    );
+}
+
+fn write_block(
+    output: &mut Vec<String>,
+    block: &Block,
+    id: &str,
+    placement: &Transform,
+    settings: &user_settings::ISettings,
+) {
+    let block_color = block.get_color(settings);
+    output.push(format!(
+        "<g id=\"{}\" transform=\"{}\">",
+        escape_xml(id),
+        transform_attribute(placement)
+    ));
+    for entity in block.get_entities().iter() {
+        if settings.disabled_layers.contains(&entity.layer) {
+            continue;
+        }
+        write_entity(output, entity, settings, block_color);
+    }
+    output.push("</g>".to_string());
+}
+
+pub fn write_svg(pattern: &Pattern, settings: &user_settings::ISettings) -> String {
+    // Each placement of a block in the view, a single block view is drawn at the origin
+    let drawn_blocks = pattern.get_placements(&settings.view);
+
+    let mut extent: Option<((f32, f32), (f32, f32))> = None;
+    for (block, placement) in drawn_blocks.iter() {
+        let block_bbox = placement.apply_bbox(block.get_bounding_box());
+        extent = Some(match extent {
+            Some(current) => bounding_box::union(&current, &block_bbox),
+            None => block_bbox,
+        });
+    }
+    let padding = settings.cross_size;
+    let ((min_x, max_x), (min_y, max_y)) = extent.unwrap_or(((0., 1.), (0., 1.)));
+    let width = max_x - min_x + 2. * padding;
+    let height = max_y - min_y + 2. * padding;
+
+    let mut output: Vec<String> = vec![];
+    output.push(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        min_x - padding,
+        -max_y - padding,
+        width,
+        height,
+        width,
+        height
+    ));
+    // Pattern coordinates have y pointing up, svg has y pointing down
+    output.push("<g transform=\"scale(1 -1)\" fill=\"none\">".to_string());
+    // Ids stay unique when a block is placed more than once
+    let mut copies: HashMap<&String, usize> = HashMap::new();
+    for (block, placement) in drawn_blocks.iter() {
+        let copy = copies.entry(&block.name).or_insert(0);
+        *copy += 1;
+        let id = if *copy == 1 {
+            block.name.clone()
+        } else {
+            format!("{}-{}", block.name, copy)
+        };
+        write_block(&mut output, block, &id, placement, settings);
+    }
+    output.push("</g>".to_string());
+    output.push("</svg>".to_string());
+
+    let mut svg = output.join("\n");
+    svg.push('\n');
+    return svg;
+}
@@ -0,0 +1,74 @@
+// Helpers shared by the integration tests, each test file only uses some of them
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, ParsePattern, Vertex,
+};
+use cad_pattern_editor::user_settings::ISettings;
+
+// Largest difference accepted between a computed value and the expected one
+pub const TOLERANCE: f32 = 1e-4;
+
+pub fn assert_close(a: f32, b: f32) {
+    assert_within(a, b, TOLERANCE);
+}
+
+pub fn assert_within(a: f32, b: f32, tolerance: f32) {
+    assert!((a - b).abs() < tolerance, "{} != {}", a, b);
+}
+
+pub fn assert_point_close(a: (f32, f32), b: (f32, f32)) {
+    assert!(
+        (a.0 - b.0).abs() < TOLERANCE && (a.1 - b.1).abs() < TOLERANCE,
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+// Insert of a block on layer 1 at a position
+pub fn insert(name: &str, x: f32, y: f32) -> ParseInsertEntity {
+    ParseInsertEntity {
+        entity_type: "INSERT".to_string(),
+        name: name.to_string(),
+        position: Vertex { x, y, bulge: None },
+        layer: "1".to_string(),
+        ..Default::default()
+    }
+}
+
+pub fn document(blocks: Vec<(&str, ParseBlock)>, inserts: Vec<ParseInsertEntity>) -> ParseDocument {
+    ParseDocument {
+        pattern_json: ParsePattern {
+            blocks: blocks
+                .into_iter()
+                .map(|(name, block)| (name.to_string(), block))
+                .collect::<BTreeMap<String, ParseBlock>>(),
+            entities: inserts,
+            ..Default::default()
+        },
+    }
+}
+
+// One block on layer 1 holding the entities, inserted once at the origin
+pub fn single_block_document(name: &str, entities: Vec<ParseEntity>) -> ParseDocument {
+    let block = ParseBlock {
+        entities,
+        centroid: Vertex {
+            x: 0.,
+            y: 0.,
+            bulge: None,
+        },
+        layer: "1".to_string(),
+        inserts: vec![],
+    };
+    document(vec![(name, block)], vec![insert(name, 0., 0.)])
+}
+
+pub fn to_editor(document: &ParseDocument) -> editor::Editor {
+    let json = serde_json::to_string(document).unwrap();
+    editor::Editor::new(json, serde_json::to_string(&ISettings::default()).unwrap())
+}
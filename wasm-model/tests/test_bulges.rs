@@ -0,0 +1,176 @@
+use std::f32::consts::PI;
+
+use cad_pattern_editor::curve::Curve;
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::Entity;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::parse_pattern::{ParseDocument, ParseEntity, Vertex};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::write_dxf;
+use cad_pattern_editor::write_svg;
+
+mod common;
+use common::assert_close;
+
+// Block S holding an open polyline from (0, 0) to (2, 0) that bulges below into a half circle,
+// followed by a straight segment up to (2, 3)
+fn bulge_document(bulge: Option<f32>) -> ParseDocument {
+    common::single_block_document(
+        "S",
+        vec![ParseEntity {
+            entity_type: "LWPOLYLINE".to_string(),
+            layer: "1".to_string(),
+            entity_index: format!("{:032x}", 1),
+            shape: Some(false),
+            vertices: Some(vec![
+                Vertex {
+                    x: 0.,
+                    y: 0.,
+                    bulge,
+                },
+                Vertex {
+                    x: 2.,
+                    y: 0.,
+                    bulge: None,
+                },
+                Vertex {
+                    x: 2.,
+                    y: 3.,
+                    bulge: None,
+                },
+            ]),
+            ..Default::default()
+        }],
+    )
+}
+
+fn bulge_handle() -> editor::Editor {
+    common::to_editor(&bulge_document(Some(1.)))
+}
+
+fn bulges(entity: &Entity) -> Vec<f32> {
+    (0..entity.vertices.shape()[0])
+        .map(|i| entity.get_bulge(i))
+        .collect()
+}
+
+#[test]
+fn test_bulge_arc_geometry() {
+    // A bulge of tan(pi / 8) is a quarter circle
+    let arc = Curve::from_bulge(&(1., 0.), &(0., 1.), (PI / 8.).tan()).unwrap();
+    let Curve::Arc { center, radius, .. } = arc.clone() else {
+        panic!("Expected an arc");
+    };
+    assert_close(center.0, 0.);
+    assert_close(center.1, 0.);
+    assert_close(radius, 1.);
+    assert_close(arc.get_sweep(), PI / 2.);
+
+    // Negative bulges curve the other way, with the same circle for a half circle
+    let arc = Curve::from_bulge(&(0., 0.), &(2., 0.), -1.).unwrap();
+    let ((_, _), (min_y, max_y)) = arc.bounding_box();
+    assert_close(min_y, 0.);
+    assert_close(max_y, 1.);
+    assert!(Curve::from_bulge(&(0., 0.), &(2., 0.), 0.).is_none());
+}
+
+#[test]
+fn test_bulges_widen_bounding_box_and_selection() {
+    let p = pattern::Pattern::from_document(&bulge_document(Some(1.)));
+    let entity = &p.get_blocks()[0].get_entities()[0];
+    let ((min_x, max_x), (min_y, max_y)) = entity.bounding_box;
+    assert_close(min_x, 0.);
+    assert_close(max_x, 2.);
+    assert_close(min_y, -1.);
+    assert_close(max_y, 3.);
+
+    // Bottom of the half circle is on the entity, the straight chord is not
+    let settings = ISettings {
+        point_threshold: 0.05,
+        ..Default::default()
+    };
+    let hit = p.find_closest_entity(&(1., -1.), &settings).unwrap();
+    assert_eq!(hit.closest.segment_index, 0);
+    assert!(p.find_closest_entity(&(1., 0.), &settings).is_none());
+}
+
+#[test]
+fn test_bulges_are_drawn_as_arcs() {
+    let mut h = bulge_handle();
+    h.update_draw_sequence();
+    let (vertex_buffer, _) = h.get_buffers();
+    assert!(vertex_buffer.len() / 3 > 3);
+    let lowest = vertex_buffer
+        .chunks(3)
+        .map(|v| v[1])
+        .fold(f32::INFINITY, f32::min);
+    // Drawn within the default chord tolerance of the bottom of the half circle
+    assert!((-1. ..=-0.95).contains(&lowest), "{}", lowest);
+}
+
+#[test]
+fn test_mirror_negates_bulges() {
+    let mut h = bulge_handle();
+    h.highlight_block("S".to_string(), true);
+    h.set_highlight_anchor(0., 0.);
+    h.set_highlight_flip(false, true);
+    h.scale_highlights();
+
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    assert_eq!(bulges(entity), vec![-1., 0., 0.]);
+    let ((_, _), (min_y, max_y)) = entity.bounding_box;
+    assert_close(min_y, -3.);
+    assert_close(max_y, 1.);
+
+    // Rotation keeps the bulges as they are
+    h.set_highlight_rotation_angle(PI / 2.);
+    h.rotate_highlights_bbox();
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    assert_eq!(bulges(entity), vec![-1., 0., 0.]);
+}
+
+#[test]
+fn test_non_uniform_scale_flattens_bulges() {
+    let mut h = bulge_handle();
+    h.highlight_block("S".to_string(), true);
+    h.set_highlight_anchor(0., 0.);
+    h.set_highlight_scale(1., 2.);
+    h.scale_highlights();
+
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    assert!(!entity.has_bulges());
+    assert!(entity.vertices.shape()[0] > 3);
+    let ((_, _), (min_y, max_y)) = entity.bounding_box;
+    assert_close(min_y, -2.);
+    assert_close(max_y, 6.);
+}
+
+#[test]
+fn test_bulges_roundtrip_through_dxf_json_and_svg() {
+    let p = pattern::Pattern::from_document(&bulge_document(Some(0.5)));
+    let document = parse_dxf::parse_dxf(&write_dxf::write_dxf(&p)).unwrap();
+    let polyline = &document.pattern_json.blocks["S"].entities[0];
+    let parsed_bulges: Vec<Option<f32>> = polyline
+        .vertices
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|v| v.bulge)
+        .collect();
+    assert_eq!(parsed_bulges, vec![Some(0.5), None, None]);
+
+    let roundtrip = pattern::Pattern::from_document(&p.to_document());
+    assert_eq!(
+        bulges(&roundtrip.get_blocks()[0].get_entities()[0]),
+        vec![0.5, 0., 0.]
+    );
+
+    // Polylines without arcs don't write bulges
+    let straight = pattern::Pattern::from_document(&bulge_document(None));
+    let json = straight.to_json();
+    assert!(!json.contains("bulge"));
+
+    let svg = write_svg::write_svg(&p, &ISettings::default());
+    assert!(svg.contains(" A 1.25 1.25 0 0 1 2 0 L 2 3"));
+}
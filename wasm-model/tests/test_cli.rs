@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use cad_pattern_editor::cli;
+use cad_pattern_editor::pattern;
+
+mod common;
+use common::assert_close;
+
+const DOCUMENT: &str = r#"{"pattern_json": {
+    "blocks": {
+        "A": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000001",
+             "vertices": [{"x": 0, "y": 0}, {"x": 2, "y": 0}]}
+        ]},
+        "B": {"layer": "5", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LINE", "layer": "5", "entity_index": "00000000000000000000000000000002",
+             "vertices": [{"x": 0, "y": 0}, {"x": 0, "y": 1}]}
+        ]}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "A", "layer": "1", "position": {"x": 0, "y": 0}},
+        {"entity_type": "INSERT", "name": "A", "layer": "1", "position": {"x": 10, "y": 0}},
+        {"entity_type": "INSERT", "name": "B", "layer": "5", "position": {"x": 0, "y": 0}}
+    ]
+}}"#;
+
+fn temp_path(name: &str) -> String {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("cad-pattern-{}-{}", std::process::id(), name));
+    path.to_string_lossy().to_string()
+}
+
+fn write_document(name: &str) -> String {
+    let path = temp_path(name);
+    fs::write(&path, DOCUMENT).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Result<String, String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    cli::run(&args)
+}
+
+fn block_bbox(path: &str, name: &str) -> ((f32, f32), (f32, f32)) {
+    let p = pattern::Pattern::new(fs::read_to_string(path).unwrap());
+    let block = p.get_blocks().iter().find(|b| b.name == name).unwrap();
+    *block.get_bounding_box()
+}
+
+#[test]
+fn test_info_counts_the_pattern() {
+    let input = write_document("info.json");
+    let output = run(&["info", &input]).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "blocks: 2",
+            "inserts: 3",
+            "entities: 2",
+            "layers: 1, 5",
+            "block A: 2 placements",
+            "block B: 1 placements",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_fails_on_errors() {
+    let input = write_document("valid.json");
+    let output = run(&["validate", &input]).unwrap();
+    assert!(output.ends_with("0 errors, 0 warnings"));
+
+    let broken = temp_path("broken.json");
+    fs::write(
+        &broken,
+        DOCUMENT.replace("\"layer\": \"5\",", "\"layer\": \"five\","),
+    )
+    .unwrap();
+    let output = run(&["validate", &broken]).unwrap_err();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].ends_with("2 errors, 0 warnings"));
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1..]
+        .iter()
+        .all(|l| l.starts_with("Error at /pattern_json/")));
+    assert!(lines.contains(&"Error at /pattern_json/blocks/B/layer: Invalid layer five"));
+}
+
+#[test]
+fn test_convert_between_formats() {
+    let input = write_document("convert.json");
+    let dxf = temp_path("convert.dxf");
+    let json = temp_path("convert_back.json");
+    let svg = temp_path("convert.svg");
+
+    assert_eq!(
+        run(&["convert", &input, &dxf]).unwrap(),
+        format!("Wrote {}", dxf)
+    );
+    run(&["convert", &dxf, &json]).unwrap();
+    let p = pattern::Pattern::new(fs::read_to_string(&json).unwrap());
+    assert_eq!(p.get_number_blocks(), 2);
+    assert_eq!(p.get_number_inserts(), 3);
+    assert_eq!(p.get_number_entities(), 2);
+
+    run(&["convert", &dxf, &svg, "--view", "Block=>A"]).unwrap();
+    assert!(fs::read_to_string(&svg).unwrap().starts_with("<svg"));
+
+    assert!(run(&["convert", &input, &svg, "--view", "Block=>C"]).is_err());
+    assert!(run(&["convert", &svg, &json]).is_err());
+    assert!(run(&["convert", &input, &temp_path("convert.txt")]).is_err());
+}
+
+#[test]
+fn test_transform_named_blocks() {
+    let input = write_document("transform.json");
+    let output = temp_path("transformed.json");
+
+    run(&[
+        "transform",
+        &input,
+        &output,
+        "--block",
+        "A",
+        "--scale",
+        "2,1",
+        "--anchor",
+        "0,0",
+    ])
+    .unwrap();
+    let ((min_x, max_x), _) = block_bbox(&output, "A");
+    assert_close(min_x, 0.);
+    assert_close(max_x, 4.);
+    // Other blocks are left alone
+    assert_eq!(block_bbox(&output, "B"), block_bbox(&input, "B"));
+
+    // A quarter turn counterclockwise about the start of the line
+    run(&[
+        "transform",
+        &input,
+        &output,
+        "--block",
+        "A",
+        "--rotate",
+        "90",
+        "--center",
+        "0,0",
+    ])
+    .unwrap();
+    let ((min_x, max_x), (min_y, max_y)) = block_bbox(&output, "A");
+    assert_close(min_x, 0.);
+    assert_close(max_x, 0.);
+    assert_close(min_y, 0.);
+    assert_close(max_y, 2.);
+
+    run(&[
+        "transform",
+        &input,
+        &output,
+        "--block",
+        "A",
+        "--block",
+        "B",
+        "--offset",
+        "1,2",
+    ])
+    .unwrap();
+    let p = pattern::Pattern::new(fs::read_to_string(&output).unwrap());
+    let placements = p.get_block_placements(&"A".to_string());
+    let positions: Vec<(f32, f32)> = placements
+        .iter()
+        .map(|t| t.apply_point(&(0., 0.)))
+        .collect();
+    assert_eq!(positions, vec![(1., 2.), (11., 2.)]);
+}
+
+#[test]
+fn test_transform_rejects_bad_arguments() {
+    let input = write_document("bad.json");
+    let output = temp_path("bad_out.json");
+    assert!(run(&["transform", &input, &output, "--offset", "1,2"]).is_err());
+    assert!(run(&["transform", &input, &output, "--block", "A"]).is_err());
+    assert!(run(&[
+        "transform",
+        &input,
+        &output,
+        "--block",
+        "C",
+        "--offset",
+        "1,2"
+    ])
+    .is_err());
+    assert!(run(&[
+        "transform",
+        &input,
+        &output,
+        "--block",
+        "A",
+        "--offset",
+        "1"
+    ])
+    .is_err());
+    assert!(run(&["transform", &input, &output, "--block", "A", "--skew", "1"]).is_err());
+    assert!(run(&["unknown"]).is_err());
+    assert!(run(&[]).is_err());
+}
+
+#[test]
+fn test_binary_reports_through_exit_code() {
+    let input = write_document("binary.json");
+    let binary = env!("CARGO_BIN_EXE_cad-pattern");
+
+    let status = Command::new(binary)
+        .args(["info", &input])
+        .output()
+        .unwrap();
+    assert!(status.status.success());
+    assert!(String::from_utf8_lossy(&status.stdout).starts_with("blocks: 2"));
+
+    let status = Command::new(binary)
+        .args(["validate", "missing.json"])
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+    assert!(String::from_utf8_lossy(&status.stderr).contains("Unable to read missing.json"));
+}
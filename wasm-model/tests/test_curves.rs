@@ -0,0 +1,251 @@
+use std::f32::consts::PI;
+
+use cad_pattern_editor::curve::Curve;
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::EntityTypes;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::parse_pattern::{ParseDocument, ParseEntity, Vertex};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::write_dxf;
+use cad_pattern_editor::write_svg;
+
+mod common;
+use common::assert_close;
+
+const ARC_ID: &str = "000000000000000000000000000000a1";
+const CIRCLE_ID: &str = "000000000000000000000000000000c1";
+
+// Block C holding a quarter arc and a circle, inserted at the origin
+fn curve_document() -> ParseDocument {
+    common::single_block_document(
+        "C",
+        vec![
+            ParseEntity {
+                entity_type: "ARC".to_string(),
+                layer: "1".to_string(),
+                entity_index: ARC_ID.to_string(),
+                center: Some(Vertex {
+                    x: 10.,
+                    y: 0.,
+                    bulge: None,
+                }),
+                radius: Some(5.),
+                start_angle: Some(0.),
+                end_angle: Some(90.),
+                ..Default::default()
+            },
+            ParseEntity {
+                entity_type: "CIRCLE".to_string(),
+                layer: "1".to_string(),
+                entity_index: CIRCLE_ID.to_string(),
+                center: Some(Vertex {
+                    x: 0.,
+                    y: 0.,
+                    bulge: None,
+                }),
+                radius: Some(2.),
+                ..Default::default()
+            },
+        ],
+    )
+}
+
+fn curve_handle() -> editor::Editor {
+    common::to_editor(&curve_document())
+}
+
+fn get_curve(h: &editor::Editor, entity_ind: usize) -> Option<Curve> {
+    h.get_pattern().get_blocks()[0].get_entities()[entity_ind]
+        .curve
+        .clone()
+}
+
+#[test]
+fn test_curves_are_parsed_as_analytic_entities() {
+    let p = pattern::Pattern::from_document(&curve_document());
+    let entities = p.get_blocks()[0].get_entities();
+    assert_eq!(entities.len(), 2);
+    assert_eq!(entities[0].entity_type, EntityTypes::ARC);
+    assert_eq!(entities[1].entity_type, EntityTypes::CIRCLE);
+    assert!(entities[1].shape);
+
+    // Quarter arc from (15, 0) to (10, 5)
+    let ((min_x, max_x), (min_y, max_y)) = entities[0].bounding_box;
+    assert_close(min_x, 10.);
+    assert_close(max_x, 15.);
+    assert_close(min_y, 0.);
+    assert_close(max_y, 5.);
+
+    let ((min_x, max_x), (min_y, max_y)) = entities[1].bounding_box;
+    assert_close(min_x, -2.);
+    assert_close(max_x, 2.);
+    assert_close(min_y, -2.);
+    assert_close(max_y, 2.);
+
+    // Curves survive a round trip through the JSON format
+    let roundtrip = pattern::Pattern::from_document(&p.to_document());
+    assert_eq!(
+        serde_json::to_string(&roundtrip.to_document()).unwrap(),
+        serde_json::to_string(&p.to_document()).unwrap()
+    );
+    let arc = &roundtrip.to_document().pattern_json.blocks["C"].entities[0];
+    assert_eq!(arc.entity_type, "ARC");
+    assert_close(arc.end_angle.unwrap(), 90.);
+    assert!(arc.vertices.is_none());
+}
+
+#[test]
+fn test_tessellation_respects_chord_tolerance() {
+    let circle = Curve::Circle {
+        center: (1., 2.),
+        radius: 10.,
+    };
+    for tolerance in [0.5, 0.05, 0.001] {
+        let points = circle.tessellate(tolerance);
+        let n = points.shape()[0];
+        for i in 0..n {
+            let (x0, y0) = (points[(i, 0)], points[(i, 1)]);
+            let (x1, y1) = (points[((i + 1) % n, 0)], points[((i + 1) % n, 1)]);
+            let (mx, my) = ((x0 + x1) / 2. - 1., (y0 + y1) / 2. - 2.);
+            let sagitta = 10. - (mx * mx + my * my).sqrt();
+            assert!(sagitta <= tolerance * 1.01, "{} > {}", sagitta, tolerance);
+        }
+    }
+    assert!(circle.tessellate(0.001).shape()[0] > circle.tessellate(0.5).shape()[0]);
+
+    // Open arcs end exactly on their end points
+    let arc = Curve::Arc {
+        center: (0., 0.),
+        radius: 1.,
+        start_angle: 0.,
+        end_angle: PI,
+    };
+    let points = arc.tessellate(0.01);
+    let last = points.shape()[0] - 1;
+    assert_close(points[(0, 0)], 1.);
+    assert_close(points[(last, 0)], -1.);
+    assert_close(points[(last, 1)], 0.);
+}
+
+#[test]
+fn test_arc_bounding_box_crossing_zero_angle() {
+    let arc = Curve::Arc {
+        center: (0., 0.),
+        radius: 1.,
+        start_angle: 1.5 * PI,
+        end_angle: 0.5 * PI,
+    };
+    let ((min_x, max_x), (min_y, max_y)) = arc.bounding_box();
+    assert_close(min_x, 0.);
+    assert_close(max_x, 1.);
+    assert_close(min_y, -1.);
+    assert_close(max_y, 1.);
+}
+
+#[test]
+fn test_curves_stay_exact_under_rotation_and_mirror() {
+    let mut h = curve_handle();
+    h.highlight_block("C".to_string(), true);
+    h.set_highlight_rotation_center(10., 0.);
+    h.set_highlight_rotation_angle(PI / 2.);
+    h.rotate_highlights_bbox();
+
+    let arc = get_curve(&h, 0).unwrap();
+    let Curve::Arc { center, radius, .. } = arc.clone() else {
+        panic!("Expected an arc");
+    };
+    assert_close(center.0, 10.);
+    assert_close(center.1, 0.);
+    assert_close(radius, 5.);
+    assert_close(arc.get_sweep(), PI / 2.);
+
+    // Mirror in x about the arc center, the middle of the arc has to mirror with it
+    let middle = |arc: &Curve| arc.point_at_angle(arc.get_start_angle() + arc.get_sweep() / 2.);
+    let before = middle(&arc);
+    h.set_highlight_anchor(10., 0.);
+    h.set_highlight_flip(true, false);
+    h.scale_highlights();
+    let arc = get_curve(&h, 0).unwrap();
+    assert_close(arc.get_sweep(), PI / 2.);
+    let after = middle(&arc);
+    assert_close(after.0, 20. - before.0);
+    assert_close(after.1, before.1);
+    assert_eq!(
+        h.get_pattern().get_blocks()[0].get_entities()[0].entity_type,
+        EntityTypes::ARC
+    );
+
+    // Uniform scale keeps the circle a circle
+    h.set_highlight_anchor(0., 0.);
+    h.set_highlight_scale(2., 2.);
+    h.scale_highlights();
+    let Some(Curve::Circle { radius, .. }) = get_curve(&h, 1) else {
+        panic!("Expected a circle");
+    };
+    assert_close(radius, 4.);
+}
+
+#[test]
+fn test_non_uniform_scale_turns_curves_into_ellipses() {
+    let mut h = curve_handle();
+    h.highlight_block("C".to_string(), true);
+    h.set_highlight_anchor(0., 0.);
+    h.set_highlight_scale(2., 1.);
+    h.scale_highlights();
+
+    let circle = &h.get_pattern().get_blocks()[0].get_entities()[1];
+    assert!(matches!(circle.curve, Some(Curve::Ellipse { .. })));
+    assert_eq!(circle.entity_type, EntityTypes::ELLIPSE);
+    assert!(circle.shape);
+    let ((min_x, max_x), (min_y, max_y)) = circle.bounding_box;
+    assert_close(min_x, -4.);
+    assert_close(max_x, 4.);
+    assert_close(min_y, -2.);
+    assert_close(max_y, 2.);
+
+    // Undo brings back the circle
+    h.undo();
+    assert!(matches!(get_curve(&h, 1), Some(Curve::Circle { .. })));
+}
+
+#[test]
+fn test_curves_draw_with_configured_tolerance() {
+    let count_vertices = |tolerance: f32| {
+        let settings = ISettings {
+            chord_tolerance: tolerance,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&curve_document()).unwrap();
+        let mut h = editor::Editor::new(json, serde_json::to_string(&settings).unwrap());
+        h.update_draw_sequence();
+        h.get_buffers().0.len() / 3
+    };
+    assert!(count_vertices(0.001) > count_vertices(0.1));
+}
+
+#[test]
+fn test_curves_roundtrip_through_dxf_and_svg() {
+    let p = pattern::Pattern::from_document(&curve_document());
+    let dxf_payload = write_dxf::write_dxf(&p);
+    let document = parse_dxf::parse_dxf(&dxf_payload).expect("Exported DXF does not parse");
+    let block = &document.pattern_json.blocks["C"];
+    let arc = block
+        .entities
+        .iter()
+        .find(|e| e.entity_type == "ARC")
+        .unwrap();
+    assert_close(arc.radius.unwrap(), 5.);
+    assert_close(arc.start_angle.unwrap(), 0.);
+    assert_close(arc.end_angle.unwrap(), 90.);
+    let circle = block
+        .entities
+        .iter()
+        .find(|e| e.entity_type == "CIRCLE")
+        .unwrap();
+    assert_close(circle.radius.unwrap(), 2.);
+
+    let svg = write_svg::write_svg(&p, &ISettings::default());
+    assert!(svg.contains("<circle cx=\"0\" cy=\"0\" r=\"2\""));
+    assert!(svg.contains("A 5 5 0 0 1"));
+}
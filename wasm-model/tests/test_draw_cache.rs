@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn load_example_editor() -> editor::Editor {
+    let path = Path::new("../example_input/shirt.json");
+    let json = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+    editor::Editor::new(json, settings_payload)
+}
+
+// Buffers drawn from scratch by a new handle after the same edits
+fn assert_matches_full_draw(h: &editor::Editor, edits: &dyn Fn(&mut editor::Editor)) {
+    let mut fresh = load_example_editor();
+    edits(&mut fresh);
+    fresh.update_draw_sequence();
+    assert_eq!(h.get_buffers(), fresh.get_buffers());
+}
+
+fn total_bytes(ranges: &[u32]) -> u32 {
+    ranges.chunks(2).map(|r| r[1] - r[0]).sum()
+}
+
+#[test]
+fn test_first_draw_writes_everything() {
+    let mut h = load_example_editor();
+    h.update_draw_sequence();
+    let vertex_bytes = (h.get_vertex_buffer_len() * 4) as u32;
+    let index_bytes = (h.get_index_buffer_len() * 4) as u32;
+    assert_eq!(h.get_changed_vertex_ranges(), vec![0, vertex_bytes]);
+    assert_eq!(h.get_changed_index_ranges(), vec![0, index_bytes]);
+
+    h.update_draw_sequence();
+    assert!(h.get_changed_vertex_ranges().is_empty());
+    assert!(h.get_changed_index_ranges().is_empty());
+}
+
+#[test]
+fn test_drag_only_rewrites_selected_block() {
+    let edits = |h: &mut editor::Editor| {
+        h.highlight_block("L-2".to_string(), true);
+        h.set_highlight_offset(5., -3.);
+    };
+    let mut h = load_example_editor();
+    h.highlight_block("L-2".to_string(), true);
+    h.update_draw_sequence();
+    let vertex_len = h.get_vertex_buffer_len();
+    let selected = h.get_user_settings().highlight_nr_selected_entities;
+
+    h.set_highlight_offset(5., -3.);
+    h.update_draw_sequence();
+
+    let changed = h.get_changed_vertex_ranges();
+    assert_eq!(changed.len(), 2);
+    assert!(total_bytes(&changed) < (vertex_len * 4) as u32);
+    assert!(h.get_changed_index_ranges().is_empty());
+    assert_eq!(h.get_vertex_buffer_len(), vertex_len);
+    assert_eq!(
+        h.get_user_settings().highlight_nr_selected_entities,
+        selected
+    );
+    assert_matches_full_draw(&h, &edits);
+}
+
+#[test]
+fn test_resized_block_moves_later_blocks() {
+    let mut h = load_example_editor();
+    h.update_draw_sequence();
+
+    // Moving the first block partly out of the viewport changes how much of it is drawn
+    h.set_viewport(-1000., -1000., 1000., 1000.);
+    h.update_draw_sequence();
+    h.highlight_block("L-1".to_string(), true);
+    h.set_highlight_offset(-1000., 0.);
+    h.offset_highlights();
+    h.reset_selection();
+    h.update_draw_sequence();
+
+    assert!(!h.get_changed_index_ranges().is_empty());
+    assert_matches_full_draw(&h, &|h: &mut editor::Editor| {
+        h.set_viewport(-1000., -1000., 1000., 1000.);
+        h.highlight_block("L-1".to_string(), true);
+        h.set_highlight_offset(-1000., 0.);
+        h.offset_highlights();
+        h.reset_selection();
+    });
+}
+
+#[test]
+fn test_settings_change_redraws_all_blocks() {
+    let mut h = load_example_editor();
+    h.update_draw_sequence();
+    h.highlight_entity("5fa8d9ac-b69a-42dd-860a-204680c8dd2b".to_string(), true);
+    h.update_draw_sequence();
+    assert_eq!(h.get_changed_vertex_ranges().len(), 2);
+    assert!(h.get_changed_index_ranges().is_empty());
+
+    h.set_layer_color(1, "#ff0000ff".to_string());
+    h.update_draw_sequence();
+    let vertex_bytes = (h.get_vertex_buffer_len() * 4) as u32;
+    assert_eq!(h.get_changed_vertex_ranges(), vec![0, vertex_bytes]);
+    assert_matches_full_draw(&h, &|h: &mut editor::Editor| {
+        h.highlight_entity("5fa8d9ac-b69a-42dd-860a-204680c8dd2b".to_string(), true);
+        h.set_layer_color(1, "#ff0000ff".to_string());
+    });
+}
+
+#[test]
+fn test_undo_redraws_restored_block() {
+    let mut h = load_example_editor();
+    h.update_draw_sequence();
+    let (original_vertices, original_indices) = h.get_buffers();
+    let (original_vertices, original_indices) =
+        (original_vertices.clone(), original_indices.clone());
+
+    h.highlight_block("L-3".to_string(), true);
+    h.set_highlight_scale(2., 2.);
+    h.scale_highlights();
+    h.update_draw_sequence();
+    h.undo();
+    h.reset_selection();
+    h.update_draw_sequence();
+
+    let (vertices, indices) = h.get_buffers();
+    assert_eq!(vertices, &original_vertices);
+    assert_eq!(indices, &original_indices);
+}
+
+#[test]
+fn test_pan_only_redraws_blocks_cut_by_viewport() {
+    let mut h = load_example_editor();
+    h.set_viewport(-10000., -10000., 10000., 10000.);
+    h.update_draw_sequence();
+    let vertex_len = h.get_vertex_buffer_len();
+
+    // Nothing comes into or leaves a viewport that still holds the whole pattern
+    h.set_viewport(-9000., -9000., 11000., 11000.);
+    h.update_draw_sequence();
+    assert!(h.get_changed_vertex_ranges().is_empty());
+    assert!(h.get_changed_index_ranges().is_empty());
+
+    // Cuts through L-1 and L-2 and leaves L-3 out
+    h.set_viewport(-1000., -1000., -60., 1000.);
+    h.update_draw_sequence();
+    assert!(!h.get_changed_vertex_ranges().is_empty());
+    assert!(h.get_vertex_buffer_len() < vertex_len);
+    assert_matches_full_draw(&h, &|h: &mut editor::Editor| {
+        h.set_viewport(-1000., -1000., -60., 1000.);
+    });
+}
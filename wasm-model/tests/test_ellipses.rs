@@ -0,0 +1,259 @@
+use std::f32::consts::{PI, TAU};
+
+use ndarray::{array, Array2};
+
+use cad_pattern_editor::curve::Curve;
+use cad_pattern_editor::entity::EntityTypes;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::parse_pattern::{ParseDocument, ParseEntity, Vertex};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::write_dxf;
+use cad_pattern_editor::write_svg;
+
+mod common;
+use common::assert_close;
+
+fn tilted_ellipse(start_param: f32, end_param: f32) -> Curve {
+    Curve::Ellipse {
+        center: (1., 2.),
+        major_axis: (3., 4.),
+        ratio: 0.4,
+        start_param,
+        end_param,
+    }
+}
+
+// Box of many points along the curve, close to the exact one
+fn sampled_bounding_box(curve: &Curve) -> ((f32, f32), (f32, f32)) {
+    let points: Vec<(f32, f32)> = (0..=20000)
+        .map(|i| {
+            curve.point_at_angle(curve.get_start_angle() + curve.get_sweep() * i as f32 / 20000.)
+        })
+        .collect();
+    let min = |v: Vec<f32>| v.into_iter().fold(f32::INFINITY, f32::min);
+    let max = |v: Vec<f32>| v.into_iter().fold(f32::NEG_INFINITY, f32::max);
+    let xs = || points.iter().map(|p| p.0).collect::<Vec<f32>>();
+    let ys = || points.iter().map(|p| p.1).collect::<Vec<f32>>();
+    ((min(xs()), max(xs())), (min(ys()), max(ys())))
+}
+
+// Distance from one of the unit circle in the ellipse's own axes, zero for points on it
+fn ellipse_error(curve: &Curve, p: &(f32, f32)) -> f32 {
+    let Curve::Ellipse {
+        center,
+        major_axis,
+        ratio,
+        ..
+    } = curve
+    else {
+        panic!("Expected an ellipse");
+    };
+    let length = (major_axis.0 * major_axis.0 + major_axis.1 * major_axis.1).sqrt();
+    let (d_x, d_y) = (p.0 - center.0, p.1 - center.1);
+    let u = (d_x * major_axis.0 + d_y * major_axis.1) / (length * length);
+    let v = (-d_x * major_axis.1 + d_y * major_axis.0) / (length * length * ratio);
+    (u * u + v * v).sqrt() - 1.
+}
+
+fn apply(p: &(f32, f32), matrix: &Array2<f32>, translation: &Array2<f32>) -> (f32, f32) {
+    (
+        p.0 * matrix[(0, 0)] + p.1 * matrix[(1, 0)] + translation[(0, 0)],
+        p.0 * matrix[(0, 1)] + p.1 * matrix[(1, 1)] + translation[(0, 1)],
+    )
+}
+
+// Every point of the transformed curve comes from the original one, and the ends line up
+fn assert_exact_transform(curve: &Curve, matrix: &Array2<f32>, translation: &Array2<f32>) {
+    let transformed = curve.transform(matrix, translation).unwrap();
+    let ((a, b), (c, d)) = (
+        (matrix[(0, 0)], matrix[(0, 1)]),
+        (matrix[(1, 0)], matrix[(1, 1)]),
+    );
+    let determinant = a * d - b * c;
+    let inverse = array![[d, -b], [-c, a]] / determinant;
+    let back = |p: &(f32, f32)| {
+        let shifted = (p.0 - translation[(0, 0)], p.1 - translation[(0, 1)]);
+        apply(&shifted, &inverse, &array![[0., 0.]])
+    };
+    for v in transformed.tessellate(0.01).rows() {
+        assert!(ellipse_error(curve, &back(&(v[0], v[1]))).abs() < 1e-3);
+    }
+    assert_close(transformed.get_sweep(), curve.get_sweep());
+
+    if !curve.is_closed() {
+        let ends = [curve.get_start_point(), curve.get_end_point()]
+            .map(|p| apply(&p, matrix, translation));
+        // A mirror swaps which end the curve starts from
+        let new_ends = if determinant > 0. {
+            [transformed.get_start_point(), transformed.get_end_point()]
+        } else {
+            [transformed.get_end_point(), transformed.get_start_point()]
+        };
+        for (p, q) in ends.iter().zip(new_ends.iter()) {
+            assert_close(p.0, q.0);
+            assert_close(p.1, q.1);
+        }
+    }
+}
+
+fn ellipse_document() -> ParseDocument {
+    common::single_block_document(
+        "E",
+        vec![ParseEntity {
+            entity_type: "ELLIPSE".to_string(),
+            layer: "1".to_string(),
+            entity_index: format!("{:032x}", 1),
+            center: Some(Vertex {
+                x: 1.,
+                y: 2.,
+                bulge: None,
+            }),
+            major_axis: Some(Vertex {
+                x: 3.,
+                y: 0.,
+                bulge: None,
+            }),
+            ratio: Some(0.5),
+            start_param: Some(0.),
+            end_param: Some(PI),
+            ..Default::default()
+        }],
+    )
+}
+
+#[test]
+fn test_ellipse_points_and_bounding_box() {
+    let ellipse = Curve::Ellipse {
+        center: (1., 2.),
+        major_axis: (3., 0.),
+        ratio: 0.5,
+        start_param: 0.,
+        end_param: TAU,
+    };
+    assert!(ellipse.is_closed());
+    let (x, y) = ellipse.point_at_angle(PI / 2.);
+    assert_close(x, 1.);
+    assert_close(y, 3.5);
+    let ((min_x, max_x), (min_y, max_y)) = ellipse.bounding_box();
+    assert_close(min_x, -2.);
+    assert_close(max_x, 4.);
+    assert_close(min_y, 0.5);
+    assert_close(max_y, 3.5);
+
+    for curve in [
+        tilted_ellipse(0., 0.),
+        tilted_ellipse(0.3, 2.),
+        tilted_ellipse(5., 1.),
+    ] {
+        let ((min_x, max_x), (min_y, max_y)) = curve.bounding_box();
+        let ((s_min_x, s_max_x), (s_min_y, s_max_y)) = sampled_bounding_box(&curve);
+        assert_close(min_x, s_min_x);
+        assert_close(max_x, s_max_x);
+        assert_close(min_y, s_min_y);
+        assert_close(max_y, s_max_y);
+    }
+}
+
+#[test]
+fn test_ellipse_transforms_are_exact() {
+    let rotation = array![[0.6, -0.8], [0.8, 0.6]];
+    let stretch = array![[2., 0.], [0., 0.5]];
+    let mirror = array![[-1., 0.], [0., 1.]];
+    let shear = array![[1., 0.], [0.7, 1.]];
+    let translation = array![[5., -3.]];
+    for curve in [
+        tilted_ellipse(0., 0.),
+        tilted_ellipse(0.3, 2.),
+        tilted_ellipse(5., 1.),
+    ] {
+        for matrix in [&rotation, &stretch, &mirror, &shear] {
+            assert_exact_transform(&curve, matrix, &translation);
+        }
+        assert_exact_transform(&curve, &stretch.dot(&mirror).dot(&rotation), &translation);
+    }
+}
+
+#[test]
+fn test_arcs_become_ellipses_under_non_uniform_scale() {
+    let arc = Curve::Arc {
+        center: (1., 1.),
+        radius: 2.,
+        start_angle: 0.,
+        end_angle: PI / 2.,
+    };
+    let matrix = array![[3., 0.], [0., 1.]];
+    let translation = array![[0., 0.]];
+    let Some(ellipse @ Curve::Ellipse { ratio, .. }) = arc.transform(&matrix, &translation) else {
+        panic!("Expected an ellipse");
+    };
+    assert_close(ratio, 1. / 3.);
+    assert_close(ellipse.get_sweep(), PI / 2.);
+    let start = ellipse.get_start_point();
+    let end = ellipse.get_end_point();
+    assert_close(start.0, 9.);
+    assert_close(start.1, 1.);
+    assert_close(end.0, 3.);
+    assert_close(end.1, 3.);
+    for v in ellipse.tessellate(0.01).rows() {
+        let (u, w) = ((v[0] - 3.) / 6., (v[1] - 1.) / 2.);
+        assert_close(u * u + w * w, 1.);
+    }
+}
+
+#[test]
+fn test_ellipse_entity_scale_and_flip() {
+    let mut h = common::to_editor(&ellipse_document());
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    assert_eq!(entity.entity_type, EntityTypes::ELLIPSE);
+    assert!(!entity.shape);
+    // Upper half from (4, 2) round to (-2, 2)
+    let ((min_x, max_x), (min_y, max_y)) = entity.bounding_box;
+    assert_close(min_x, -2.);
+    assert_close(max_x, 4.);
+    assert_close(min_y, 2.);
+    assert_close(max_y, 3.5);
+
+    h.highlight_block("E".to_string(), true);
+    h.set_highlight_anchor(1., 2.);
+    h.set_highlight_scale(1., 4.);
+    h.set_highlight_flip(false, true);
+    h.scale_highlights();
+
+    // Now the lower half with the minor axis stretched past the major axis
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    let ((min_x, max_x), (min_y, max_y)) = entity.bounding_box;
+    assert_close(min_x, -2.);
+    assert_close(max_x, 4.);
+    assert_close(min_y, -4.);
+    assert_close(max_y, 2.);
+    let Some(Curve::Ellipse {
+        major_axis, ratio, ..
+    }) = entity.curve
+    else {
+        panic!("Expected an ellipse");
+    };
+    assert_close(major_axis.0.abs(), 0.);
+    assert_close(major_axis.1.abs(), 6.);
+    assert_close(ratio, 0.5);
+}
+
+#[test]
+fn test_ellipses_roundtrip_through_json_dxf_and_svg() {
+    let p = pattern::Pattern::from_document(&ellipse_document());
+    let roundtrip = pattern::Pattern::from_document(&p.to_document());
+    assert_eq!(
+        serde_json::to_string(&roundtrip.to_document()).unwrap(),
+        serde_json::to_string(&p.to_document()).unwrap()
+    );
+
+    let document = parse_dxf::parse_dxf(&write_dxf::write_dxf(&p)).unwrap();
+    let ellipse = &document.pattern_json.blocks["E"].entities[0];
+    assert_eq!(ellipse.entity_type, "ELLIPSE");
+    assert_close(ellipse.ratio.unwrap(), 0.5);
+    assert_close(ellipse.major_axis.as_ref().unwrap().x, 3.);
+    assert_close(ellipse.end_param.unwrap(), PI);
+
+    let svg = write_svg::write_svg(&p, &ISettings::default());
+    assert!(svg.contains("M 4 2 A 3 1.5 0 0 1"));
+}
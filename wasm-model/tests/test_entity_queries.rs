@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::Path;
+
+use ndarray::array;
+
+use cad_pattern_editor::entity::{Entity, EntityTypes};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn make_entity(entity_type: EntityTypes, shape: bool, vertices: ndarray::Array2<f32>) -> Entity {
+    Entity::new(
+        entity_type,
+        1,
+        shape,
+        vertices,
+        0.5,
+        [b'0'; 32],
+        "#12".to_string(),
+    )
+}
+
+#[test]
+fn test_closest_point_on_point() {
+    let e = make_entity(EntityTypes::POINT, false, array![[1., 2.]]);
+
+    let closest = e.get_closest_point_on_entity(&(4., 6.)).unwrap();
+
+    assert_eq!(closest.point, (1., 2.));
+    assert_eq!(closest.distance, 5.);
+    assert!(e.has_point_within_threshold(&(4., 6.), 5.));
+    assert!(!e.has_point_within_threshold(&(4., 6.), 4.9));
+}
+
+#[test]
+fn test_closest_point_on_line() {
+    let e = make_entity(EntityTypes::LINE, false, array![[0., 0.], [10., 0.]]);
+
+    let middle = e.get_closest_point_on_entity(&(3., 2.)).unwrap();
+    let past_end = e.get_closest_point_on_entity(&(13., 4.)).unwrap();
+
+    assert_eq!(middle.point, (3., 0.));
+    assert_eq!(middle.distance, 2.);
+    assert_eq!(past_end.point, (10., 0.));
+    assert_eq!(past_end.distance, 5.);
+}
+
+#[test]
+fn test_closest_point_on_open_and_closed_polyline() {
+    let vertices = array![[0., 0.], [10., 0.], [10., 10.], [0., 10.]];
+    let open = make_entity(EntityTypes::LWPOLYLINE, false, vertices.clone());
+    let closed = make_entity(EntityTypes::LWPOLYLINE, true, vertices);
+
+    let open_closest = open.get_closest_point_on_entity(&(-1., 5.)).unwrap();
+    let closed_closest = closed.get_closest_point_on_entity(&(-1., 5.)).unwrap();
+
+    assert_eq!(open_closest.segment_index, 0);
+    assert_eq!(open_closest.point, (0., 0.));
+    assert_eq!(closed_closest.segment_index, 3);
+    assert_eq!(closed_closest.point, (0., 5.));
+    assert_eq!(closed_closest.distance, 1.);
+    assert!(closed.contains_point(&(5., 5.)));
+    assert!(!open.contains_point(&(5., 5.)));
+}
+
+#[test]
+fn test_closest_defined_point() {
+    let e = make_entity(
+        EntityTypes::LWPOLYLINE,
+        false,
+        array![[0., 0.], [10., 0.], [10., 10.]],
+    );
+
+    let closest = e.get_closest_defined_point_on_entity(&(9., 1.)).unwrap();
+
+    assert_eq!(closest.point, (10., 0.));
+    assert_eq!(closest.segment_index, 1);
+    assert!(e.has_defined_point_within_threshold(&(9., 1.), 1.5));
+    assert!(!e.has_defined_point_within_threshold(&(5., 1.), 1.5));
+}
+
+#[test]
+fn test_closest_point_on_text() {
+    let e = make_entity(EntityTypes::TEXT, false, array![[0., 0.]]);
+
+    let inside = e.get_closest_point_on_entity(&(0.2, 0.2)).unwrap();
+    let above = e.get_closest_point_on_entity(&(0.2, 1.5)).unwrap();
+
+    assert_eq!(inside.distance, 0.);
+    assert_eq!(above.point, (0.2, 0.5));
+    assert_eq!(above.distance, 1.);
+}
+
+#[test]
+fn test_find_closest_entity_in_pattern() {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    let p = pattern::Pattern::new(json_payload);
+    let settings = ISettings {
+        point_threshold: 0.5,
+        ..Default::default()
+    };
+
+    // First LINE of L-1 starts at (-24.9363, 15.25) in block coordinates
+    let insert = &p.get_inserts()[0];
+    let point = (
+        insert.position[(0, 0)] - 20.,
+        insert.position[(0, 1)] + 15.35,
+    );
+    let hit = p.find_closest_entity(&point, &settings).unwrap();
+
+    assert_eq!(hit.block, "L-1");
+    assert_eq!(hit.entity_index, "5fa8d9ac-b69a-42dd-860a-204680c8dd2b");
+    assert!((hit.closest.distance - 0.1).abs() < 1e-4);
+    assert!(p.find_closest_entity(&(1000., 1000.), &settings).is_none());
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_pattern;
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+
+// First LINE of block L-1 and the POINT at its start
+const LINE_INDEX: &str = "5fa8d9ac-b69a-42dd-860a-204680c8dd2b";
+const POINT_INDEX: &str = "547a676f-f635-49b8-bb96-f70a0c9f519e";
+
+fn load_example_json() -> String {
+    let path = Path::new("../example_input/shirt.json");
+    fs::read_to_string(path).expect("Unable to read JSON file")
+}
+
+fn load_example_editor(settings: &ISettings) -> editor::Editor {
+    let settings_payload = serde_json::to_string(settings).unwrap();
+    editor::Editor::new(load_example_json(), settings_payload)
+}
+
+fn find_entity<'a>(
+    document: &'a parse_pattern::ParseDocument,
+    entity_index: &str,
+) -> &'a parse_pattern::ParseEntity {
+    document.pattern_json.blocks["L-1"]
+        .entities
+        .iter()
+        .find(|e| e.entity_index == entity_index)
+        .unwrap()
+}
+
+#[test]
+fn test_window_and_crossing_selection() {
+    let p = pattern::Pattern::new(load_example_json());
+    let settings = ISettings::default();
+    let insert = &p.get_inserts()[0];
+    let (d_x, d_y) = (insert.position[(0, 0)], insert.position[(0, 1)]);
+
+    // Box around the start of the first line of L-1 at (-24.9363, 15.25)
+    let bbox = ((d_x - 25.5, d_x - 24.5), (d_y + 14.5, d_y + 16.));
+
+    let (window, window_bbox) = p.find_entities_with_bbox(&bbox, &settings, false);
+    let (crossing, _) = p.find_entities_with_bbox(&bbox, &settings, true);
+
+    assert!(window.contains(&POINT_INDEX.to_string()));
+    assert!(!window.contains(&LINE_INDEX.to_string()));
+    assert!(crossing.contains(&POINT_INDEX.to_string()));
+    assert!(crossing.contains(&LINE_INDEX.to_string()));
+    assert!(crossing.len() > window.len());
+    assert!(window_bbox.is_some());
+}
+
+#[test]
+fn test_highlight_entities_by_index() {
+    let mut p = pattern::Pattern::new(load_example_json());
+
+    p.highlight_entity_selection(&[LINE_INDEX.to_string(), POINT_INDEX.to_string()]);
+    assert_eq!(p.get_highlighted_entity_indexes().len(), 2);
+
+    p.set_entity_highlight(LINE_INDEX, false);
+    assert_eq!(
+        p.get_highlighted_entity_indexes(),
+        vec![POINT_INDEX.to_string()]
+    );
+    assert!(!p.set_entity_highlight("not-an-index", true));
+}
+
+#[test]
+fn test_offset_only_moves_selected_entities() {
+    let mut h = load_example_editor(&ISettings::default());
+    let before = parse_pattern::parse_pattern(&h.to_json()).unwrap();
+
+    h.highlight_entity(POINT_INDEX.to_string(), true);
+    h.set_highlight_offset(1., 2.);
+    h.offset_highlights();
+
+    let after = parse_pattern::parse_pattern(&h.to_json()).unwrap();
+    let moved_before = find_entity(&before, POINT_INDEX).position.as_ref().unwrap();
+    let moved_after = find_entity(&after, POINT_INDEX).position.as_ref().unwrap();
+    let line_before = find_entity(&before, LINE_INDEX).vertices.as_ref().unwrap();
+    let line_after = find_entity(&after, LINE_INDEX).vertices.as_ref().unwrap();
+
+    assert_eq!(moved_after.x, moved_before.x + 1.);
+    assert_eq!(moved_after.y, moved_before.y + 2.);
+    assert_eq!(line_before[0].x, line_after[0].x);
+    assert_eq!(
+        before.pattern_json.entities[0].position.x,
+        after.pattern_json.entities[0].position.x
+    );
+}
+
+#[test]
+fn test_scale_only_selected_entities() {
+    let mut h = load_example_editor(&ISettings::default());
+    let before = parse_pattern::parse_pattern(&h.to_json()).unwrap();
+    let insert = &before.pattern_json.entities[0].position;
+
+    h.highlight_entity(LINE_INDEX.to_string(), true);
+    // Anchor at the start of the line in world coordinates
+    h.set_highlight_anchor(insert.x - 24.9363, insert.y + 15.25);
+    h.set_highlight_scale(0.5, 1.);
+    h.scale_highlights();
+
+    let after = parse_pattern::parse_pattern(&h.to_json()).unwrap();
+    let line_before = find_entity(&before, LINE_INDEX).vertices.as_ref().unwrap();
+    let line_after = find_entity(&after, LINE_INDEX).vertices.as_ref().unwrap();
+    let expected_end = line_before[0].x + (line_before[1].x - line_before[0].x) * 0.5;
+
+    assert!((line_after[0].x - line_before[0].x).abs() < 1e-3);
+    assert!((line_after[1].x - expected_end).abs() < 1e-3);
+    assert_eq!(
+        find_entity(&before, POINT_INDEX)
+            .position
+            .as_ref()
+            .unwrap()
+            .x,
+        find_entity(&after, POINT_INDEX)
+            .position
+            .as_ref()
+            .unwrap()
+            .x
+    );
+}
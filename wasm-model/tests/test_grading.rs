@@ -0,0 +1,238 @@
+use std::fs;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::{Entity, EntityTypes};
+use cad_pattern_editor::load_report::LoadIssueKind;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::color;
+
+const DOCUMENT: &str = r##"{"pattern_json": {
+    "blocks": {
+        "P": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000001",
+             "shape": true, "vertices": [{"x": 0, "y": 0}, {"x": 10, "y": 0}, {"x": 10, "y": 10}, {"x": 0, "y": 10}]},
+            {"entity_type": "POINT", "layer": "2", "entity_index": "00000000000000000000000000000002",
+             "position": {"x": 10, "y": 0}},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000003",
+             "start_point": {"x": 10, "y": 0}, "text_height": 0.4, "text": "#1"},
+            {"entity_type": "POINT", "layer": "2", "entity_index": "00000000000000000000000000000004",
+             "position": {"x": 10, "y": 10}},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000005",
+             "start_point": {"x": 10, "y": 10}, "text_height": 0.4, "text": "#2"},
+            {"entity_type": "POINT", "layer": "3", "entity_index": "0000000000000000000000000000000a",
+             "position": {"x": 0, "y": 0}}
+        ]}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "P", "layer": "1", "position": {"x": 5, "y": 5}}
+    ]
+}}"##;
+
+const RULES: &str = r##"{"base_size": "M", "sizes": ["S", "M", "L"], "rules": [
+    {"point": "#1", "moves": {"S": [-1, 0], "L": [1, 0]}},
+    {"point": "#2", "moves": {"S": [-1, -1], "L": [1, 1]}},
+    {"point": "0000000000000000000000000000000a", "moves": {"L": [0, -1]}}
+]}"##;
+
+fn load() -> editor::Editor {
+    editor::Editor::new(
+        DOCUMENT.to_string(),
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    )
+}
+
+fn get_entities(e: &editor::Editor, entity_type: EntityTypes) -> Vec<&Entity> {
+    e.get_pattern().get_blocks()[0]
+        .get_entities()
+        .iter()
+        .filter(|x| x.entity_type == entity_type)
+        .collect()
+}
+
+fn outline(e: &editor::Editor) -> Vec<(f32, f32)> {
+    get_entities(e, EntityTypes::LWPOLYLINE)[0]
+        .vertices
+        .rows()
+        .into_iter()
+        .map(|v| (v[0], v[1]))
+        .collect()
+}
+
+fn points(e: &editor::Editor) -> Vec<(f32, f32)> {
+    get_entities(e, EntityTypes::POINT)
+        .iter()
+        .map(|p| (p.vertices[(0, 0)], p.vertices[(0, 1)]))
+        .collect()
+}
+
+#[test]
+fn test_rules_find_points_by_label_and_index() {
+    let mut e = load();
+    let report = e.set_grade_rules(RULES.to_string());
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(e.get_grade_sizes(), vec!["S", "M", "L"]);
+    assert_eq!(e.get_active_size().as_deref(), Some("M"));
+    let grading = e.get_pattern().get_grading().unwrap();
+    assert_eq!(grading.get_graded_point_indexes().len(), 3);
+    assert_eq!(e.get_pattern().get_graded_block_names(), vec!["P"]);
+}
+
+#[test]
+fn test_sizes_move_points_and_attached_vertices() {
+    let mut e = load();
+    e.set_grade_rules(RULES.to_string());
+
+    assert!(e.set_active_size("L".to_string()));
+    assert_eq!(
+        outline(&e),
+        vec![(0., -1.), (11., 0.), (11., 11.), (0., 10.)]
+    );
+    assert_eq!(points(&e), vec![(11., 0.), (11., 11.), (0., -1.)]);
+    assert_eq!(
+        *e.get_pattern().get_blocks()[0].get_bounding_box(),
+        ((0., 11.), (-1., 11.))
+    );
+
+    assert!(e.set_active_size("S".to_string()));
+    assert_eq!(outline(&e), vec![(0., 0.), (9., 0.), (9., 9.), (0., 10.)]);
+
+    assert!(e.set_active_size("M".to_string()));
+    assert_eq!(
+        outline(&e),
+        vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.)]
+    );
+    assert!(!e.set_active_size("XL".to_string()));
+    assert_eq!(e.get_active_size().as_deref(), Some("M"));
+}
+
+#[test]
+fn test_size_changes_are_undone() {
+    let mut e = load();
+    e.set_grade_rules(RULES.to_string());
+    e.set_active_size("L".to_string());
+    e.set_active_size("S".to_string());
+
+    assert!(e.undo());
+    assert_eq!(e.get_active_size().as_deref(), Some("L"));
+    assert_eq!(outline(&e)[1], (11., 0.));
+    assert!(e.undo());
+    assert_eq!(e.get_active_size().as_deref(), Some("M"));
+    assert_eq!(outline(&e)[1], (10., 0.));
+    assert!(e.redo());
+    assert_eq!(e.get_active_size().as_deref(), Some("L"));
+
+    // New rules start from the base size with a fresh history
+    e.set_grade_rules(RULES.to_string());
+    assert_eq!(e.get_active_size().as_deref(), Some("M"));
+    assert_eq!(outline(&e)[1], (10., 0.));
+    assert!(!e.can_undo());
+
+    e.set_active_size("L".to_string());
+    e.clear_grade_rules();
+    assert_eq!(e.get_active_size(), None);
+    assert_eq!(outline(&e)[1], (10., 0.));
+    assert!(e.get_grade_sizes().is_empty());
+}
+
+#[test]
+fn test_report_lists_rules_that_do_not_apply() {
+    let mut e = load();
+    let report = e.set_grade_rules(
+        r##"{"base_size": "M", "sizes": ["M", "L"], "rules": [
+            {"point": "#1", "moves": {"L": [1, 0], "XL": [2, 0]}},
+            {"point": "#9", "moves": {"L": [1, 0]}}
+        ]}"##
+            .to_string(),
+    );
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].kind, LoadIssueKind::UnknownGradePoint);
+    assert_eq!(report.errors[0].path, "/rules/1/point");
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].kind, LoadIssueKind::InvalidGradeRule);
+    assert_eq!(report.warnings[0].path, "/rules/0/moves/XL");
+    // The rules that could be read are used
+    assert!(e.set_active_size("L".to_string()));
+    assert_eq!(outline(&e)[1], (11., 0.));
+
+    let report =
+        e.set_grade_rules(r#"{"base_size": "XS", "sizes": ["M"], "rules": []}"#.to_string());
+    assert_eq!(report.errors[0].path, "/base_size");
+    assert!(e.get_active_size().is_none());
+    assert_eq!(outline(&e)[1], (10., 0.));
+
+    let report = e.set_grade_rules("{".to_string());
+    assert_eq!(report.errors[0].kind, LoadIssueKind::InvalidDocument);
+}
+
+#[test]
+fn test_overlay_draws_every_other_size() {
+    let mut e = load();
+    e.set_grade_rules(RULES.to_string());
+    e.update_draw_sequence();
+    let plain_len = e.get_buffers().0.len();
+
+    e.set_grade_overlay(true);
+    e.update_draw_sequence();
+    let (vertices, _) = e.get_buffers();
+    // The outline and the points that move, two in the small size and three in the large
+    let nest_vertices = (4 + 2 * 4) + (4 + 3 * 4);
+    assert_eq!(vertices.len(), plain_len + nest_vertices * 3);
+    let overlay_color = color::rbga_to_float(&ISettings::default().grade_overlay_color);
+    let drawn_in_overlay: Vec<(f32, f32)> = vertices
+        .chunks(3)
+        .filter(|v| v[2].to_bits() == overlay_color.to_bits())
+        .map(|v| (v[0], v[1]))
+        .collect();
+    assert_eq!(drawn_in_overlay.len(), nest_vertices);
+    // The large outline, placed by the insert
+    assert!(drawn_in_overlay.contains(&(16., 16.)));
+    assert!(drawn_in_overlay.contains(&(5., 4.)));
+
+    e.set_grade_overlay(false);
+    e.update_draw_sequence();
+    assert_eq!(e.get_buffers().0.len(), plain_len);
+}
+
+#[test]
+fn test_example_points_are_found_by_label() {
+    let json = fs::read_to_string("../example_input/shirt.json").unwrap();
+    let mut e = editor::Editor::new(json, serde_json::to_string(&ISettings::default()).unwrap());
+    let report = e.set_grade_rules(
+        r##"{"base_size": "M", "sizes": ["M", "L"], "rules": [
+            {"point": "#3", "moves": {"L": [0.5, 0.25]}}
+        ]}"##
+            .to_string(),
+    );
+    assert!(report.is_clean());
+    let entities_of = |e: &editor::Editor| -> Vec<Entity> {
+        e.get_pattern()
+            .get_blocks()
+            .iter()
+            .find(|b| b.name == "L-1")
+            .unwrap()
+            .get_entities()
+            .clone()
+    };
+    let before = entities_of(&e);
+    e.set_active_size("L".to_string());
+    let after = entities_of(&e);
+
+    // The point, its label and the first vertex of the piece outline move
+    let outline_before = before.iter().find(|x| x.layer == 1 && x.shape).unwrap();
+    let outline_after = after.iter().find(|x| x.layer == 1 && x.shape).unwrap();
+    let moved = (
+        outline_after.vertices[(0, 0)] - outline_before.vertices[(0, 0)],
+        outline_after.vertices[(0, 1)] - outline_before.vertices[(0, 1)],
+    );
+    assert!((moved.0 - 0.5).abs() < 1e-4 && (moved.1 - 0.25).abs() < 1e-4);
+    assert_eq!(
+        outline_after.vertices.row(1),
+        outline_before.vertices.row(1)
+    );
+    let changed = before
+        .iter()
+        .zip(after.iter())
+        .filter(|(a, b)| a != b)
+        .count();
+    assert_eq!(changed, 3);
+}
@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn load_example_editor() -> editor::Editor {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+    editor::Editor::new(json_payload, settings_payload)
+}
+
+fn offset_block(h: &mut editor::Editor, block: &str, x: f32, y: f32) {
+    h.reset_selection();
+    h.highlight_block(block.to_string(), true);
+    h.set_highlight_offset(x, y);
+    h.offset_highlights();
+}
+
+#[test]
+fn test_undo_and_redo_offset() {
+    let mut h = load_example_editor();
+    let original = h.to_json();
+    assert!(!h.can_undo());
+
+    offset_block(&mut h, "L-1", 3.5, -1.25);
+    let moved = h.to_json();
+
+    assert!(h.can_undo());
+    assert!(h.undo());
+    assert_eq!(h.to_json(), original);
+    assert!(!h.can_undo());
+    assert!(h.can_redo());
+
+    assert!(h.redo());
+    assert_eq!(h.to_json(), moved);
+    assert!(!h.redo());
+}
+
+#[test]
+fn test_undo_rotation_restores_exact_vertices() {
+    let mut h = load_example_editor();
+    let original = h.to_json();
+
+    h.highlight_block("L-2".to_string(), true);
+    for _ in 0..10 {
+        h.set_highlight_rotation_center(-50.3, 70.1);
+        h.set_highlight_rotation_angle(0.377);
+        h.rotate_highlights_bbox();
+    }
+    assert_ne!(h.to_json(), original);
+
+    while h.undo() {}
+
+    assert_eq!(h.to_json(), original);
+}
+
+#[test]
+fn test_undo_scale_in_block_view() {
+    let mut h = load_example_editor();
+    let original = h.to_json();
+
+    h.set_view("Block=>L-3".to_string());
+    h.highlight_block("L-3".to_string(), true);
+    h.set_highlight_scale(1.7, 0.3);
+    h.set_highlight_anchor(2., 2.);
+    h.scale_highlights();
+    let scaled = h.to_json();
+
+    assert!(h.undo());
+    assert_eq!(h.to_json(), original);
+    assert!(h.redo());
+    assert_eq!(h.to_json(), scaled);
+}
+
+#[test]
+fn test_new_edit_clears_redo() {
+    let mut h = load_example_editor();
+
+    offset_block(&mut h, "L-1", 1., 1.);
+    h.undo();
+    assert!(h.can_redo());
+
+    offset_block(&mut h, "L-2", 1., 1.);
+    assert!(!h.can_redo());
+}
+
+#[test]
+fn test_history_depth() {
+    let mut h = load_example_editor();
+    h.set_history_depth(2);
+
+    for i in 0..5 {
+        offset_block(&mut h, "L-1", i as f32, 0.);
+    }
+
+    assert!(h.undo());
+    assert!(h.undo());
+    assert!(!h.undo());
+
+    h.set_history_depth(0);
+    offset_block(&mut h, "L-1", 1., 0.);
+    assert!(!h.can_undo());
+}
+
+#[test]
+fn test_empty_edit_is_not_recorded() {
+    let mut h = load_example_editor();
+
+    h.set_highlight_offset(1., 1.);
+    h.offset_highlights();
+
+    assert!(!h.can_undo());
+}
+
+#[test]
+fn test_edits_store_only_what_changed() {
+    let mut h = load_example_editor();
+
+    // Moving a placement changes its insert and none of the entities
+    h.highlight_block("L-1".to_string(), true);
+    let before = h.get_pattern().snapshot_highlighted();
+    h.set_highlight_offset(2., 0.);
+    h.offset_highlights();
+    let command = h.get_pattern().get_changes(&before);
+    assert_eq!(command.inserts.len(), 1);
+    assert!(command.entities.is_empty());
+
+    // Editing a point changes the point and the label that follows it
+    let point = h
+        .get_pattern()
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_entities().iter())
+        .find(|e| h.get_point_label(e.entity_index_as_uuid()).is_some())
+        .unwrap()
+        .entity_index_as_uuid();
+    h.reset_selection();
+    h.highlight_entity(point, true);
+    let before = h.get_pattern().snapshot_highlighted();
+    h.set_highlight_offset(0., 1.);
+    h.offset_highlights();
+    let command = h.get_pattern().get_changes(&before);
+    assert!(command.inserts.is_empty());
+    assert_eq!(command.entities.len(), 2);
+    assert!(command
+        .entities
+        .iter()
+        .all(|c| c.before.is_some() && c.after.is_some()));
+}
+
+#[test]
+fn test_shrinking_depth_keeps_next_redo() {
+    let mut h = load_example_editor();
+    let mut states = vec![h.to_json()];
+    for _ in 0..3 {
+        offset_block(&mut h, "L-1", 1., 0.);
+        states.push(h.to_json());
+    }
+    while h.undo() {}
+    assert_eq!(h.to_json(), states[0]);
+
+    h.set_history_depth(1);
+    assert!(h.redo());
+    assert_eq!(h.to_json(), states[1]);
+    assert!(!h.redo());
+    assert!(h.undo());
+    assert_eq!(h.to_json(), states[0]);
+}
@@ -0,0 +1,218 @@
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, Vertex,
+};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::color;
+
+const FIRST: &str = "00000000-0000-0000-0000-0000000000a1";
+const SECOND: &str = "00000000-0000-0000-0000-0000000000a2";
+
+mod common;
+use common::assert_close;
+
+fn insert(x: f32, insert_index: Option<&str>) -> ParseInsertEntity {
+    ParseInsertEntity {
+        insert_index: insert_index.map(|i| i.to_string()),
+        ..common::insert("A", x, 0.)
+    }
+}
+
+// Block A holds a unit line along x and is placed twice
+fn document(inserts: Vec<ParseInsertEntity>) -> ParseDocument {
+    let block = ParseBlock {
+        entities: vec![ParseEntity {
+            entity_type: "LINE".to_string(),
+            layer: "1".to_string(),
+            entity_index: format!("{:032x}", 1),
+            vertices: Some(vec![
+                Vertex {
+                    x: 0.,
+                    y: 0.,
+                    bulge: None,
+                },
+                Vertex {
+                    x: 1.,
+                    y: 0.,
+                    bulge: None,
+                },
+            ]),
+            ..Default::default()
+        }],
+        centroid: Vertex {
+            x: 0.5,
+            y: 0.,
+            bulge: None,
+        },
+        layer: "1".to_string(),
+        inserts: vec![],
+    };
+    common::document(vec![("A", block)], inserts)
+}
+
+fn two_copies() -> editor::Editor {
+    common::to_editor(&document(vec![
+        insert(0., Some(FIRST)),
+        insert(10., Some(SECOND)),
+    ]))
+}
+
+fn insert_x(h: &editor::Editor, ind: usize) -> f32 {
+    h.get_pattern().get_inserts()[ind].position[(0, 0)]
+}
+
+#[test]
+fn test_insert_indexes_are_kept_or_generated() {
+    let p = pattern::Pattern::from_document(&document(vec![
+        insert(0., Some(FIRST)),
+        insert(10., None),
+        insert(20., Some(FIRST)),
+    ]));
+    let indexes: Vec<String> = p
+        .get_inserts()
+        .iter()
+        .map(|i| i.insert_index_as_uuid())
+        .collect();
+    assert_eq!(indexes[0], FIRST);
+    // Missing and repeated indexes are numbered
+    assert_eq!(indexes[1], "00000000-0000-0000-0000-000000000001");
+    assert_eq!(indexes[2], "00000000-0000-0000-0000-000000000002");
+
+    // Generated indexes are saved so they stay the same after loading again
+    let reloaded = pattern::Pattern::new(p.to_json());
+    assert_eq!(p, reloaded);
+    let from_dxf = pattern::Pattern::new_from_dxf(p.export_dxf());
+    assert_eq!(p.get_inserts(), from_dxf.get_inserts());
+}
+
+#[test]
+fn test_moving_one_copy_leaves_the_other() {
+    let mut h = two_copies();
+    assert!(h.highlight_insert(SECOND.to_string(), true));
+    assert!(!h.highlight_insert("missing".to_string(), true));
+    assert_eq!(h.get_highlighted_inserts(), vec![SECOND]);
+
+    h.set_highlight_offset(5., 0.);
+    h.offset_highlights();
+    assert_close(insert_x(&h, 0), 0.);
+    assert_close(insert_x(&h, 1), 15.);
+    let placements = h.get_pattern().get_block_placements(&"A".to_string());
+    assert_close(placements[1].apply_point(&(0., 0.)).0, 15.);
+
+    // Undo keeps the current selection
+    h.undo();
+    assert_close(insert_x(&h, 1), 10.);
+    assert_eq!(h.get_highlighted_inserts(), vec![SECOND]);
+}
+
+#[test]
+fn test_only_the_selected_copy_is_drawn_highlighted() {
+    let mut h = two_copies();
+    h.update_draw_sequence();
+    assert_eq!(h.get_buffers().0.len() / 3, 4);
+
+    h.highlight_insert(FIRST.to_string(), true);
+    h.set_highlight_offset(0., 3.);
+    h.update_draw_sequence();
+    let highlight = color::rbga_to_float(&h.get_user_settings().highlight_color);
+    let vertices: Vec<&[f32]> = h.get_buffers().0.chunks(3).collect();
+    assert_eq!(vertices.len(), 4);
+    for v in vertices {
+        // The selected copy shows the pending move, the other stays in place
+        if v[2] == highlight {
+            assert_close(v[1], 3.);
+            assert!(v[0] < 2.);
+        } else {
+            assert_close(v[1], 0.);
+            assert!(v[0] >= 10.);
+        }
+    }
+    assert_eq!(h.get_user_settings().highlight_nr_selected_entities, 1);
+}
+
+#[test]
+fn test_rotating_one_copy_changes_its_insert() {
+    let mut h = two_copies();
+    h.highlight_insert(FIRST.to_string(), true);
+    h.set_highlight_rotation_center(0., 0.);
+    h.set_highlight_rotation_angle(std::f32::consts::FRAC_PI_2);
+    // Positive angles turn the selection clockwise
+    let bbox = h.rotate_highlights_bbox().unwrap();
+    assert_close(bbox.0 .0, 0.);
+    assert_close(bbox.1 .0, -1.);
+
+    let p = h.get_pattern();
+    assert_close(p.get_inserts()[0].rotation, -90.);
+    assert_close(p.get_inserts()[1].rotation, 0.);
+    // The block itself is not edited
+    let line = &p.get_blocks()[0].get_entities()[0];
+    assert_close(line.vertices[(1, 0)], 1.);
+    let placements = p.get_block_placements(&"A".to_string());
+    let end = placements[0].apply_point(&(1., 0.));
+    assert_close(end.0, 0.);
+    assert_close(end.1, -1.);
+}
+
+#[test]
+fn test_block_view_edits_change_every_copy() {
+    let mut h = two_copies();
+    h.set_view("Block=>A".to_string());
+    h.highlight_block("A".to_string(), true);
+    h.set_highlight_offset(0., 2.);
+    h.offset_highlights();
+
+    assert_close(insert_x(&h, 0), 0.);
+    assert_close(insert_x(&h, 1), 10.);
+    let p = h.get_pattern();
+    for placement in p.get_block_placements(&"A".to_string()) {
+        let line = &p.get_blocks()[0].get_entities()[0];
+        let start = placement.apply_point(&(line.vertices[(0, 0)], line.vertices[(0, 1)]));
+        assert_close(start.1, 2.);
+    }
+}
+
+#[test]
+fn test_closest_entity_reports_its_insert() {
+    let h = two_copies();
+    let settings = ISettings {
+        point_threshold: 0.1,
+        ..Default::default()
+    };
+    let hit = h
+        .get_pattern()
+        .find_closest_entity(&(10.5, 0.05), &settings)
+        .unwrap();
+    assert_eq!(hit.insert_index, Some(SECOND.to_string()));
+    assert_close(hit.closest.point.0, 10.5);
+}
+
+#[test]
+fn test_shearing_a_rotated_copy_edits_its_block() {
+    let mut rotated = insert(0., Some(FIRST));
+    rotated.rotation = Some(30.);
+    let mut h = common::to_editor(&document(vec![rotated, insert(10., Some(SECOND))]));
+    let end_of_line = |h: &editor::Editor| {
+        let p = h.get_pattern();
+        let line = &p.get_blocks()[0].get_entities()[0];
+        p.get_block_placements(&"A".to_string())[0]
+            .apply_point(&(line.vertices[(1, 0)], line.vertices[(1, 1)]))
+    };
+    let before = end_of_line(&h);
+
+    // Stretching along x shears the turned copy, which an insert can't hold
+    h.highlight_insert(FIRST.to_string(), true);
+    h.set_highlight_anchor(0., 0.);
+    h.set_highlight_scale(2., 1.);
+    h.scale_highlights();
+
+    let after = end_of_line(&h);
+    assert_close(after.0, 2. * before.0);
+    assert_close(after.1, before.1);
+    assert_close(h.get_pattern().get_inserts()[0].rotation, 30.);
+
+    assert!(h.undo());
+    let undone = end_of_line(&h);
+    assert_close(undone.0, before.0);
+    assert_close(undone.1, before.1);
+}
@@ -0,0 +1,223 @@
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, Vertex,
+};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::write_dxf;
+use cad_pattern_editor::write_svg;
+
+mod common;
+use common::{assert_close, document, insert, to_editor};
+
+fn assert_point(p: (f32, f32), x: f32, y: f32) {
+    common::assert_point_close(p, (x, y));
+}
+
+// Block holding a unit line from the origin along x
+fn line_block(id: usize, inserts: Vec<ParseInsertEntity>) -> ParseBlock {
+    ParseBlock {
+        entities: vec![ParseEntity {
+            entity_type: "LINE".to_string(),
+            layer: "1".to_string(),
+            entity_index: format!("{:032x}", id),
+            vertices: Some(vec![
+                Vertex {
+                    x: 0.,
+                    y: 0.,
+                    bulge: None,
+                },
+                Vertex {
+                    x: 1.,
+                    y: 0.,
+                    bulge: None,
+                },
+            ]),
+            ..Default::default()
+        }],
+        centroid: Vertex {
+            x: 0.5,
+            y: 0.,
+            bulge: None,
+        },
+        layer: "1".to_string(),
+        inserts,
+    }
+}
+
+// Block A turned a quarter and doubled in size at (5, 5), holding block B a unit along its own x axis
+fn nested_document() -> ParseDocument {
+    let mut nested = insert("B", 1., 0.);
+    nested.rotation = Some(90.);
+    let mut top = insert("A", 5., 5.);
+    top.rotation = Some(90.);
+    top.scale_x = Some(2.);
+    top.scale_y = Some(2.);
+    document(
+        vec![
+            ("A", line_block(1, vec![nested])),
+            ("B", line_block(2, vec![])),
+        ],
+        vec![top],
+    )
+}
+
+#[test]
+fn test_rotated_and_scaled_insert_placement() {
+    let p = pattern::Pattern::from_document(&nested_document());
+    let placements = p.get_block_placements(&"A".to_string());
+    assert_eq!(placements.len(), 1);
+    assert_point(placements[0].apply_point(&(1., 0.)), 5., 7.);
+
+    // B sits two units up from A's origin and points back along world x
+    let placements = p.get_block_placements(&"B".to_string());
+    assert_eq!(placements.len(), 1);
+    assert_point(placements[0].apply_point(&(0., 0.)), 5., 7.);
+    assert_point(placements[0].apply_point(&(1., 0.)), 3., 7.);
+
+    let settings = ISettings {
+        point_threshold: 0.1,
+        ..Default::default()
+    };
+    let hit = p.find_closest_entity(&(5.05, 6.), &settings).unwrap();
+    assert_eq!(hit.block, "A");
+    assert_point(hit.closest.point, 5., 6.);
+    assert_close(hit.closest.distance, 0.05);
+    let hit = p.find_closest_entity(&(4., 7.05), &settings).unwrap();
+    assert_eq!(hit.block, "B");
+    // The line is not where it would be without the rotation
+    assert!(p.find_closest_entity(&(6., 5.), &settings).is_none());
+
+    let (entities, bbox) = p.find_entities_with_bbox(&((4.5, 5.5), (4.5, 7.5)), &settings, false);
+    assert_eq!(entities, vec!["00000000-0000-0000-0000-000000000001"]);
+    let ((min_x, max_x), (min_y, max_y)) = bbox.unwrap();
+    assert_close(min_x, 5.);
+    assert_close(max_x, 5.);
+    assert_close(min_y, 5.);
+    assert_close(max_y, 7.);
+}
+
+#[test]
+fn test_rotated_inserts_are_drawn_in_place() {
+    let mut h = to_editor(&nested_document());
+    h.update_draw_sequence();
+    let (vertex_buffer, _) = h.get_buffers();
+    let points: Vec<(f32, f32)> = vertex_buffer.chunks(3).map(|v| (v[0], v[1])).collect();
+    assert_eq!(points.len(), 4);
+    for (x, y) in [(5., 5.), (5., 7.), (3., 7.)] {
+        assert!(points
+            .iter()
+            .any(|p| (p.0 - x).abs() < 1e-3 && (p.1 - y).abs() < 1e-3));
+    }
+}
+
+#[test]
+fn test_array_inserts_place_every_copy() {
+    let mut array = insert("A", 1., 1.);
+    array.columns = Some(3);
+    array.rows = Some(2);
+    array.column_spacing = Some(10.);
+    array.row_spacing = Some(20.);
+    let mut h = to_editor(&document(vec![("A", line_block(1, vec![]))], vec![array]));
+
+    let placements = h.get_pattern().get_block_placements(&"A".to_string());
+    assert_eq!(placements.len(), 6);
+    assert_point(placements[5].apply_point(&(0., 0.)), 21., 21.);
+
+    h.update_draw_sequence();
+    assert_eq!(h.get_buffers().0.len() / 3, 12);
+
+    let settings = ISettings {
+        point_threshold: 0.1,
+        ..Default::default()
+    };
+    assert!(h
+        .get_pattern()
+        .find_closest_entity(&(11.5, 21.), &settings)
+        .is_some());
+}
+
+#[test]
+fn test_insert_cycles_are_not_followed() {
+    // A holds B, B holds A and C holds itself
+    let document = document(
+        vec![
+            ("A", line_block(1, vec![insert("B", 1., 0.)])),
+            ("B", line_block(2, vec![insert("A", 1., 0.)])),
+            ("C", line_block(3, vec![insert("C", 1., 0.)])),
+        ],
+        vec![insert("A", 0., 0.)],
+    );
+    let mut h = to_editor(&document);
+    let blocks: Vec<usize> = h
+        .get_pattern()
+        .get_instances()
+        .iter()
+        .map(|i| i.block)
+        .collect();
+    assert_eq!(blocks, vec![0, 1, 2]);
+    h.update_draw_sequence();
+    assert_eq!(h.get_buffers().0.len() / 3, 6);
+}
+
+#[test]
+fn test_moving_a_nested_block_moves_its_insert() {
+    let mut h = to_editor(&nested_document());
+    h.highlight_block("B".to_string(), true);
+    // A is turned a quarter, so moving up in the model moves B along A's x axis
+    h.set_highlight_offset(0., 4.);
+    h.offset_highlights();
+    let a = &h.get_pattern().get_blocks()[0];
+    assert_close(a.get_inserts()[0].position[(0, 0)], 3.);
+    assert_close(a.get_inserts()[0].position[(0, 1)], 0.);
+    let placement = &h.get_pattern().get_block_placements(&"B".to_string())[0];
+    assert_point(placement.apply_point(&(0., 0.)), 5., 11.);
+
+    h.undo();
+    let a = &h.get_pattern().get_blocks()[0];
+    assert_close(a.get_inserts()[0].position[(0, 0)], 1.);
+}
+
+#[test]
+fn test_scaling_a_rotated_block_follows_the_view_axes() {
+    let mut h = to_editor(&nested_document());
+    h.highlight_block("A".to_string(), true);
+    h.set_highlight_anchor(5., 5.);
+    h.set_highlight_scale(1., 3.);
+    h.scale_highlights();
+
+    // The line runs up the model from (5, 5), so the scale stretches it to three times the length
+    let p = h.get_pattern();
+    let placement = &p.get_block_placements(&"A".to_string())[0];
+    let line = &p.get_blocks()[0].get_entities()[0];
+    let end = placement.apply_point(&(line.vertices[(1, 0)], line.vertices[(1, 1)]));
+    assert_point(end, 5., 11.);
+}
+
+#[test]
+fn test_insert_transforms_roundtrip_through_json_dxf_and_svg() {
+    let p = pattern::Pattern::from_document(&nested_document());
+    let json = p.to_json();
+    assert!(json.contains("\"rotation\":90.0"));
+    assert!(!json.contains("columns"));
+    let roundtrip = pattern::Pattern::from_document(&p.to_document());
+    assert_eq!(roundtrip.to_json(), json);
+
+    let document = parse_dxf::parse_dxf(&write_dxf::write_dxf(&p)).unwrap();
+    let top = &document.pattern_json.entities[0];
+    assert_eq!(top.rotation, Some(90.));
+    assert_eq!(top.scale_x, Some(2.));
+    let nested = &document.pattern_json.blocks["A"].inserts;
+    assert_eq!(nested.len(), 1);
+    assert_eq!(nested[0].name, "B");
+    let from_dxf = pattern::Pattern::from_document(&document);
+    assert_point(
+        from_dxf.get_block_placements(&"B".to_string())[0].apply_point(&(1., 0.)),
+        3.,
+        7.,
+    );
+
+    let svg = write_svg::write_svg(&p, &ISettings::default());
+    assert!(svg.contains("<g id=\"A\" transform=\"matrix("));
+    assert!(svg.contains("<g id=\"B\" transform=\"matrix("));
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::load_report::{LoadIssue, LoadIssueKind};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::log::{self, Level};
+
+fn kinds(issues: &[LoadIssue]) -> Vec<LoadIssueKind> {
+    issues.iter().map(|i| i.kind).collect()
+}
+
+const DOCUMENT: &str = r#"{"pattern_json": {
+    "blocks": {
+        "L-1": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000001",
+             "vertices": [{"x": 0, "y": 0}, {"x": 1, "y": 0}]},
+            {"entity_type": "LINE", "layer": "one", "entity_index": "00000000000000000000000000000002",
+             "vertices": [{"x": 0, "y": 0}, {"x": 1, "y": 0}]},
+            {"entity_type": "LINE", "layer": "1", "entity_index": "short",
+             "vertices": [{"x": 0, "y": 0}, {"x": 1, "y": 0}]},
+            {"entity_type": "HATCH", "layer": "1", "entity_index": "00000000000000000000000000000004"},
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000005",
+             "vertices": [{"x": 0, "y": 0}]},
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000006",
+             "vertices": [{"x": 0, "y": 0, "bulge": 1}, {"x": 1, "y": 0}]}
+        ]},
+        "a/b": {"layer": "x", "centroid": {"x": 0, "y": 0}, "entities": []}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "L-1", "layer": "1", "position": {"x": 0, "y": 0},
+         "insert_index": "000000000000000000000000000000a1"},
+        {"entity_type": "INSERT", "name": "L-1", "layer": "1", "position": {"x": 5, "y": 0},
+         "insert_index": "000000000000000000000000000000a1"},
+        {"entity_type": "INSERT", "name": "missing", "layer": "1", "position": {"x": 0, "y": 0}}
+    ]
+}}"#;
+
+#[test]
+fn test_unreadable_document_fails() {
+    let report = pattern::Pattern::try_new("{\"pattern_json\": ").unwrap_err();
+    assert_eq!(kinds(&report.errors), vec![LoadIssueKind::InvalidDocument]);
+    assert_eq!(report.errors[0].path, "");
+    assert!(report.errors[0].reason.starts_with("Invalid JSON"));
+
+    let report = pattern::Pattern::try_from_dxf("not a dxf").unwrap_err();
+    assert_eq!(kinds(&report.errors), vec![LoadIssueKind::InvalidDocument]);
+}
+
+#[test]
+fn test_report_lists_what_was_left_out() {
+    let (p, report) = pattern::Pattern::try_new(DOCUMENT).unwrap();
+    // The line and the polyline are loaded, the second insert keeps its place with a new index
+    assert_eq!(p.get_number_entities(), 2);
+    assert_eq!(p.get_number_inserts(), 2);
+    assert_eq!(p.get_number_blocks(), 1);
+
+    assert_eq!(
+        kinds(&report.errors),
+        vec![
+            LoadIssueKind::UnknownBlock,
+            LoadIssueKind::InvalidLayer,
+            LoadIssueKind::InvalidEntityIndex,
+            LoadIssueKind::UnknownEntityType,
+            LoadIssueKind::InvalidGeometry,
+            LoadIssueKind::InvalidLayer,
+        ]
+    );
+    assert_eq!(report.errors[0].path, "/pattern_json/entities/2");
+    assert_eq!(report.errors[0].block_key, None);
+
+    let layer = &report.errors[1];
+    assert_eq!(layer.path, "/pattern_json/blocks/L-1/entities/1/layer");
+    assert_eq!(layer.block_key.as_deref(), Some("L-1"));
+    assert_eq!(
+        layer.entity_index.as_deref(),
+        Some("00000000000000000000000000000002")
+    );
+    assert_eq!(report.errors[4].path, "/pattern_json/blocks/L-1/entities/4");
+    // Block keys are escaped in the path
+    assert_eq!(report.errors[5].path, "/pattern_json/blocks/a~1b/layer");
+    assert_eq!(report.errors[5].block_key.as_deref(), Some("a/b"));
+
+    assert_eq!(
+        kinds(&report.warnings),
+        vec![LoadIssueKind::DuplicateInsertIndex]
+    );
+    assert_eq!(report.warnings[0].path, "/pattern_json/entities/1");
+    assert_eq!(report.get_messages().len(), 7);
+}
+
+#[test]
+fn test_example_file_loads_cleanly() {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+
+    let (p, report) = pattern::Pattern::try_new(&json_payload).unwrap();
+    assert!(report.is_clean());
+    assert_eq!(p.get_number_entities(), 305);
+
+    let h = editor::Editor::new(
+        json_payload,
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    );
+    assert!(h.get_report().is_clean());
+}
+
+#[test]
+fn test_load_problems_are_logged_and_kept_by_the_editor() {
+    let messages: Arc<Mutex<Vec<(Level, String)>>> = Arc::new(Mutex::new(vec![]));
+    let sink = messages.clone();
+    log::set_logger(Box::new(move |level, message| {
+        sink.lock().unwrap().push((level, message.to_string()));
+    }));
+    let e = editor::Editor::new(
+        DOCUMENT.to_string(),
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    );
+    log::clear_logger();
+
+    assert_eq!(e.get_report().errors.len(), 6);
+    assert_eq!(e.get_pattern().get_number_entities(), 2);
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.iter().filter(|m| m.0 == Level::Error).count(), 6);
+    assert!(messages.contains(&(
+        Level::Warning,
+        "Insert index used twice at /pattern_json/entities/1".to_string()
+    )));
+}
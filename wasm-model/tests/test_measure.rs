@@ -0,0 +1,241 @@
+use std::f32::consts::PI;
+use std::fs;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::measure::PickSnap;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::color;
+
+mod common;
+use common::assert_within;
+
+// Curves are measured along their tessellation, so lengths and areas are only this close
+const MEASURE_TOLERANCE: f32 = 1e-3;
+
+const SQUARE: &str = "00000000000000000000000000000001";
+const TRIANGLE: &str = "00000000000000000000000000000002";
+const OPEN: &str = "00000000000000000000000000000003";
+const BULGED: &str = "00000000000000000000000000000004";
+const CIRCLE: &str = "00000000000000000000000000000005";
+const POINT: &str = "00000000000000000000000000000006";
+const SCALED_SQUARE: &str = "00000000000000000000000000000007";
+
+const DOCUMENT: &str = r##"{"pattern_json": {
+    "blocks": {
+        "P": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000001",
+             "shape": true, "vertices": [{"x": 0, "y": 0}, {"x": 10, "y": 0}, {"x": 10, "y": 10}, {"x": 0, "y": 10}]},
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000002",
+             "shape": true, "vertices": [{"x": 0, "y": 0}, {"x": 0, "y": 4}, {"x": 3, "y": 0}]},
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000003",
+             "shape": false, "vertices": [{"x": 0, "y": 0}, {"x": 3, "y": 4}, {"x": 3, "y": 10}]},
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000004",
+             "shape": false, "vertices": [{"x": 0, "y": 0, "bulge": 1}, {"x": 2, "y": 0}]},
+            {"entity_type": "CIRCLE", "layer": "1", "entity_index": "00000000000000000000000000000005",
+             "center": {"x": 20, "y": 20}, "radius": 2},
+            {"entity_type": "POINT", "layer": "2", "entity_index": "00000000000000000000000000000006",
+             "position": {"x": 1, "y": 1}}
+        ]},
+        "Q": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "00000000000000000000000000000007",
+             "shape": true, "vertices": [{"x": 0, "y": 0}, {"x": 2, "y": 0}, {"x": 2, "y": 2}, {"x": 0, "y": 2}]}
+        ]}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "P", "layer": "1", "position": {"x": 5, "y": 5}},
+        {"entity_type": "INSERT", "name": "Q", "layer": "1", "position": {"x": 50, "y": 0},
+         "scale_x": 2, "scale_y": 3}
+    ]
+}}"##;
+
+fn load() -> editor::Editor {
+    editor::Editor::new(
+        DOCUMENT.to_string(),
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    )
+}
+
+#[test]
+fn test_closed_polylines_have_signed_area_and_perimeter() {
+    let e = load();
+    let square = e.measure_entity(SQUARE.to_string()).unwrap();
+    assert!(square.closed);
+    assert_eq!(square.area, Some(100.));
+    assert_eq!(square.perimeter, Some(40.));
+    assert_eq!(square.length, None);
+    assert_eq!(square.segment_lengths, vec![10., 10., 10., 10.]);
+    // Measured where the insert places the block
+    assert_eq!(square.centroid, Some((10., 10.)));
+
+    // Clockwise outlines have a negative area
+    let triangle = e.measure_entity(TRIANGLE.to_string()).unwrap();
+    assert_eq!(triangle.area, Some(-6.));
+    assert_eq!(triangle.segment_lengths, vec![4., 5., 3.]);
+    assert_eq!(triangle.perimeter, Some(12.));
+    let (c_x, c_y) = triangle.centroid.unwrap();
+    assert_within(c_x, 6., MEASURE_TOLERANCE);
+    assert_within(c_y, 5. + 4. / 3., MEASURE_TOLERANCE);
+}
+
+#[test]
+fn test_open_entities_and_curves_have_length() {
+    let e = load();
+    let open = e.measure_entity(OPEN.to_string()).unwrap();
+    assert!(!open.closed);
+    assert_eq!(open.length, Some(11.));
+    assert_eq!(open.segment_lengths, vec![5., 6.]);
+    assert_eq!(
+        (open.area, open.perimeter, open.centroid),
+        (None, None, None)
+    );
+
+    // A half circle bulge is one segment
+    let bulged = e.measure_entity(BULGED.to_string()).unwrap();
+    assert_eq!(bulged.segment_lengths.len(), 1);
+    assert_within(bulged.length.unwrap(), PI, MEASURE_TOLERANCE);
+
+    let circle = e.measure_entity(CIRCLE.to_string()).unwrap();
+    assert_within(circle.area.unwrap(), 4. * PI, MEASURE_TOLERANCE);
+    assert_within(circle.perimeter.unwrap(), 4. * PI, MEASURE_TOLERANCE);
+    let (c_x, c_y) = circle.centroid.unwrap();
+    assert_within(c_x, 25., MEASURE_TOLERANCE);
+    assert_within(c_y, 25., MEASURE_TOLERANCE);
+
+    assert!(e.measure_entity(POINT.to_string()).is_none());
+    assert!(e.measure_entity("unknown".to_string()).is_none());
+}
+
+#[test]
+fn test_block_outline_is_the_largest_closed_entity() {
+    let e = load();
+    let block = e.measure_block("P".to_string()).unwrap();
+    assert_eq!(block.name, "P");
+    assert_eq!(block.entities.len(), 5);
+    let outline = block.outline.unwrap();
+    assert_eq!(outline.entity_index.replace('-', ""), SQUARE);
+    assert!(e.measure_block("missing".to_string()).is_none());
+}
+
+#[test]
+fn test_measurements_follow_scale_edits() {
+    let mut e = load();
+    // The insert of Q draws it three times as tall and twice as wide
+    let scaled = e.measure_entity(SCALED_SQUARE.to_string()).unwrap();
+    assert_within(scaled.area.unwrap(), 24., MEASURE_TOLERANCE);
+    assert_within(scaled.perimeter.unwrap(), 20., MEASURE_TOLERANCE);
+    assert_eq!(scaled.segment_lengths, vec![4., 6., 4., 6.]);
+
+    e.highlight_block("P".to_string(), true);
+    e.set_highlight_anchor(5., 5.);
+    e.set_highlight_scale(2., 0.5);
+    e.scale_highlights();
+    let square = e.measure_entity(SQUARE.to_string()).unwrap();
+    assert_within(square.area.unwrap(), 100., MEASURE_TOLERANCE);
+    assert_within(square.perimeter.unwrap(), 50., MEASURE_TOLERANCE);
+    let (c_x, c_y) = square.centroid.unwrap();
+    assert_within(c_x, 15., MEASURE_TOLERANCE);
+    assert_within(c_y, 7.5, MEASURE_TOLERANCE);
+}
+
+#[test]
+fn test_example_pieces_are_measured() {
+    let json = fs::read_to_string("../example_input/shirt.json").unwrap();
+    let e = editor::Editor::new(json, serde_json::to_string(&ISettings::default()).unwrap());
+    for name in e.get_all_block_names() {
+        let block = e.measure_block(name.clone()).unwrap();
+        let outline = block.outline.unwrap();
+        assert!(outline.area.unwrap().abs() > 100., "{}", name);
+        let perimeter = outline.perimeter.unwrap();
+        let segment_total: f32 = outline.segment_lengths.iter().sum();
+        assert_within(perimeter, segment_total, MEASURE_TOLERANCE);
+    }
+}
+
+fn load_with_threshold(threshold: f32) -> editor::Editor {
+    let settings = ISettings {
+        point_threshold: threshold,
+        ..ISettings::default()
+    };
+    editor::Editor::new(
+        DOCUMENT.to_string(),
+        serde_json::to_string(&settings).unwrap(),
+    )
+}
+
+#[test]
+fn test_picks_snap_to_vertices_midpoints_and_entities() {
+    let mut e = load_with_threshold(1.);
+    let m = e
+        .measure_distance(vec![5.3, 5.2], vec![15.2, 10.3])
+        .unwrap();
+    assert_eq!(m.start.point, (5., 5.));
+    assert_eq!(m.start.snap, PickSnap::Vertex);
+    // The middle of the right side of the square
+    assert_eq!(m.end.point, (15., 10.));
+    assert_eq!(m.end.snap, PickSnap::Midpoint);
+    assert_eq!(
+        m.end.entity_index.as_deref().map(|i| i.replace('-', "")),
+        Some(SQUARE.to_string())
+    );
+    assert_eq!((m.dx, m.dy), (10., 5.));
+    assert_within(m.distance, 125f32.sqrt(), MEASURE_TOLERANCE);
+    assert_within(m.angle, 26.565, MEASURE_TOLERANCE);
+
+    let m = e
+        .measure_distance(vec![15.3, 12.7], vec![100., 100.])
+        .unwrap();
+    assert_eq!(m.start.snap, PickSnap::OnEntity);
+    assert_within(m.start.point.0, 15., MEASURE_TOLERANCE);
+    assert_within(m.start.point.1, 12.7, MEASURE_TOLERANCE);
+    assert_eq!(m.end.snap, PickSnap::Free);
+    assert_eq!(m.end.point, (100., 100.));
+    assert_eq!(m.end.entity_index, None);
+    assert!(m.angle > 0. && m.angle < 90.);
+
+    assert!(e.measure_distance(vec![1.], vec![2., 2.]).is_none());
+}
+
+#[test]
+fn test_picks_snap_to_scaled_placements() {
+    let mut e = load_with_threshold(1.);
+    // Q is placed at (50, 0) twice as wide and three times as tall
+    let m = e
+        .measure_distance(vec![54.1, 3.2], vec![49.8, 0.1])
+        .unwrap();
+    assert_eq!(m.start.snap, PickSnap::Midpoint);
+    assert_within(m.start.point.0, 54., MEASURE_TOLERANCE);
+    assert_within(m.start.point.1, 3., MEASURE_TOLERANCE);
+    assert_eq!(m.end.point, (50., 0.));
+    assert_within(
+        m.angle,
+        -180. + 3f32.atan2(4.).to_degrees(),
+        MEASURE_TOLERANCE,
+    );
+}
+
+#[test]
+fn test_measurement_is_drawn_until_cleared() {
+    let mut e = load_with_threshold(1.);
+    e.update_draw_sequence();
+    let plain = e.get_buffers().0.clone();
+
+    e.measure_distance(vec![5., 5.], vec![15., 15.]);
+    e.update_draw_sequence();
+    let overlay_color = color::rbga_to_float(&ISettings::default().overlay_color);
+    let overlay: Vec<(f32, f32)> = e
+        .get_buffers()
+        .0
+        .chunks(3)
+        .filter(|v| v[2].to_bits() == overlay_color.to_bits())
+        .map(|v| (v[0], v[1]))
+        .collect();
+    // The line and a cross at each end
+    assert_eq!(overlay.len(), 2 + 2 * 4);
+    assert!(overlay.contains(&(5., 5.)) && overlay.contains(&(15., 15.)));
+    // The blocks are drawn before the overlay and do not change
+    assert_eq!(e.get_buffers().0[..plain.len()], plain[..]);
+
+    e.clear_measurement();
+    e.update_draw_sequence();
+    assert_eq!(*e.get_buffers().0, plain);
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+
+#[test]
+fn test_parse_dxf_from_example_file() {
+    let path = Path::new("../example_input/shirt.dxf");
+
+    let dxf_payload: String = fs::read_to_string(path).expect("Unable to read DXF file");
+
+    let document = parse_dxf::parse_dxf(&dxf_payload).expect("Unable to parse DXF file");
+
+    assert_eq!(document.pattern_json.blocks.len(), 3);
+    assert_eq!(document.pattern_json.entities.len(), 3);
+}
+
+#[test]
+fn test_create_pattern_object_dxf_example_file() {
+    let path = Path::new("../example_input/shirt.dxf");
+
+    let dxf_payload: String = fs::read_to_string(path).expect("Unable to read DXF file");
+
+    let p = pattern::Pattern::new_from_dxf(dxf_payload);
+
+    assert_eq!(p.get_number_blocks(), 3);
+    assert_eq!(p.get_number_inserts(), 3);
+    assert_eq!(p.get_number_entities(), 305);
+}
+
+#[test]
+fn test_dxf_matches_json_example_file() {
+    let json_payload = fs::read_to_string(Path::new("../example_input/shirt.json"))
+        .expect("Unable to read JSON file");
+    let dxf_payload = fs::read_to_string(Path::new("../example_input/shirt.dxf"))
+        .expect("Unable to read DXF file");
+
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+
+    let from_json = editor::Editor::new(json_payload, settings_payload.clone());
+    let from_dxf = editor::Editor::new_from_dxf(dxf_payload, settings_payload);
+
+    assert_eq!(from_json.get_all_layers(), from_dxf.get_all_layers());
+    assert_eq!(
+        from_json.get_all_block_names(),
+        from_dxf.get_all_block_names()
+    );
+    assert_eq!(
+        from_json.get_number_entities(),
+        from_dxf.get_number_entities()
+    );
+}
+
+#[test]
+fn test_parse_dxf_polyline_and_model_space() {
+    let dxf_payload = [
+        "0", "SECTION", "2", "HEADER", "9", "$ACADVER", "1", "AC1009", "0", "ENDSEC", "0",
+        "SECTION", "2", "ENTITIES", "0", "POLYLINE", "8", "1", "66", "1", "70", "1", "0", "VERTEX",
+        "8", "1", "10", "0.0", "20", "0.0", "0", "VERTEX", "8", "1", "10", "1.0", "20", "0.0", "0",
+        "VERTEX", "8", "1", "10", "1.0", "20", "1.0", "0", "SEQEND", "8", "1", "0", "POINT", "5",
+        "2A", "8", "2", "10", "0.5", "20", "0.5", "0", "ENDSEC", "0", "EOF",
+    ]
+    .join("\n");
+
+    let document = parse_dxf::parse_dxf(&dxf_payload).expect("Unable to parse DXF");
+    let block = &document.pattern_json.blocks[parse_dxf::MODEL_SPACE_BLOCK];
+
+    assert_eq!(block.entities.len(), 2);
+    assert_eq!(block.entities[0].vertices.as_ref().unwrap().len(), 3);
+    assert_eq!(block.entities[0].shape, Some(true));
+    assert_eq!(block.entities[1].entity_index, format!("{:0>32}", "2a"));
+    assert_eq!(document.pattern_json.entities.len(), 1);
+}
+
+#[test]
+fn test_parse_dxf_rejects_unsupported_version() {
+    let dxf_payload = [
+        "0", "SECTION", "2", "HEADER", "9", "$ACADVER", "1", "AC1006", "0", "ENDSEC", "0",
+        "SECTION", "2", "ENTITIES", "0", "ENDSEC", "0", "EOF",
+    ]
+    .join("\n");
+
+    assert!(parse_dxf::parse_dxf(&dxf_payload).is_err());
+}
+
+#[test]
+fn test_parse_dxf_named_layers() {
+    let dxf_payload = [
+        "0", "SECTION", "2", "TABLES", "0", "TABLE", "2", "LAYER", "0", "LAYER", "2", "0", "0",
+        "LAYER", "2", "CUT", "0", "LAYER", "2", "GRAIN", "0", "LAYER", "2", "7", "0", "ENDTAB",
+        "0", "ENDSEC", "0", "SECTION", "2", "ENTITIES", "0", "LINE", "8", "CUT", "10", "0.0", "20",
+        "0.0", "11", "1.0", "21", "0.0", "0", "LINE", "8", "GRAIN", "10", "0.0", "20", "1.0", "11",
+        "1.0", "21", "1.0", "0", "POINT", "8", "7", "10", "0.5", "20", "0.5", "0", "POINT", "8",
+        "NOTCH", "10", "1.0", "20", "0.5", "0", "ENDSEC", "0", "EOF",
+    ]
+    .join("\n");
+
+    let document = parse_dxf::parse_dxf(&dxf_payload).expect("Unable to parse DXF");
+    let block = &document.pattern_json.blocks[parse_dxf::MODEL_SPACE_BLOCK];
+    let layers: Vec<&str> = block.entities.iter().map(|e| e.layer.as_str()).collect();
+    // Numbered layers keep their number, named ones follow the largest in the order they appear
+    assert_eq!(layers, vec!["8", "9", "7", "10"]);
+    assert_eq!(document.pattern_json.layer_names[&8], "CUT");
+    assert_eq!(document.pattern_json.layer_names[&10], "NOTCH");
+    assert!(!document.pattern_json.layer_names.contains_key(&7));
+
+    let p = pattern::Pattern::new_from_dxf(dxf_payload);
+    assert_eq!(p.get_number_entities(), 4);
+    assert_eq!(p.get_all_layers(), vec![0, 7, 8, 9, 10]);
+
+    // Names are written back to DXF and kept in json
+    let exported = p.export_dxf();
+    assert!(exported.contains("\n  8\nCUT\n"));
+    assert!(exported.contains("\n  2\nGRAIN\n"));
+    let reloaded = pattern::Pattern::new_from_dxf(exported);
+    assert_eq!(reloaded.get_layer_names(), p.get_layer_names());
+    assert_eq!(reloaded.get_all_layers(), p.get_all_layers());
+    let from_json = pattern::Pattern::new(p.to_json());
+    assert_eq!(from_json.get_layer_names(), p.get_layer_names());
+}
@@ -1,8 +1,10 @@
 use std::fs;
 use std::path::Path;
 
+use cad_pattern_editor::editor;
 use cad_pattern_editor::parse_pattern;
 use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
 
 #[test]
 fn test_parse_json_from_example_file() {
@@ -27,3 +29,42 @@ fn test_create_pattern_object_example_file() {
     assert_eq!(p.get_number_inserts(), 3);
     assert_eq!(p.get_number_entities(), 305);
 }
+
+#[test]
+fn test_save_and_reload_example_file() {
+    let path = Path::new("../example_input/shirt.json");
+
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+
+    let p = pattern::Pattern::new(json_payload);
+    let saved = p.to_json();
+    let reloaded = pattern::Pattern::new(saved.clone());
+
+    assert_eq!(reloaded.get_number_entities(), 305);
+    assert_eq!(p, reloaded);
+    assert_eq!(saved, reloaded.to_json());
+}
+
+#[test]
+fn test_saved_json_keeps_edits() {
+    let path = Path::new("../example_input/shirt.json");
+
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+
+    let mut h = editor::Editor::new(json_payload, settings_payload.clone());
+    h.set_view("Block=>L-3".to_string());
+    h.highlight_block("L-3".to_string(), true);
+    h.set_highlight_scale(2., 0.5);
+    h.set_highlight_anchor(1., 1.);
+    h.scale_highlights();
+
+    let saved = h.to_json();
+    let reloaded = editor::Editor::new(saved.clone(), settings_payload);
+
+    assert_eq!(saved, reloaded.to_json());
+    assert_ne!(
+        saved,
+        pattern::Pattern::new(fs::read_to_string(path).unwrap()).to_json()
+    );
+}
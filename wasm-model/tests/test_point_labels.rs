@@ -0,0 +1,135 @@
+use std::fs;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::{Entity, EntityTypes};
+use cad_pattern_editor::user_settings::ISettings;
+
+const POINT_A: &str = "00000000000000000000000000000001";
+const POINT_B: &str = "00000000000000000000000000000002";
+const LABEL_A: &str = "00000000000000000000000000000003";
+const LINE: &str = "00000000000000000000000000000007";
+
+const DOCUMENT: &str = r##"{"pattern_json": {
+    "blocks": {
+        "P": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "POINT", "layer": "2", "entity_index": "00000000000000000000000000000001",
+             "position": {"x": 0, "y": 0}},
+            {"entity_type": "POINT", "layer": "2", "entity_index": "00000000000000000000000000000002",
+             "position": {"x": 5, "y": 5}},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000003",
+             "start_point": {"x": 0, "y": 0}, "text_height": 0.4, "text": "#1"},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000004",
+             "start_point": {"x": 5.0005, "y": 5}, "text_height": 0.4, "text": "near"},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000005",
+             "start_point": {"x": 5, "y": 5}, "text_height": 0.4, "text": "#2"},
+            {"entity_type": "TEXT", "layer": "2", "entity_index": "00000000000000000000000000000006",
+             "start_point": {"x": 20, "y": 20}, "text_height": 0.4, "text": "#9"},
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000007",
+             "start": {"x": 0, "y": 0}, "end": {"x": 5, "y": 5}}
+        ]}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "P", "layer": "1", "position": {"x": 0, "y": 0}}
+    ]
+}}"##;
+
+fn load(json: String) -> editor::Editor {
+    editor::Editor::new(json, serde_json::to_string(&ISettings::default()).unwrap())
+}
+
+fn get_entity<'a>(e: &'a editor::Editor, entity_index: &str) -> &'a Entity {
+    e.get_pattern()
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_entities().iter())
+        .find(|x| x.entity_index_as_uuid().replace('-', "") == entity_index)
+        .unwrap()
+}
+
+fn position(e: &editor::Editor, entity_index: &str) -> (f32, f32) {
+    let entity = get_entity(e, entity_index);
+    (entity.vertices[(0, 0)], entity.vertices[(0, 1)])
+}
+
+#[test]
+fn test_labels_are_linked_to_the_point_they_are_written_on() {
+    let e = load(DOCUMENT.to_string());
+    assert_eq!(
+        e.get_point_label(POINT_A.to_string()).as_deref(),
+        Some("#1")
+    );
+    // Both texts are within the tolerance, the closer one is the label
+    assert_eq!(
+        e.get_point_label(POINT_B.to_string()).as_deref(),
+        Some("#2")
+    );
+    assert_eq!(e.get_point_label(LINE.to_string()), None);
+    assert_eq!(e.get_point_label("not an index".to_string()), None);
+
+    // Links are found again when the pattern is read back
+    let reloaded = load(e.to_json());
+    assert_eq!(
+        reloaded.get_point_label(POINT_B.to_string()).as_deref(),
+        Some("#2")
+    );
+}
+
+#[test]
+fn test_labels_follow_edited_points() {
+    let mut e = load(DOCUMENT.to_string());
+    e.highlight_entity(POINT_A.to_string(), true);
+    e.set_highlight_offset(1., 2.);
+    e.offset_highlights();
+    assert_eq!(position(&e, POINT_A), (1., 2.));
+    assert_eq!(position(&e, LABEL_A), (1., 2.));
+    // Texts that are not the label stay put
+    assert_eq!(
+        position(&e, "00000000000000000000000000000004"),
+        (5.0005, 5.)
+    );
+
+    // A label edited along with its point only moves once
+    e.highlight_entity(LABEL_A.to_string(), true);
+    e.set_highlight_offset(1., 0.);
+    e.offset_highlights();
+    assert_eq!(position(&e, POINT_A), (2., 2.));
+    assert_eq!(position(&e, LABEL_A), (2., 2.));
+
+    assert!(e.undo());
+    assert!(e.undo());
+    assert_eq!(position(&e, LABEL_A), (0., 0.));
+}
+
+#[test]
+fn test_labels_keep_to_rotated_points() {
+    let mut e = load(DOCUMENT.to_string());
+    e.highlight_entity(POINT_B.to_string(), true);
+    e.set_highlight_rotation_center(0., 0.);
+    e.set_highlight_rotation_angle(std::f32::consts::FRAC_PI_2);
+    e.rotate_highlights_bbox();
+    let (point, label) = (
+        position(&e, POINT_B),
+        position(&e, "00000000000000000000000000000005"),
+    );
+    assert!((point.0 - label.0).abs() < 1e-5 && (point.1 - label.1).abs() < 1e-5);
+    assert!((point.0 - 5.).abs() < 1e-4 && (point.1 + 5.).abs() < 1e-4);
+}
+
+#[test]
+fn test_example_points_are_labelled() {
+    let json = fs::read_to_string("../example_input/shirt.json").unwrap();
+    let e = load(json);
+    let points: Vec<&Entity> = e
+        .get_pattern()
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_entities().iter())
+        .filter(|x| x.entity_type == EntityTypes::POINT)
+        .collect();
+    let labelled = points
+        .iter()
+        .filter_map(|p| e.get_point_label(p.entity_index_as_uuid()))
+        .collect::<Vec<String>>();
+    assert_eq!(labelled.len(), 48 + 43 + 29);
+    assert!(labelled.iter().all(|l| l.starts_with('#')));
+}
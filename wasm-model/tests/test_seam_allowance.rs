@@ -0,0 +1,332 @@
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::Entity;
+use cad_pattern_editor::seam_allowance::{offset_polygon, JoinType};
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::geometry;
+
+const SQUARE: &str = "00000000000000000000000000000001";
+const OPEN_LINE: &str = "00000000000000000000000000000002";
+const CIRCLE: &str = "00000000000000000000000000000003";
+
+fn document(square_vertices: &str) -> String {
+    format!(
+        r#"{{"pattern_json": {{
+    "blocks": {{
+        "P": {{"layer": "1", "centroid": {{"x": 0, "y": 0}}, "entities": [
+            {{"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "{}", "shape": true,
+             "vertices": {}}},
+            {{"entity_type": "LWPOLYLINE", "layer": "1", "entity_index": "{}", "shape": false,
+             "vertices": [{{"x": 0, "y": 0}}, {{"x": 5, "y": 0}}, {{"x": 5, "y": 5}}]}},
+            {{"entity_type": "CIRCLE", "layer": "1", "entity_index": "{}",
+             "center": {{"x": 30, "y": 0}}, "radius": 5}}
+        ]}}
+    }},
+    "entities": [
+        {{"entity_type": "INSERT", "name": "P", "layer": "1", "position": {{"x": 0, "y": 0}}}}
+    ]
+}}}}"#,
+        SQUARE, square_vertices, OPEN_LINE, CIRCLE
+    )
+}
+
+const SQUARE_VERTICES: &str =
+    r#"[{"x": 0, "y": 0}, {"x": 10, "y": 0}, {"x": 10, "y": 10}, {"x": 0, "y": 10}]"#;
+
+fn load(square_vertices: &str) -> editor::Editor {
+    editor::Editor::new(
+        document(square_vertices),
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    )
+}
+
+fn get_entity<'a>(e: &'a editor::Editor, entity_index: &str) -> &'a Entity {
+    e.get_pattern()
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_entities().iter())
+        .find(|x| x.entity_index_as_uuid().replace('-', "") == entity_index.replace('-', ""))
+        .unwrap()
+}
+
+fn points(entity: &Entity) -> Vec<(f32, f32)> {
+    entity
+        .vertices
+        .rows()
+        .into_iter()
+        .map(|v| (v[0], v[1]))
+        .collect()
+}
+
+fn assert_points_close(actual: &[(f32, f32)], expected: &[(f32, f32)]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for (a, b) in actual.iter().zip(expected.iter()) {
+        assert!(
+            geometry::distance(a, b) < 1e-4,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+fn has_crossing(polygon: &[(f32, f32)]) -> bool {
+    let n = polygon.len();
+    (0..n).any(|i| {
+        (i + 2..n).any(|j| {
+            !(i == 0 && j == n - 1)
+                && geometry::segment_crossing(
+                    &polygon[i],
+                    &polygon[i + 1],
+                    &polygon[j],
+                    &polygon[(j + 1) % n],
+                )
+                .is_some()
+        })
+    })
+}
+
+#[test]
+fn test_miter_adds_closed_outline_on_layer() {
+    let mut e = load(SQUARE_VERTICES);
+    let index = e
+        .add_seam_allowance(SQUARE.to_string(), vec![1.], "miter".to_string())
+        .unwrap();
+
+    let outline = get_entity(&e, &index);
+    assert!(outline.shape);
+    assert_eq!(outline.layer, 11);
+    assert_points_close(
+        &points(outline),
+        &[(-1., -1.), (11., -1.), (11., 11.), (-1., 11.)],
+    );
+    assert_eq!(e.get_pattern().get_number_entities(), 4);
+    assert_eq!(
+        *e.get_pattern().get_blocks()[0].get_bounding_box(),
+        ((-1., 35.), (-5., 11.))
+    );
+    // The original outline is left as it was
+    assert_eq!(points(get_entity(&e, SQUARE)).len(), 4);
+}
+
+#[test]
+fn test_per_edge_widths_and_direction() {
+    // Edges bottom, right, top, left
+    let mut e = load(SQUARE_VERTICES);
+    let index = e
+        .add_seam_allowance(
+            SQUARE.to_string(),
+            vec![1., 2., 3., 4.],
+            "miter".to_string(),
+        )
+        .unwrap();
+    assert_points_close(
+        &points(get_entity(&e, &index)),
+        &[(-4., -1.), (12., -1.), (12., 13.), (-4., 13.)],
+    );
+
+    // The same square walked clockwise keeps each width with its edge: left, top, right, bottom
+    let mut e =
+        load(r#"[{"x": 0, "y": 0}, {"x": 0, "y": 10}, {"x": 10, "y": 10}, {"x": 10, "y": 0}]"#);
+    let index = e
+        .add_seam_allowance(
+            SQUARE.to_string(),
+            vec![4., 3., 2., 1.],
+            "miter".to_string(),
+        )
+        .unwrap();
+    let mut output = points(get_entity(&e, &index));
+    output.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_points_close(&output, &[(-4., -1.), (-4., 13.), (12., -1.), (12., 13.)]);
+    assert!(geometry::signed_area(&points(get_entity(&e, &index))) > 0.);
+}
+
+#[test]
+fn test_bevel_and_round_corners() {
+    let mut e = load(SQUARE_VERTICES);
+    let index = e
+        .add_seam_allowance(SQUARE.to_string(), vec![1.], "bevel".to_string())
+        .unwrap();
+    let bevel = points(get_entity(&e, &index));
+    assert_eq!(bevel.len(), 8);
+    assert!((geometry::signed_area(&bevel) - 142.).abs() < 1e-3);
+
+    let index = e
+        .add_seam_allowance(SQUARE.to_string(), vec![1.], "Round".to_string())
+        .unwrap();
+    let round = points(get_entity(&e, &index));
+    assert!(round.len() > 8);
+    let expected_area = 100. + 40. + std::f32::consts::PI;
+    assert!((geometry::signed_area(&round) - expected_area).abs() < 0.05);
+    // Every point is one width from the square
+    let square = [(0., 0.), (10., 0.), (10., 10.), (0., 10.)];
+    for p in round.iter() {
+        let distance = (0..4)
+            .map(|i| geometry::closest_point_on_segment(p, &square[i], &square[(i + 1) % 4]).1)
+            .fold(f32::INFINITY, f32::min);
+        assert!((distance - 1.).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn test_long_miters_are_bevelled() {
+    // A sharp spike whose miter would reach far past the limit
+    let polygon = [(0., 0.), (10., 0.), (0., 0.5)];
+    let output = offset_polygon(&polygon, &[1., 1., 1.], JoinType::Miter, 0.01).unwrap();
+    let furthest = output.iter().map(|p| p.0).fold(f32::NEG_INFINITY, f32::max);
+    assert!(furthest > 10. && furthest < 11.);
+    assert!(!has_crossing(&output));
+}
+
+#[test]
+fn test_inside_corners_lose_their_loops() {
+    // A slot narrower than twice the allowance closes up
+    let polygon = [
+        (0., 0.),
+        (10., 0.),
+        (10., 10.),
+        (6., 10.),
+        (6., 2.),
+        (4., 2.),
+        (4., 10.),
+        (0., 10.),
+    ];
+    let widths = [1.5; 8];
+    for join in [JoinType::Miter, JoinType::Bevel, JoinType::Round] {
+        let output = offset_polygon(&polygon, &widths, join, 0.01).unwrap();
+        assert!(!has_crossing(&output), "{:?} {:?}", join, output);
+        let ((min_x, max_x), (min_y, max_y)) = output.iter().fold(
+            (
+                (f32::INFINITY, f32::NEG_INFINITY),
+                (f32::INFINITY, f32::NEG_INFINITY),
+            ),
+            |((a, b), (c, d)), p| ((a.min(p.0), b.max(p.0)), (c.min(p.1), d.max(p.1))),
+        );
+        assert!((min_y + 1.5).abs() < 1e-4 && (max_y - 11.5).abs() < 1e-4);
+        assert!((min_x + 1.5).abs() < 1e-4 && (max_x - 11.5).abs() < 1e-4);
+        if join == JoinType::Miter {
+            assert!(
+                (geometry::signed_area(&output) - 169.).abs() < 1e-2,
+                "{:?}",
+                output
+            );
+        }
+    }
+
+    // A wide slot stays open, narrowed by the allowance on both sides
+    let polygon = [
+        (0., 0.),
+        (10., 0.),
+        (10., 10.),
+        (7., 10.),
+        (7., 2.),
+        (3., 2.),
+        (3., 10.),
+        (0., 10.),
+    ];
+    let output = offset_polygon(&polygon, &[1.; 8], JoinType::Miter, 0.01).unwrap();
+    assert!(!has_crossing(&output));
+    assert!(output
+        .iter()
+        .any(|p| geometry::distance(p, &(4., 3.)) < 1e-4));
+    assert!(output
+        .iter()
+        .any(|p| geometry::distance(p, &(6., 3.)) < 1e-4));
+}
+
+#[test]
+fn test_curves_and_bulges_are_followed() {
+    let mut e = load(SQUARE_VERTICES);
+    let index = e
+        .add_seam_allowance(CIRCLE.to_string(), vec![2.], "round".to_string())
+        .unwrap();
+    for p in points(get_entity(&e, &index)).iter() {
+        assert!((geometry::distance(p, &(30., 0.)) - 7.).abs() < 0.02);
+    }
+
+    // A half disc, the bulged edge has its own width
+    let body = document(r#"[{"x": 0, "y": 0, "bulge": 1}, {"x": 10, "y": 0}]"#);
+    let mut e = editor::Editor::new(body, serde_json::to_string(&ISettings::default()).unwrap());
+    let index = e
+        .add_seam_allowance(SQUARE.to_string(), vec![1., 2.], "miter".to_string())
+        .unwrap();
+    let output = points(get_entity(&e, &index));
+    // The arc runs below the chord from 0 to 10, its allowance is a wider arc
+    let lowest = output.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+    assert!((lowest + 6.).abs() < 0.02);
+    let highest = output.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+    assert!((highest - 2.).abs() < 1e-3);
+}
+
+#[test]
+fn test_invalid_requests_add_nothing() {
+    let mut e = load(SQUARE_VERTICES);
+    let square = SQUARE.to_string();
+    assert!(e
+        .add_seam_allowance(OPEN_LINE.to_string(), vec![1.], "miter".to_string())
+        .is_none());
+    assert!(e
+        .add_seam_allowance(square.clone(), vec![1., 2.], "miter".to_string())
+        .is_none());
+    assert!(e
+        .add_seam_allowance(square.clone(), vec![-1.], "miter".to_string())
+        .is_none());
+    assert!(e
+        .add_seam_allowance(square.clone(), vec![1.], "zigzag".to_string())
+        .is_none());
+    assert!(e
+        .add_seam_allowance("missing".to_string(), vec![1.], "miter".to_string())
+        .is_none());
+    assert_eq!(e.get_pattern().get_number_entities(), 3);
+    assert!(!e.can_undo());
+}
+
+#[test]
+fn test_seam_allowance_can_be_undone_and_exported() {
+    let mut e = load(SQUARE_VERTICES);
+    e.set_seam_allowance_layer(14);
+    let index = e
+        .add_seam_allowance(SQUARE.to_string(), vec![1.], "miter".to_string())
+        .unwrap();
+    assert_ne!(index, SQUARE);
+    assert_eq!(get_entity(&e, &index).layer, 14);
+    assert!(e.to_json().contains(&index));
+
+    assert!(e.undo());
+    assert_eq!(e.get_pattern().get_number_entities(), 3);
+    assert!(e.redo());
+    assert_eq!(e.get_pattern().get_number_entities(), 4);
+}
+
+#[test]
+fn test_every_piece_of_the_example_gets_an_allowance() {
+    let json_payload = std::fs::read_to_string("../example_input/shirt.json").unwrap();
+    let mut e = editor::Editor::new(
+        json_payload,
+        serde_json::to_string(&ISettings::default()).unwrap(),
+    );
+    let pieces: Vec<(String, f32)> = e
+        .get_pattern()
+        .get_blocks()
+        .iter()
+        .flat_map(|b| b.get_entities().iter())
+        .filter(|x| x.shape && x.vertices.shape()[0] > 2)
+        .map(|x| {
+            (
+                x.entity_index_as_uuid(),
+                geometry::signed_area(&points(x)).abs(),
+            )
+        })
+        .collect();
+    assert!(!pieces.is_empty());
+
+    for (entity_index, area) in pieces.iter() {
+        for join in ["miter", "round", "bevel"] {
+            let index = e
+                .add_seam_allowance(entity_index.clone(), vec![1.], join.to_string())
+                .unwrap();
+            let outline = points(get_entity(&e, &index));
+            assert!(!has_crossing(&outline));
+            assert!(geometry::signed_area(&outline) > *area);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use cad_pattern_editor::editor;
+use cad_pattern_editor::snap::SnapMode;
+use cad_pattern_editor::user_settings::ISettings;
+
+mod common;
+use common::assert_point_close;
+
+const LINE: &str = "00000000000000000000000000000001";
+const CROSSING_LINE: &str = "00000000000000000000000000000002";
+
+// Two lines crossing at (3, 0), a square on its own layer and a turned copy of the lines
+const DOCUMENT: &str = r##"{"pattern_json": {
+    "blocks": {
+        "A": {"layer": "1", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000001",
+             "vertices": [{"x": 0, "y": 0}, {"x": 10, "y": 0}]},
+            {"entity_type": "LINE", "layer": "1", "entity_index": "00000000000000000000000000000002",
+             "vertices": [{"x": 3, "y": -4}, {"x": 3, "y": 6}]}
+        ]},
+        "B": {"layer": "5", "centroid": {"x": 0, "y": 0}, "entities": [
+            {"entity_type": "LWPOLYLINE", "layer": "5", "entity_index": "00000000000000000000000000000003",
+             "shape": true, "vertices": [{"x": 20, "y": 0}, {"x": 24, "y": 0}, {"x": 24, "y": 4}, {"x": 20, "y": 4}]}
+        ]}
+    },
+    "entities": [
+        {"entity_type": "INSERT", "name": "A", "layer": "1", "position": {"x": 0, "y": 0}},
+        {"entity_type": "INSERT", "name": "B", "layer": "5", "position": {"x": 0, "y": 0}},
+        {"entity_type": "INSERT", "name": "A", "layer": "1", "position": {"x": 100, "y": 0}, "rotation": 90}
+    ]
+}}"##;
+
+fn load() -> editor::Editor {
+    let settings = ISettings {
+        point_threshold: 1.,
+        ..ISettings::default()
+    };
+    editor::Editor::new(
+        DOCUMENT.to_string(),
+        serde_json::to_string(&settings).unwrap(),
+    )
+}
+
+fn all_modes() -> Vec<String> {
+    [
+        "endpoint",
+        "midpoint",
+        "intersection",
+        "perpendicular",
+        "nearest",
+    ]
+    .iter()
+    .map(|m| m.to_string())
+    .collect()
+}
+
+#[test]
+fn test_endpoints_and_midpoints() {
+    let e = load();
+    let target = e.snap_point(vec![9.6, 0.3], all_modes(), None).unwrap();
+    assert_eq!(target.mode, SnapMode::Endpoint);
+    assert_eq!(target.point, (10., 0.));
+    assert_eq!(target.entity_indexes.len(), 1);
+    assert_eq!(target.entity_indexes[0].replace('-', ""), LINE);
+
+    let target = e.snap_point(vec![5.2, 0.4], all_modes(), None).unwrap();
+    assert_eq!(target.mode, SnapMode::Midpoint);
+    assert_eq!(target.point, (5., 0.));
+    assert_point_close(
+        (target.distance, 0.),
+        ((0.2f32 * 0.2 + 0.4 * 0.4).sqrt(), 0.),
+    );
+}
+
+#[test]
+fn test_intersections_beat_nearby_points_further_away() {
+    let e = load();
+    // The middle of the crossing line at (3, 1) is in reach but further than the crossing
+    let target = e.snap_point(vec![3.3, 0.2], all_modes(), None).unwrap();
+    assert_eq!(target.mode, SnapMode::Intersection);
+    assert_point_close(target.point, (3., 0.));
+    let mut indexes: Vec<String> = target
+        .entity_indexes
+        .iter()
+        .map(|i| i.replace('-', ""))
+        .collect();
+    indexes.sort();
+    assert_eq!(indexes, vec![LINE, CROSSING_LINE]);
+
+    // Without intersections the midpoint wins
+    let modes = vec!["endpoint".to_string(), "midpoint".to_string()];
+    let target = e.snap_point(vec![3.3, 0.2], modes, None).unwrap();
+    assert_eq!(target.mode, SnapMode::Midpoint);
+    assert_eq!(target.point, (3., 1.));
+}
+
+#[test]
+fn test_nearest_only_when_nothing_else_is_in_reach() {
+    let e = load();
+    let target = e.snap_point(vec![7.5, 0.3], all_modes(), None).unwrap();
+    assert_eq!(target.mode, SnapMode::Nearest);
+    assert_point_close(target.point, (7.5, 0.));
+
+    assert!(e.snap_point(vec![7.5, 5.], all_modes(), None).is_none());
+    assert!(e.snap_point(vec![7.5, 0.3], vec![], None).is_none());
+    // Unknown modes are left out
+    let modes = vec!["tangent".to_string(), "nearest".to_string()];
+    assert!(e.snap_point(vec![7.5, 0.3], modes, None).is_some());
+}
+
+#[test]
+fn test_perpendicular_drops_from_the_base_point() {
+    let e = load();
+    let modes = vec!["perpendicular".to_string()];
+    let target = e
+        .snap_point(vec![7.2, 0.5], modes.clone(), Some(vec![7., 5.]))
+        .unwrap();
+    assert_eq!(target.mode, SnapMode::Perpendicular);
+    assert_point_close(target.point, (7., 0.));
+    assert!(e.snap_point(vec![7.2, 0.5], modes, None).is_none());
+}
+
+#[test]
+fn test_only_visible_layers_are_snapped_to() {
+    let mut e = load();
+    let target = e.snap_point(vec![24.2, 4.1], all_modes(), None).unwrap();
+    assert_eq!(target.point, (24., 4.));
+    e.disable_layer(5);
+    assert!(e.snap_point(vec![24.2, 4.1], all_modes(), None).is_none());
+}
+
+#[test]
+fn test_placements_are_snapped_where_they_are_drawn() {
+    let e = load();
+    // The copy at (100, 0) is turned a quarter counterclockwise
+    let target = e.snap_point(vec![100.2, 9.7], all_modes(), None).unwrap();
+    assert_eq!(target.mode, SnapMode::Endpoint);
+    assert_point_close(target.point, (100., 10.));
+}
+
+#[test]
+fn test_offset_between_snapped_points() {
+    let mut e = load();
+    e.highlight_block("B".to_string(), true);
+    let modes = vec!["endpoint".to_string()];
+    let base = e.snap_point(vec![20.3, 0.2], modes.clone(), None).unwrap();
+    let target = e.snap_point(vec![9.8, -0.4], modes, None).unwrap();
+    e.offset_highlights_between(
+        vec![base.point.0, base.point.1],
+        vec![target.point.0, target.point.1],
+    );
+    assert_eq!(
+        e.get_highlighted_bounding_box(),
+        Some(((10., 14.), (0., 4.)))
+    );
+    assert_eq!(e.get_user_settings().highlight_offset, (0., 0.));
+
+    assert!(e.undo());
+    assert_eq!(
+        e.get_highlighted_bounding_box(),
+        Some(((20., 24.), (0., 4.)))
+    );
+    // Picks that are not points are ignored
+    e.offset_highlights_between(vec![1.], vec![2., 2.]);
+    assert!(!e.can_undo());
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_pattern::{
+    ParseBlock, ParseDocument, ParseEntity, ParseInsertEntity, ParsePattern, Vertex,
+};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+use cad_pattern_editor::utils::spatial_index::SpatialIndex;
+
+// Start of the first LINE of block L-1 in block coordinates
+const LINE_START: (f32, f32) = (-24.9363, 15.25);
+
+fn load_example_editor() -> editor::Editor {
+    let path = Path::new("../example_input/shirt.json");
+    let json = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+    editor::Editor::new(json, settings_payload)
+}
+
+// Square outlines of size 1 laid out on a grid with a spacing of 2
+fn grid_pattern(columns: usize, rows: usize) -> pattern::Pattern {
+    let mut blocks = BTreeMap::new();
+    let mut inserts = vec![];
+    for i in 0..columns * rows {
+        let name = format!("B-{}", i);
+        let corners = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+        blocks.insert(
+            name.clone(),
+            ParseBlock {
+                entities: vec![ParseEntity {
+                    entity_type: "LWPOLYLINE".to_string(),
+                    layer: "1".to_string(),
+                    entity_index: format!("{:032x}", i),
+                    shape: Some(true),
+                    vertices: Some(
+                        corners
+                            .iter()
+                            .map(|(x, y)| Vertex {
+                                x: *x,
+                                y: *y,
+                                bulge: None,
+                            })
+                            .collect(),
+                    ),
+                    ..Default::default()
+                }],
+                centroid: Vertex {
+                    x: 0.5,
+                    y: 0.5,
+                    bulge: None,
+                },
+                layer: "1".to_string(),
+                inserts: vec![],
+            },
+        );
+        inserts.push(ParseInsertEntity {
+            entity_type: "INSERT".to_string(),
+            name,
+            position: Vertex {
+                x: (i % columns) as f32 * 2.,
+                y: (i / columns) as f32 * 2.,
+                bulge: None,
+            },
+            layer: "1".to_string(),
+            ..Default::default()
+        });
+    }
+    let document = ParseDocument {
+        pattern_json: ParsePattern {
+            blocks,
+            entities: inserts,
+            ..Default::default()
+        },
+    };
+    pattern::Pattern::from_document(&document)
+}
+
+#[test]
+fn test_index_queries_and_updates() {
+    let mut index = SpatialIndex::new(vec![((0., 1.), (0., 1.)), ((5., 6.), (5., 6.))]);
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.query_bbox(&((-1., 10.), (-1., 10.))), vec![0, 1]);
+    assert_eq!(index.query_point(&(1.5, 0.5), 0.6), vec![0]);
+    assert!(index.query_point(&(3., 3.), 0.5).is_empty());
+
+    index.update(0, ((3., 4.), (3., 4.)));
+    assert_eq!(index.query_point(&(3., 3.), 0.5), vec![0]);
+    assert!(index.query_point(&(0.5, 0.5), 0.).is_empty());
+}
+
+#[test]
+fn test_grid_selection_uses_insert_positions() {
+    let p = grid_pattern(10, 10);
+    let settings = ISettings {
+        point_threshold: 0.25,
+        ..Default::default()
+    };
+
+    // Edge of block 23 which sits at (6, 4)
+    let hit = p.find_closest_entity(&(7., 4.5), &settings).unwrap();
+    assert_eq!(hit.block, "B-23");
+    assert!((hit.closest.point.0 - 7.).abs() < 1e-5);
+    assert!(p.find_closest_entity(&(7.5, 3.5), &settings).is_none());
+
+    // Window around blocks 0, 1, 10 and 11 only
+    let (selected, bbox) = p.find_entities_with_bbox(&((-0.5, 3.5), (-0.5, 3.5)), &settings, false);
+    let mut expected: Vec<String> = [0, 1, 10, 11]
+        .iter()
+        .map(|i| format!("{:032x}", i))
+        .map(|s| {
+            format!(
+                "{}-{}-{}-{}-{}",
+                &s[..8],
+                &s[8..12],
+                &s[12..16],
+                &s[16..20],
+                &s[20..]
+            )
+        })
+        .collect();
+    expected.sort();
+    let mut selected = selected;
+    selected.sort();
+    assert_eq!(selected, expected);
+    assert_eq!(bbox, Some(((0., 3.), (0., 3.))));
+}
+
+#[test]
+fn test_index_follows_moved_block() {
+    let mut h = load_example_editor();
+    let settings = ISettings::default();
+    let insert = h.get_pattern().get_inserts()[0].position.clone();
+    let start = (insert[(0, 0)] + LINE_START.0, insert[(0, 1)] + LINE_START.1);
+    assert_eq!(
+        h.get_pattern()
+            .find_closest_entity(&start, &settings)
+            .unwrap()
+            .block,
+        "L-1"
+    );
+
+    h.highlight_block("L-1".to_string(), true);
+    h.set_highlight_offset(1000., 0.);
+    h.offset_highlights();
+
+    let moved = (start.0 + 1000., start.1);
+    assert!(h
+        .get_pattern()
+        .find_closest_entity(&start, &settings)
+        .is_none());
+    assert_eq!(
+        h.get_pattern()
+            .find_closest_entity(&moved, &settings)
+            .unwrap()
+            .block,
+        "L-1"
+    );
+
+    h.undo();
+    assert!(h
+        .get_pattern()
+        .find_closest_entity(&moved, &settings)
+        .is_none());
+    assert!(h
+        .get_pattern()
+        .find_closest_entity(&start, &settings)
+        .is_some());
+}
+
+#[test]
+fn test_index_follows_flipped_block() {
+    let mut h = load_example_editor();
+    let settings = ISettings::default();
+    let insert = h.get_pattern().get_inserts()[0].position.clone();
+    let (d_x, d_y) = (insert[(0, 0)], insert[(0, 1)]);
+
+    // Mirror L-1 about the vertical line through its insert point
+    h.highlight_block("L-1".to_string(), true);
+    h.set_highlight_anchor(d_x, d_y);
+    h.set_highlight_flip(true, false);
+    h.scale_highlights();
+
+    let mirrored = (d_x - LINE_START.0, d_y + LINE_START.1);
+    let hit = h
+        .get_pattern()
+        .find_closest_entity(&mirrored, &settings)
+        .unwrap();
+    assert_eq!(hit.block, "L-1");
+    assert!(hit.closest.distance < 1e-3);
+}
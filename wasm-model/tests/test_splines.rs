@@ -0,0 +1,284 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+use cad_pattern_editor::curve::Curve;
+use cad_pattern_editor::editor;
+use cad_pattern_editor::entity::EntityTypes;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::parse_pattern::{ParseDocument, ParseEntity, Vertex};
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::spline::Spline;
+use cad_pattern_editor::write_dxf;
+
+mod common;
+use common::assert_close;
+
+// Rational quadratic quarter of the unit circle
+fn quarter_circle() -> Spline {
+    Spline::new(
+        2,
+        vec![0., 0., 0., 1., 1., 1.],
+        vec![(1., 0.), (1., 1.), (0., 1.)],
+        vec![1., FRAC_1_SQRT_2, 1.],
+        vec![],
+        false,
+    )
+    .unwrap()
+}
+
+// Full unit circle from nine weighted control points
+fn full_circle() -> Spline {
+    let w = FRAC_1_SQRT_2;
+    Spline::new(
+        2,
+        vec![0., 0., 0., 0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1., 1., 1.],
+        vec![
+            (1., 0.),
+            (1., 1.),
+            (0., 1.),
+            (-1., 1.),
+            (-1., 0.),
+            (-1., -1.),
+            (0., -1.),
+            (1., -1.),
+            (1., 0.),
+        ],
+        vec![1., w, 1., w, 1., w, 1., w, 1.],
+        vec![],
+        true,
+    )
+    .unwrap()
+}
+
+fn bezier_points() -> Vec<(f32, f32)> {
+    vec![(0., 0.), (1., 2.), (3., 2.), (4., 0.)]
+}
+
+fn spline_document() -> ParseDocument {
+    let vertices = |points: Vec<(f32, f32)>| {
+        points
+            .into_iter()
+            .map(|(x, y)| Vertex { x, y, bulge: None })
+            .collect::<Vec<Vertex>>()
+    };
+    common::single_block_document(
+        "S",
+        vec![
+            ParseEntity {
+                entity_type: "SPLINE".to_string(),
+                layer: "1".to_string(),
+                entity_index: format!("{:032x}", 1),
+                degree: Some(3),
+                knots: Some(vec![0., 0., 0., 0., 1., 1., 1., 1.]),
+                control_points: Some(vertices(bezier_points())),
+                ..Default::default()
+            },
+            ParseEntity {
+                entity_type: "SPLINE".to_string(),
+                layer: "1".to_string(),
+                entity_index: format!("{:032x}", 2),
+                degree: Some(3),
+                fit_points: Some(vertices(vec![(0., 5.), (1., 6.), (2., 5.), (3., 6.)])),
+                ..Default::default()
+            },
+        ],
+    )
+}
+
+#[test]
+fn test_rational_spline_matches_circle() {
+    let spline = quarter_circle();
+    for i in 0..=20 {
+        let (x, y) = spline.evaluate(i as f32 / 20.);
+        assert_close((x * x + y * y).sqrt(), 1.);
+    }
+    assert_close(spline.evaluate(0.5).0, FRAC_1_SQRT_2);
+
+    let circle = full_circle();
+    for i in 0..=40 {
+        let (x, y) = circle.evaluate(i as f32 / 40.);
+        assert_close((x * x + y * y).sqrt(), 1.);
+    }
+    let ((min_x, max_x), (min_y, max_y)) = circle.bounding_box();
+    assert_close(min_x, -1.);
+    assert_close(max_x, 1.);
+    assert_close(min_y, -1.);
+    assert_close(max_y, 1.);
+}
+
+#[test]
+fn test_cubic_spline_matches_bezier() {
+    let points = bezier_points();
+    let spline = Spline::new(
+        3,
+        vec![0., 0., 0., 0., 1., 1., 1., 1.],
+        points.clone(),
+        vec![],
+        vec![],
+        false,
+    )
+    .unwrap();
+    for i in 0..=10 {
+        let t = i as f32 / 10.;
+        let s = 1. - t;
+        let basis = [s * s * s, 3. * s * s * t, 3. * s * t * t, t * t * t];
+        let x: f32 = basis.iter().zip(points.iter()).map(|(b, p)| b * p.0).sum();
+        let y: f32 = basis.iter().zip(points.iter()).map(|(b, p)| b * p.1).sum();
+        let (e_x, e_y) = spline.evaluate(t);
+        assert_close(e_x, x);
+        assert_close(e_y, y);
+    }
+
+    // Highest point is 1.5 at the middle, well inside the control polygon
+    let ((min_x, max_x), (min_y, max_y)) = spline.bounding_box();
+    assert_close(min_x, 0.);
+    assert_close(max_x, 4.);
+    assert_close(min_y, 0.);
+    assert_close(max_y, 1.5);
+}
+
+#[test]
+fn test_spline_tessellation_respects_tolerance() {
+    let spline = quarter_circle();
+    for tolerance in [0.1, 0.01, 0.0001] {
+        let points = spline.tessellate(tolerance);
+        for i in 0..points.shape()[0] - 1 {
+            let (m_x, m_y) = (
+                (points[(i, 0)] + points[(i + 1, 0)]) / 2.,
+                (points[(i, 1)] + points[(i + 1, 1)]) / 2.,
+            );
+            assert!(1. - (m_x * m_x + m_y * m_y).sqrt() <= tolerance * 1.01);
+        }
+    }
+    assert!(spline.tessellate(0.0001).shape()[0] > spline.tessellate(0.1).shape()[0]);
+
+    // Closed splines don't repeat their first point
+    let points = full_circle().tessellate(0.01);
+    let last = points.shape()[0] - 1;
+    assert!((points[(0, 0)] - points[(last, 0)]).abs() > 1e-3);
+}
+
+#[test]
+fn test_invalid_splines_are_rejected() {
+    let points = bezier_points();
+    assert!(Spline::new(3, vec![0., 1.], points.clone(), vec![], vec![], false).is_none());
+    assert!(Spline::new(
+        3,
+        vec![0., 0., 0., 0., 1., 1., 1., 1.],
+        points.clone(),
+        vec![1., 1.],
+        vec![],
+        false
+    )
+    .is_none());
+    assert!(Spline::new(5, vec![0.; 10], points, vec![], vec![], false).is_none());
+    assert!(Spline::new(3, vec![], vec![], vec![], vec![(1., 1.)], false).is_none());
+}
+
+#[test]
+fn test_fit_points_are_interpolated() {
+    let p = pattern::Pattern::from_document(&spline_document());
+    let entity = &p.get_blocks()[0].get_entities()[1];
+    assert_eq!(entity.entity_type, EntityTypes::SPLINE);
+    let Some(Curve::Spline(spline)) = &entity.curve else {
+        panic!("Expected a spline");
+    };
+    assert_eq!(spline.control_points.len(), 4);
+    // Equal chords, so the fit points sit at even parameter steps
+    let fit_points = [(0., 5.), (1., 6.), (2., 5.), (3., 6.)];
+    for (i, (f_x, f_y)) in fit_points.iter().enumerate() {
+        let (x, y) = spline.evaluate(i as f32 / 3.);
+        assert_close(x, *f_x);
+        assert_close(y, *f_y);
+    }
+}
+
+#[test]
+fn test_spline_transforms_are_exact() {
+    let mut h = common::to_editor(&spline_document());
+    let get_spline =
+        |h: &editor::Editor| match &h.get_pattern().get_blocks()[0].get_entities()[0].curve {
+            Some(Curve::Spline(spline)) => spline.clone(),
+            _ => panic!("Expected a spline"),
+        };
+    let before = get_spline(&h);
+
+    // Non uniform scale and a mirror, the spline stays a spline
+    h.highlight_block("S".to_string(), true);
+    h.set_highlight_anchor(1., 1.);
+    h.set_highlight_scale(2., 0.5);
+    h.set_highlight_flip(true, false);
+    h.scale_highlights();
+    let after = get_spline(&h);
+    for i in 0..=10 {
+        let t = i as f32 / 10.;
+        let (x, y) = before.evaluate(t);
+        let (a_x, a_y) = after.evaluate(t);
+        assert_close(a_x, 1. - 2. * (x - 1.));
+        assert_close(a_y, 1. + 0.5 * (y - 1.));
+    }
+
+    // Quarter turn about the origin
+    h.set_highlight_rotation_angle(PI / 2.);
+    h.rotate_highlights_bbox();
+    let rotated = get_spline(&h);
+    for i in 0..=10 {
+        let t = i as f32 / 10.;
+        let (x, y) = after.evaluate(t);
+        let (r_x, r_y) = rotated.evaluate(t);
+        assert_close((r_x * r_x + r_y * r_y).sqrt(), (x * x + y * y).sqrt());
+        assert_close(r_x * x + r_y * y, 0.);
+    }
+    let entity = &h.get_pattern().get_blocks()[0].get_entities()[0];
+    assert_eq!(entity.bounding_box, rotated.bounding_box());
+}
+
+#[test]
+fn test_splines_roundtrip_through_json_and_dxf() {
+    let p = pattern::Pattern::from_document(&spline_document());
+    let roundtrip = pattern::Pattern::from_document(&p.to_document());
+    assert_eq!(
+        serde_json::to_string(&roundtrip.to_document()).unwrap(),
+        serde_json::to_string(&p.to_document()).unwrap()
+    );
+
+    let document = parse_dxf::parse_dxf(&write_dxf::write_dxf(&p)).unwrap();
+    let entities = &document.pattern_json.blocks["S"].entities;
+    assert_eq!(entities.len(), 2);
+    assert!(entities.iter().all(|e| e.entity_type == "SPLINE"));
+    assert_eq!(entities[0].degree, Some(3));
+    assert_eq!(entities[0].control_points.as_ref().unwrap().len(), 4);
+    assert_eq!(entities[1].fit_points.as_ref().unwrap().len(), 4);
+
+    let from_dxf = pattern::Pattern::from_document(&document);
+    assert_eq!(from_dxf.get_number_entities(), 2);
+}
+
+#[test]
+fn test_closed_fit_points_are_interpolated_periodically() {
+    let corners = vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+    let spline = Spline::new(3, vec![], vec![], vec![], corners.clone(), true).unwrap();
+    assert_eq!(spline.control_points.len(), 7);
+    // Equal chords put the corners a quarter apart, the domain starts at the second one
+    let (start, end) = spline.get_domain();
+    assert_close(start, 0.25);
+    assert_close(end, 1.25);
+    for (i, (c_x, c_y)) in [(1., 0.), (1., 1.), (0., 1.), (0., 0.), (1., 0.)]
+        .iter()
+        .enumerate()
+    {
+        let (x, y) = spline.evaluate(0.25 + i as f32 / 4.);
+        assert_close(x, *c_x);
+        assert_close(y, *c_y);
+    }
+    // The loop rounds the corners, so it runs outside the square between them
+    assert!(spline.evaluate(1.125).1 < 0.);
+    let ((min_x, max_x), (min_y, max_y)) = spline.bounding_box();
+    assert_close(max_x - min_x, max_y - min_y);
+
+    // Repeating the first point to close the loop gives the same curve
+    let mut repeated = corners.clone();
+    repeated.push((0., 0.));
+    let again = Spline::new(3, vec![], vec![], vec![], repeated, true).unwrap();
+    assert_eq!(again.control_points, spline.control_points);
+    assert_eq!(again.knots, spline.knots);
+}
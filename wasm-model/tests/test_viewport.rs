@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn load_example_editor() -> editor::Editor {
+    let path = Path::new("../example_input/shirt.json");
+    let json = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+    editor::Editor::new(json, settings_payload)
+}
+
+// Bounding box of a block in world coordinates as (min_x, min_y, max_x, max_y)
+fn world_bbox(h: &editor::Editor, block_name: &str) -> (f32, f32, f32, f32) {
+    let pattern = h.get_pattern();
+    let block = pattern
+        .get_blocks()
+        .iter()
+        .find(|b| b.name == block_name)
+        .unwrap();
+    let insert = pattern
+        .get_inserts()
+        .iter()
+        .find(|i| i.name == block_name)
+        .unwrap();
+    let ((min_x, max_x), (min_y, max_y)) = block.get_bounding_box();
+    let (d_x, d_y) = (insert.position[(0, 0)], insert.position[(0, 1)]);
+    (min_x + d_x, min_y + d_y, max_x + d_x, max_y + d_y)
+}
+
+#[test]
+fn test_viewport_culls_blocks() {
+    let mut h = load_example_editor();
+    h.update_draw_sequence();
+    let full_len = h.get_vertex_buffer_len();
+    assert!(full_len > 0);
+
+    let (min_x, min_y, max_x, max_y) = world_bbox(&h, "L-1");
+    h.set_viewport(min_x, min_y, max_x, max_y);
+    h.update_draw_sequence();
+    let block_len = h.get_vertex_buffer_len();
+    assert!(block_len > 0 && block_len < full_len);
+
+    // Only part of the block is visible
+    h.set_viewport(min_x, min_y, (min_x + max_x) / 2., (min_y + max_y) / 2.);
+    h.update_draw_sequence();
+    assert!(h.get_vertex_buffer_len() < block_len);
+
+    h.set_viewport(1e6, 1e6, 1e6 + 1., 1e6 + 1.);
+    h.update_draw_sequence();
+    assert_eq!(h.get_vertex_buffer_len(), 0);
+    assert_eq!(h.get_index_buffer_len(), 0);
+
+    h.clear_viewport();
+    h.update_draw_sequence();
+    assert_eq!(h.get_vertex_buffer_len(), full_len);
+}
+
+#[test]
+fn test_viewport_keeps_selection_statistics() {
+    let mut h = load_example_editor();
+    h.highlight_block("L-2".to_string(), true);
+    h.update_draw_sequence();
+    let expected_count = h.get_user_settings().highlight_nr_selected_entities;
+    let expected_size = h.get_user_settings().highlight_width_height;
+    assert!(expected_count > 0);
+
+    // Selection is off screen so is not drawn but still counted
+    h.set_viewport(1e6, 1e6, 1e6 + 1., 1e6 + 1.);
+    h.update_draw_sequence();
+    assert_eq!(h.get_vertex_buffer_len(), 0);
+    assert_eq!(
+        h.get_user_settings().highlight_nr_selected_entities,
+        expected_count
+    );
+    assert_eq!(h.get_user_settings().highlight_width_height, expected_size);
+}
+
+#[test]
+fn test_viewport_draws_selection_dragged_into_view() {
+    let mut h = load_example_editor();
+    let (min_x, min_y, max_x, max_y) = world_bbox(&h, "L-2");
+    h.highlight_block("L-2".to_string(), true);
+
+    // Viewport to the right of L-2, the selection is dragged into it
+    let width = max_x - min_x;
+    h.set_viewport(max_x + width, min_y, max_x + 2. * width, max_y);
+    h.update_draw_sequence();
+    let before = h.get_vertex_buffer_len();
+
+    h.set_highlight_offset(1.5 * width, 0.);
+    h.update_draw_sequence();
+    assert!(h.get_vertex_buffer_len() > before);
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::parse_dxf;
+use cad_pattern_editor::pattern;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn load_json_example() -> pattern::Pattern {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    pattern::Pattern::new(json_payload)
+}
+
+#[test]
+fn test_export_dxf_is_readable() {
+    let p = load_json_example();
+
+    let dxf_payload = p.export_dxf();
+    let document = parse_dxf::parse_dxf(&dxf_payload).expect("Exported DXF does not parse");
+
+    assert!(dxf_payload.ends_with("EOF\n"));
+    assert_eq!(document.pattern_json.blocks.len(), 3);
+    assert_eq!(document.pattern_json.entities.len(), 3);
+}
+
+#[test]
+fn test_json_to_dxf_round_trip() {
+    let p = load_json_example();
+
+    let reloaded = pattern::Pattern::new_from_dxf(p.export_dxf());
+
+    assert_eq!(reloaded.get_number_entities(), 305);
+    assert_eq!(p, reloaded);
+}
+
+#[test]
+fn test_dxf_to_dxf_round_trip() {
+    let path = Path::new("../example_input/shirt.dxf");
+    let dxf_payload: String = fs::read_to_string(path).expect("Unable to read DXF file");
+    let p = pattern::Pattern::new_from_dxf(dxf_payload);
+
+    let exported = p.export_dxf();
+    let reloaded = pattern::Pattern::new_from_dxf(exported.clone());
+
+    assert_eq!(p, reloaded);
+    assert_eq!(exported, reloaded.export_dxf());
+}
+
+#[test]
+fn test_model_space_entities_written_to_entities_section() {
+    let dxf_payload = [
+        "0", "SECTION", "2", "ENTITIES", "0", "LINE", "5", "2B", "8", "3", "10", "1.5", "20",
+        "2.5", "11", "4", "21", "-1", "0", "ENDSEC", "0", "EOF",
+    ]
+    .join("\n");
+    let p = pattern::Pattern::new_from_dxf(dxf_payload);
+
+    let exported = p.export_dxf();
+
+    assert!(!exported.contains("\nINSERT\n"));
+    assert_eq!(p, pattern::Pattern::new_from_dxf(exported));
+}
+
+#[test]
+fn test_edits_are_exported() {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(&ISettings::default()).unwrap();
+    let mut h = editor::Editor::new(json_payload, settings_payload);
+
+    h.highlight_block("L-2".to_string(), true);
+    h.set_highlight_offset(10., -5.);
+    h.offset_highlights();
+
+    let reloaded = pattern::Pattern::new_from_dxf(h.export_dxf());
+    let original = load_json_example();
+    let moved = &reloaded.get_inserts()[1];
+
+    assert_eq!(moved.name, "L-2");
+    assert_eq!(
+        &moved.position - &original.get_inserts()[1].position,
+        ndarray::array![[10., -5.]]
+    );
+    assert_eq!(original.get_blocks(), reloaded.get_blocks());
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::Path;
+
+use cad_pattern_editor::editor;
+use cad_pattern_editor::user_settings::ISettings;
+
+fn load_example_editor(settings: &ISettings) -> editor::Editor {
+    let path = Path::new("../example_input/shirt.json");
+    let json_payload: String = fs::read_to_string(path).expect("Unable to read JSON file");
+    let settings_payload = serde_json::to_string(settings).unwrap();
+    editor::Editor::new(json_payload, settings_payload)
+}
+
+#[test]
+fn test_export_svg_model_view() {
+    let h = load_example_editor(&ISettings::default());
+
+    let svg = h.export_svg();
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<g id=").count(), 3);
+    assert!(svg.contains("<g id=\"L-1\" transform=\"translate(-58.73511 11.00978)\">"));
+    assert_eq!(svg.matches("<path").count(), 160);
+    assert_eq!(svg.matches(" Z\"").count(), 6);
+    assert_eq!(svg.matches("<text").count(), 145);
+    assert!(svg.contains("font-size=\"0.4\""));
+}
+
+#[test]
+fn test_export_svg_respects_layers_and_colors() {
+    let mut settings = ISettings {
+        disabled_layers: vec![2],
+        ..Default::default()
+    };
+    settings.layer_colors.insert(1, (255, 0, 0, 128));
+    let h = load_example_editor(&settings);
+
+    let svg = h.export_svg();
+
+    assert_eq!(svg.matches("<text").count(), 69);
+    assert_eq!(svg.matches("<path").count(), 87);
+    assert_eq!(
+        svg.matches("stroke=\"#ff0000\" stroke-opacity=\"0.5019608\"")
+            .count(),
+        3
+    );
+    assert_eq!(
+        svg.matches("fill=\"#ff0000\" fill-opacity=\"0.5019608\"")
+            .count(),
+        12
+    );
+}
+
+#[test]
+fn test_export_svg_single_block_view() {
+    let settings = ISettings {
+        view: "Block=>L-2".to_string(),
+        ..Default::default()
+    };
+    let h = load_example_editor(&settings);
+
+    let svg = h.export_svg();
+
+    assert_eq!(svg.matches("<g id=").count(), 1);
+    assert!(svg.contains("<g id=\"L-2\" transform=\"translate(0 0)\">"));
+}